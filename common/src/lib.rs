@@ -0,0 +1,3 @@
+// Helpers shared by the resource and the factory contracts.
+
+pub mod pagination;
@@ -0,0 +1,48 @@
+// Cursor based pagination shared by the resource and the factory. A cursor is the
+// hex encoded borsh key of the last item of the previous page, so pages stay
+// stable while entries are added or removed in between.
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+pub struct Page<T> {
+  pub items: Vec<T>,
+  /// pass to the next call, `None` on the last page
+  pub next_cursor: Option<String>,
+}
+
+pub fn encode_cursor<K: BorshSerialize>(key: &K) -> String {
+  key.try_to_vec().unwrap().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn decode_cursor<K: BorshDeserialize>(cursor: &str) -> K {
+  assert!(cursor.len().is_multiple_of(2) && cursor.is_ascii(), "invalid cursor");
+  let bytes: Vec<u8> = (0..cursor.len())
+    .step_by(2)
+    .map(|index| u8::from_str_radix(&cursor[index..index + 2], 16).expect("invalid cursor"))
+    .collect();
+  K::try_from_slice(&bytes).expect("invalid cursor")
+}
+
+/// Collects up to `limit` entries of a key ordered iterator that already starts
+/// after the cursor. Each entry is `(key, item)`. An empty page without cursor
+/// means the end, so `limit` has to be at least 1.
+pub fn paginate<K: BorshSerialize, T>(entries: impl Iterator<Item = (K, T)>, limit: usize) -> Page<T> {
+  assert!(limit > 0, "limit must be at least 1");
+  let mut entries = entries.peekable();
+  let mut items = Vec::new();
+  let mut last_key = None;
+  while items.len() < limit {
+    let Some((key, item)) = entries.next() else {
+      break;
+    };
+    items.push(item);
+    last_key = Some(key);
+  }
+  let next_cursor = match (entries.peek(), last_key) {
+    (Some(_), Some(key)) => Some(encode_cursor(&key)),
+    _ => None,
+  };
+  Page { items, next_cursor }
+}
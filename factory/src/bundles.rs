@@ -0,0 +1,139 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, Gas, Promise, PromiseResult};
+use serde::{Deserialize, Serialize};
+
+use chershare_resource::InvoiceLine;
+
+use crate::{tgas, ChershareResourceFactory, ChershareResourceFactoryExt};
+
+/// Bounded so collecting all lines fits into one call.
+const MAX_BUNDLE_ITEMS: usize = 10;
+const GET_INVOICE_LINE_GAS: Gas = tgas(5);
+const IS_BOOKER_GAS: Gas = tgas(5);
+
+/// Bookings on several resources that belong together, e.g. a multi-room event
+/// or everything an organisation booked for a project.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Bundle {
+  pub owner: String,
+  /// `(resource name, booking id)`
+  pub items: Vec<(String, U128)>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ConsolidatedInvoiceLine {
+  pub resource: String,
+  /// `None` if the resource didn't know the booking
+  pub line: Option<InvoiceLine>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ConsolidatedInvoice {
+  pub bundle_id: u64,
+  pub lines: Vec<ConsolidatedInvoiceLine>,
+  pub total_price: U128,
+  pub total_refunded: U128,
+  pub total_due: U128,
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Bundles bookings the caller made. Their resources confirm that first, the
+  /// promise resolves to the bundle id.
+  pub fn create_bundle(&mut self, items: Vec<(String, U128)>) -> Promise {
+    assert!(!items.is_empty() && items.len() <= MAX_BUNDLE_ITEMS, "a bundle has 1 to {} items", MAX_BUNDLE_ITEMS);
+    for (name, _) in items.iter() {
+      assert!(self.check_resource_contained(name), "resource is not registered");
+    }
+    let owner = env::predecessor_account_id().to_string();
+    let checks = items.iter()
+      .map(|(name, booking_id)| {
+        Promise::new(self.resource_account_id(name)).function_call(
+          "is_booker".to_string(),
+          serde_json::json!({ "booking_id": booking_id, "account_id": owner }).to_string().into_bytes(),
+          0,
+          IS_BOOKER_GAS,
+        )
+      })
+      .reduce(|joint, check| joint.and(check))
+      .unwrap();
+    checks.then(
+      Self::ext(env::current_account_id())
+        .with_static_gas(tgas(5))
+        .create_bundle_callback(owner, items)
+    )
+  }
+
+  #[private]
+  pub fn create_bundle_callback(&mut self, owner: String, items: Vec<(String, U128)>) -> u64 {
+    for (index, (name, booking_id)) in items.iter().enumerate() {
+      let is_booker = match env::promise_result(index as u64) {
+        PromiseResult::Successful(value) => serde_json::from_slice::<bool>(&value).unwrap_or(false),
+        _ => false,
+      };
+      assert!(is_booker, "booking {} on {} is not the caller's", booking_id.0, name);
+    }
+    let id = self.next_bundle_id;
+    self.next_bundle_id += 1;
+    let bundle = Bundle { owner, items };
+    self.bundles.insert(&id, &bundle);
+    self.emit("BundleCreation", serde_json::json!({
+      "id": id,
+      "bundle": bundle,
+    }));
+    id
+  }
+
+  pub fn get_bundle(&self, bundle_id: u64) -> Option<Bundle> {
+    self.bundles.get(&bundle_id)
+  }
+
+  /// Collects the invoice lines of all bookings in a bundle from their resources.
+  /// The promise resolves to a `ConsolidatedInvoice`. Has to be called as a
+  /// transaction, view calls can't reach other contracts.
+  pub fn get_consolidated_invoice(&self, bundle_id: u64) -> Promise {
+    let bundle = self.bundles.get(&bundle_id).expect("bundle not found");
+    let lines = bundle.items.iter()
+      .map(|(name, booking_id)| {
+        Promise::new(self.resource_account_id(name)).function_call(
+          "get_invoice_line".to_string(),
+          serde_json::json!({ "booking_id": booking_id }).to_string().into_bytes(),
+          0,
+          GET_INVOICE_LINE_GAS,
+        )
+      })
+      .reduce(|joint, line| joint.and(line))
+      .unwrap();
+    lines.then(
+      Self::ext(env::current_account_id())
+        .with_static_gas(tgas(5))
+        .consolidated_invoice_callback(bundle_id)
+    )
+  }
+
+  #[private]
+  pub fn consolidated_invoice_callback(&self, bundle_id: u64) -> ConsolidatedInvoice {
+    let bundle = self.bundles.get(&bundle_id).unwrap();
+    let lines: Vec<ConsolidatedInvoiceLine> = bundle.items.into_iter()
+      .enumerate()
+      .map(|(index, (resource, _))| {
+        let line = match env::promise_result(index as u64) {
+          PromiseResult::Successful(value) => serde_json::from_slice::<Option<InvoiceLine>>(&value).ok().flatten(),
+          _ => None,
+        };
+        ConsolidatedInvoiceLine { resource, line }
+      })
+      .collect();
+    let sum = |field: fn(&InvoiceLine) -> u128| -> U128 {
+      U128::from(lines.iter().filter_map(|line| line.line.as_ref()).map(field).sum::<u128>())
+    };
+    ConsolidatedInvoice {
+      bundle_id,
+      total_price: sum(|line| line.price.0),
+      total_refunded: sum(|line| line.refunded.0),
+      total_due: sum(|line| line.amount_due.0),
+      lines,
+    }
+  }
+}
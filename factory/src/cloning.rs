@@ -0,0 +1,103 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+
+use chershare_resource::{PricingParams, ResourceInitParams};
+
+use crate::{tgas, ChershareResourceFactory, ChershareResourceFactoryExt, CREATE_RESOURCE_GAS};
+
+const GET_INIT_PARAMS_GAS: Gas = tgas(5);
+/// deploying the clone from the callback needs the gas of a whole `create_resource`
+const CLONE_CALLBACK_GAS: Gas = tgas(CREATE_RESOURCE_GAS.0 / 10u64.pow(12) + 10 + 5);
+
+/// Fields of the source resource to replace in the clone.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct CloneOverrides {
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub image_urls: Option<Vec<String>>,
+  pub contact: Option<String>,
+  pub tags: Option<Vec<String>>,
+  pub pricing: Option<PricingParams>,
+  pub coordinates: Option<[f32; 2]>,
+}
+
+impl CloneOverrides {
+  fn apply(self, init_params: ResourceInitParams) -> ResourceInitParams {
+    ResourceInitParams {
+      title: self.title.unwrap_or(init_params.title),
+      description: self.description.unwrap_or(init_params.description),
+      image_urls: self.image_urls.unwrap_or(init_params.image_urls),
+      contact: self.contact.unwrap_or(init_params.contact),
+      tags: self.tags.unwrap_or(init_params.tags),
+      pricing: self.pricing.unwrap_or(init_params.pricing),
+      coordinates: self.coordinates.unwrap_or(init_params.coordinates),
+      ..init_params
+    }
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Creates `new_name` with the parameters of the caller's resource `source_name`.
+  /// The clone belongs to the same tenant, the attached deposit is used like in
+  /// `create_resource`.
+  #[payable]
+  pub fn clone_resource(
+    &mut self,
+    source_name: String,
+    new_name: String,
+    overrides: Option<CloneOverrides>,
+  ) -> Promise {
+    let source = self.resources.get(&source_name).expect("resource is not registered");
+    assert_eq!(
+      env::signer_account_id().to_string(),
+      source.owner,
+      "only the owner can clone a resource"
+    );
+    self.assert_name_available(&new_name);
+    let creation_fee = self.creation_fee(&source.tenant);
+    assert!(env::attached_deposit() >= creation_fee, "deposit does not cover the creation fee");
+
+    Promise::new(self.resource_account_id(&source_name))
+      .function_call("get_init_params".to_string(), vec![], 0, GET_INIT_PARAMS_GAS)
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(CLONE_CALLBACK_GAS)
+          .clone_resource_callback(
+            new_name,
+            source.owner,
+            overrides.unwrap_or_default(),
+            source.tenant,
+            U128(env::attached_deposit()),
+          )
+      )
+  }
+
+  #[private]
+  pub fn clone_resource_callback(
+    &mut self,
+    name: String,
+    owner: String,
+    overrides: CloneOverrides,
+    tenant: Option<String>,
+    deposit: U128,
+    #[callback_result] call_result: Result<ResourceInitParams, PromiseError>,
+  ) {
+    // the name may have been taken or the fee raised while reading the source
+    let name_available = !self.check_resource_contained(&name)
+      && !self.locked_names.contains(&name)
+      && self.aliases.get(&name).is_none();
+    let creation_fee = self.creation_fee(&tenant);
+    match call_result {
+      Ok(init_params) if name_available && deposit.0 >= creation_fee => {
+        self.deploy_resource(name, owner, overrides.apply(init_params), tenant, deposit.0, creation_fee);
+      },
+      _ => {
+        if deposit.0 > 0 {
+          Promise::new(AccountId::try_from(owner).unwrap()).transfer(deposit.0);
+        }
+      },
+    }
+  }
+}
@@ -0,0 +1,27 @@
+use serde::Serialize;
+use near_sdk::{env, near_bindgen};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+impl ChershareResourceFactory {
+  /// Logs `Event: {json}` with the next `event_seq` added to the data. Data that
+  /// isn't an object is wrapped as `{"value": data}`. Indexers can spot missed
+  /// receipts by gaps in the sequence.
+  pub(crate) fn emit(&mut self, event: &str, data: impl Serialize) {
+    let mut value = serde_json::to_value(data).unwrap();
+    if !value.is_object() {
+      value = serde_json::json!({ "value": value });
+    }
+    value["event_seq"] = self.event_seq.into();
+    self.event_seq += 1;
+    env::log_str(&format!("{}: {}", event, value));
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Number of events emitted so far, i.e. the `event_seq` of the next one.
+  pub fn get_event_seq(&self) -> u64 {
+    self.event_seq
+  }
+}
@@ -0,0 +1,159 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+/// Fee changes only apply after this delay so resource owners can react.
+const FEE_CHANGE_TIMELOCK_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const MAX_FEE_BPS: u16 = 10_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub enum FeeChange {
+  SetDefault { bps: u16 },
+  /// `None` removes the category override
+  SetCategory { category: String, bps: Option<u16> },
+  /// `Some(0)` exempts a resource, `None` removes the override
+  SetResource { name: String, bps: Option<u16> },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct PendingFeeChange {
+  pub change: FeeChange,
+  pub executable_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct FeeChangeLog {
+  id: u64,
+  change: FeeChange,
+  executable_at: u64,
+}
+
+impl ChershareResourceFactory {
+  pub(crate) fn assert_governance(&self) {
+    assert_eq!(
+      env::predecessor_account_id(),
+      self.governance,
+      "only governance can do this"
+    );
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Fee in basis points the resource `name` owes the platform on payouts.
+  /// Resource overrides win over category overrides, which win over the default.
+  pub fn get_fee_bps(&self, name: String) -> u16 {
+    if let Some(bps) = self.fee_resource_bps.get(&name) {
+      return bps;
+    }
+    self.resources.get(&name)
+      .and_then(|record| record.category)
+      .and_then(|category| self.fee_category_bps.get(&category))
+      .unwrap_or(self.fee_default_bps)
+  }
+
+  pub fn get_fee_default_bps(&self) -> u16 {
+    self.fee_default_bps
+  }
+
+  pub fn get_fee_category_overrides(&self) -> Vec<(String, u16)> {
+    self.fee_category_bps.to_vec()
+  }
+
+  pub fn get_pending_fee_changes(&self) -> Vec<(u64, PendingFeeChange)> {
+    self.pending_fee_changes.to_vec()
+  }
+
+  pub fn propose_fee_change(&mut self, change: FeeChange) -> u64 {
+    self.assert_governance();
+    let bps = match &change {
+      FeeChange::SetDefault { bps } => Some(*bps),
+      FeeChange::SetCategory { bps, .. } | FeeChange::SetResource { bps, .. } => *bps,
+    };
+    assert!(bps.unwrap_or(0) <= MAX_FEE_BPS, "fee above 100%");
+    let id = self.next_fee_change_id;
+    self.next_fee_change_id += 1;
+    let executable_at = env::block_timestamp() / 1_000_000 + FEE_CHANGE_TIMELOCK_MS;
+    self.pending_fee_changes.insert(&id, &PendingFeeChange {
+      change: change.clone(),
+      executable_at,
+    });
+    self.emit("FeeChangeProposal", &FeeChangeLog {
+      id,
+      change,
+      executable_at,
+    });
+    id
+  }
+
+  pub fn cancel_fee_change(&mut self, id: u64) {
+    self.assert_governance();
+    self.pending_fee_changes.remove(&id).expect("fee change not found");
+    self.emit("FeeChangeCancellation", id);
+  }
+
+  /// Anyone can apply a proposed change once its timelock has passed.
+  pub fn execute_fee_change(&mut self, id: u64) {
+    let pending = self.pending_fee_changes.get(&id).expect("fee change not found");
+    assert!(
+      env::block_timestamp() / 1_000_000 >= pending.executable_at,
+      "fee change is still timelocked"
+    );
+    self.pending_fee_changes.remove(&id);
+    match pending.change.clone() {
+      FeeChange::SetDefault { bps } => self.fee_default_bps = bps,
+      FeeChange::SetCategory { category, bps: Some(bps) } => {
+        self.fee_category_bps.insert(&category, &bps);
+      },
+      FeeChange::SetCategory { category, bps: None } => {
+        self.fee_category_bps.remove(&category);
+      },
+      FeeChange::SetResource { name, bps: Some(bps) } => {
+        self.fee_resource_bps.insert(&name, &bps);
+      },
+      FeeChange::SetResource { name, bps: None } => {
+        self.fee_resource_bps.remove(&name);
+      },
+    }
+    self.emit("FeeChangeExecution", &FeeChangeLog {
+      id,
+      change: pending.change,
+      executable_at: pending.executable_at,
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::{context, factory, register};
+
+  #[test]
+  #[should_panic(expected = "fee change is still timelocked")]
+  fn fee_change_waits_for_the_timelock() {
+    let mut factory = factory();
+    testing_env!(context("governance.near").build());
+    let id = factory.propose_fee_change(FeeChange::SetDefault { bps: 300 });
+    testing_env!(context("alice.near").block_timestamp((FEE_CHANGE_TIMELOCK_MS - 1) * 1_000_000).build());
+    factory.execute_fee_change(id);
+  }
+
+  #[test]
+  fn anyone_applies_a_fee_change_after_the_timelock() {
+    let mut factory = factory();
+    register(&mut factory, "bike", "city bike");
+    testing_env!(context("governance.near").build());
+    let default_bps = factory.get_fee_bps("bike".to_string());
+    let id = factory.propose_fee_change(FeeChange::SetResource { name: "bike".to_string(), bps: Some(0) });
+    assert_eq!(factory.get_fee_bps("bike".to_string()), default_bps);
+
+    testing_env!(context("alice.near").block_timestamp(FEE_CHANGE_TIMELOCK_MS * 1_000_000).build());
+    factory.execute_fee_change(id);
+    assert_eq!(factory.get_fee_bps("bike".to_string()), 0);
+    assert!(factory.get_pending_fee_changes().is_empty());
+  }
+}
@@ -0,0 +1,66 @@
+use near_sdk::near_bindgen;
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+/// Resources are bucketed into cells of one degree latitude by one degree longitude.
+const MAX_BBOX_CELLS: i32 = 2_500;
+const MAX_GEO_PAGE_SIZE: u64 = 100;
+
+/// `[lat, lon]` to the grid cell containing it.
+fn geo_cell(coordinates: [f32; 2]) -> (i16, i16) {
+  (coordinates[0].floor() as i16, coordinates[1].floor() as i16)
+}
+
+impl ChershareResourceFactory {
+  pub(crate) fn geo_index_add(&mut self, name: &str, coordinates: [f32; 2]) {
+    let cell = geo_cell(coordinates);
+    let mut names = self.geo_index.get(&cell).unwrap_or_default();
+    names.push(name.to_string());
+    self.geo_index.insert(&cell, &names);
+  }
+
+  pub(crate) fn geo_index_remove(&mut self, name: &String, coordinates: [f32; 2]) {
+    let cell = geo_cell(coordinates);
+    if let Some(mut names) = self.geo_index.get(&cell) {
+      names.retain(|indexed| indexed != name);
+      self.geo_index.insert(&cell, &names);
+    }
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Names of the resources located inside the bounding box. Meant for map views
+  /// while the indexer is unavailable, so the box may span at most 2500 cells.
+  pub fn find_in_bbox(
+    &self,
+    min_lat: f32,
+    min_lon: f32,
+    max_lat: f32,
+    max_lon: f32,
+    from_index: u64,
+    limit: u64,
+  ) -> Vec<String> {
+    assert!(min_lat <= max_lat && min_lon <= max_lon, "invalid bounding box");
+    let (min_cell_lat, min_cell_lon) = geo_cell([min_lat, min_lon]);
+    let (max_cell_lat, max_cell_lon) = geo_cell([max_lat, max_lon]);
+    assert!(
+      (max_cell_lat as i32 - min_cell_lat as i32 + 1) * (max_cell_lon as i32 - min_cell_lon as i32 + 1) <= MAX_BBOX_CELLS,
+      "bounding box too large"
+    );
+    (min_cell_lat..=max_cell_lat)
+      .flat_map(|lat| (min_cell_lon..=max_cell_lon).map(move |lon| (lat, lon)))
+      .filter_map(|cell| self.geo_index.get(&cell))
+      .flatten()
+      .filter(|name| {
+        self.resources.get(name).is_some_and(|record| {
+          let [lat, lon] = record.coordinates;
+          record.is_listed() &&
+          lat >= min_lat && lat <= max_lat && lon >= min_lon && lon <= max_lon
+        })
+      })
+      .skip(from_index as usize)
+      .take(limit.min(MAX_GEO_PAGE_SIZE) as usize)
+      .collect()
+  }
+}
@@ -0,0 +1,35 @@
+use near_sdk::{near_bindgen, AccountId};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+/// Keeps a single propagation call within gas.
+const MAX_PROPAGATION_BATCH: usize = 20;
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Resources created from now on register with `indexer_account`, existing ones
+  /// learn about it through `propagate_indexer_account`.
+  pub fn set_indexer_account(&mut self, indexer_account: Option<AccountId>) {
+    self.assert_governance();
+    self.indexer_account = indexer_account.clone();
+    self.emit("IndexerChange", serde_json::json!({ "indexer_account": indexer_account }));
+  }
+
+  pub fn get_indexer_account(&self) -> Option<AccountId> {
+    self.indexer_account.clone()
+  }
+
+  /// Passes the current indexer on to already deployed resources.
+  pub fn propagate_indexer_account(&mut self, names: Vec<String>) {
+    self.assert_governance();
+    assert!(names.len() <= MAX_PROPAGATION_BATCH, "too many resources in one batch");
+    for name in names.iter() {
+      assert!(self.check_resource_contained(name), "resource is not registered");
+      self.call_resource(
+        name,
+        "set_indexer_account",
+        serde_json::json!({ "indexer_account": self.indexer_account }),
+      );
+    }
+  }
+}
@@ -0,0 +1,44 @@
+use near_sdk::{env, near_bindgen, Promise, PromiseError, PublicKey};
+
+use crate::{tgas, ChershareResourceFactory, ChershareResourceFactoryExt};
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Replaces the full access key on the resource account, `None` removes it and
+  /// leaves the resource to its in-contract permissions. Only the owner can ask
+  /// for this, the new key shows up as `full_access_key` in the registry.
+  pub fn rotate_resource_key(&mut self, name: String, new_key: Option<PublicKey>) -> Promise {
+    let record = self.resources.get(&name).expect("resource is not registered");
+    assert_eq!(env::predecessor_account_id().to_string(), record.owner, "only the owner can do this");
+    let old_key: PublicKey = record.full_access_key
+      .expect("resource has no full access key")
+      .parse()
+      .unwrap();
+    self.call_resource(
+      &name,
+      "replace_access_key",
+      serde_json::json!({ "old_key": old_key, "new_key": new_key }),
+    ).then(
+      Self::ext(env::current_account_id())
+        .with_static_gas(tgas(5))
+        .on_resource_key_rotated(name, new_key)
+    )
+  }
+
+  #[private]
+  pub fn on_resource_key_rotated(
+    &mut self,
+    name: String,
+    new_key: Option<PublicKey>,
+    #[callback_result] call_result: Result<(), PromiseError>,
+  ) {
+    assert!(call_result.is_ok(), "key rotation failed");
+    let Some(mut record) = self.resources.get(&name) else { return };
+    record.full_access_key = new_key.as_ref().map(String::from);
+    self.resources.insert(&name, &record);
+    self.emit("ResourceKeyRotated", serde_json::json!({
+      "name": name,
+      "full_access_key": record.full_access_key,
+    }));
+  }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeSet;
+
+use near_sdk::near_bindgen;
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+const MIN_TOKEN_LENGTH: usize = 2;
+const MAX_TOKEN_LENGTH: usize = 32;
+const MAX_TOKENS_PER_RESOURCE: usize = 32;
+/// A word this common doesn't narrow a search down anyway, later resources
+/// aren't indexed under it.
+const MAX_RESOURCES_PER_TOKEN: usize = 1_000;
+const MAX_KEYWORD_PAGE_SIZE: u64 = 100;
+
+/// Lowercase alphanumeric words of the text, deduplicated. Longer words are
+/// dropped, they are rarely searched for.
+fn tokenize<'a>(texts: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+  texts.into_iter()
+    .flat_map(|text| text.split(|c: char| !c.is_alphanumeric()))
+    .filter(|token| (MIN_TOKEN_LENGTH..=MAX_TOKEN_LENGTH).contains(&token.chars().count()))
+    .map(|token| token.to_lowercase())
+    .collect()
+}
+
+impl ChershareResourceFactory {
+  /// (Re)indexes the title and tags of a resource, replacing its previous tokens.
+  pub(crate) fn index_keywords(&mut self, name: &String, title: &str, tags: &[String]) {
+    for token in self.resource_keywords.get(name).unwrap_or_default() {
+      if let Some(mut names) = self.keyword_index.get(&token) {
+        names.retain(|indexed| indexed != name);
+        if names.is_empty() {
+          self.keyword_index.remove(&token);
+        } else {
+          self.keyword_index.insert(&token, &names);
+        }
+      }
+    }
+    let mut tokens = Vec::new();
+    for token in tokenize(std::iter::once(title).chain(tags.iter().map(String::as_str))) {
+      if tokens.len() == MAX_TOKENS_PER_RESOURCE {
+        break;
+      }
+      let mut names = self.keyword_index.get(&token).unwrap_or_default();
+      if names.len() == MAX_RESOURCES_PER_TOKEN {
+        continue;
+      }
+      names.push(name.clone());
+      self.keyword_index.insert(&token, &names);
+      tokens.push(token);
+    }
+    self.resource_keywords.insert(name, &tokens);
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Names of the resources whose title or tags contain every word of `query`.
+  pub fn search(&self, query: String, from_index: u64, limit: u64) -> Vec<String> {
+    let mut token_matches = tokenize([query.as_str()])
+      .into_iter()
+      .map(|token| self.keyword_index.get(&token).unwrap_or_default());
+    let first = match token_matches.next() {
+      Some(names) => names,
+      None => return vec![],
+    };
+    let rest: Vec<Vec<String>> = token_matches.collect();
+    first.into_iter()
+      .filter(|name| rest.iter().all(|names| names.contains(name)))
+      .filter(|name| self.is_listed(name))
+      .skip(from_index as usize)
+      .take(limit.min(MAX_KEYWORD_PAGE_SIZE) as usize)
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tests::{factory, register};
+
+  #[test]
+  fn long_and_short_words_are_not_indexed() {
+    let longest = "b".repeat(MAX_TOKEN_LENGTH);
+    let too_long = "c".repeat(MAX_TOKEN_LENGTH + 1);
+    let tokens = tokenize(["a Cargo-bike", longest.as_str(), too_long.as_str()]);
+    assert_eq!(tokens, BTreeSet::from(["bike".to_string(), "cargo".to_string(), longest]));
+  }
+
+  #[test]
+  fn a_resource_gets_at_most_32_tokens() {
+    let mut factory = factory();
+    register(&mut factory, "bike", "city bike");
+    let tags: Vec<String> = (0..40).map(|index| format!("tag{}", index)).collect();
+    factory.index_keywords(&"bike".to_string(), "city bike", &tags);
+    assert_eq!(factory.resource_keywords.get(&"bike".to_string()).unwrap().len(), MAX_TOKENS_PER_RESOURCE);
+  }
+
+  #[test]
+  fn common_words_stop_indexing_new_resources() {
+    let mut factory = factory();
+    let crowded: Vec<String> = (0..MAX_RESOURCES_PER_TOKEN).map(|index| format!("bike{}", index)).collect();
+    factory.keyword_index.insert(&"cargo".to_string(), &crowded);
+    register(&mut factory, "bike", "city bike");
+    assert_eq!(factory.keyword_index.get(&"cargo".to_string()).unwrap().len(), MAX_RESOURCES_PER_TOKEN);
+    assert_eq!(factory.resource_keywords.get(&"bike".to_string()).unwrap(), vec!["bike", "city"]);
+    assert_eq!(factory.search("city bike".to_string(), 0, 10), vec!["bike"]);
+  }
+}
@@ -1,3 +1,4 @@
+use std::ops::Bound;
 use std::str::FromStr;
 
 use near_sdk::borsh::{
@@ -5,41 +6,252 @@ use near_sdk::borsh::{
   BorshDeserialize,
   BorshSerialize,
 };
-use near_sdk::collections::LookupSet;
+use near_sdk::collections::{
+  LookupMap,
+  LookupSet,
+  TreeMap,
+  UnorderedMap,
+  Vector,
+};
+use near_sdk::json_types::U128;
 use near_sdk::{
   self,
   env,
   near_bindgen,
   AccountId,
+  BorshStorageKey,
   Gas,
+  PanicOnDefault,
   Promise, 
   PromiseError, 
 };
 
-use chershare_resource::ResourceInitParams;
+use chershare_common::pagination::{decode_cursor, paginate, Page};
+use chershare_resource::{
+  ActiveWindow,
+  Pricing,
+  ResourceInitParams,
+  ResourceStatus,
+  ResourceSummary,
+  Visibility,
+};
 use serde::{Deserialize, Serialize};
 
+mod bundles;
+mod cloning;
+mod events;
+mod fees;
+mod geo;
+mod indexer;
+mod keys;
+mod keywords;
+mod migration;
+mod moderation;
+mod names;
+mod operators;
+mod presets;
+mod rollouts;
+mod search;
+mod tenants;
+mod upgrades;
+
+pub use bundles::{Bundle, ConsolidatedInvoice, ConsolidatedInvoiceLine};
+pub use cloning::CloneOverrides;
+pub use fees::{FeeChange, PendingFeeChange};
+pub use migration::LegacyResource;
+pub use moderation::Delisting;
+pub use operators::{OperatorProfile, ResourceListing};
+pub use presets::ConfigPreset;
+pub use rollouts::{Rollout, RolloutStage};
+pub use tenants::Tenant;
+pub use upgrades::{CodeVersion, DryRunOutcome};
+
+use upgrades::SHADOW_PREFIX;
+
 // Constants
 
-const fn tgas(n: u64) -> Gas {
+pub(crate) const fn tgas(n: u64) -> Gas {
   Gas(n * 10u64.pow(12))
 }
-const CREATE_RESOURCE_GAS: Gas = tgas(65 + 5);
+pub(crate) const CREATE_RESOURCE_GAS: Gas = tgas(65 + 5);
+/// geo, keyword and price index entries of a new resource
+const INDEX_RESOURCE_GAS: Gas = tgas(50);
+/// registering a new resource, its indexing is a call of its own
+const CREATE_CALLBACK_GAS: Gas = tgas(15 + INDEX_RESOURCE_GAS.0 / 10u64.pow(12));
 // const STORAGE_PRICE_PER_BYTE: u128 = 10_u128.pow(19); 
 
+const MAX_EXPORT_PAGE_SIZE: u64 = 200;
+
+/// Code every resource is created with.
+pub(crate) const RESOURCE_WASM: &[u8] =
+  include_bytes!("../../target/wasm32-unknown-unknown/release/chershare_resource.wasm");
+
+/// What the factory remembers about each resource it has produced.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct ResourceRecord {
+  pub owner: String,
+  pub created_at: u64,
+  pub category: Option<String>,
+  pub status: ResourceStatus,
+  /// only `Public` resources show up in enumerations and search
+  pub visibility: Visibility,
+  /// seasonal resources are only listed while their window is open
+  pub active_window: ActiveWindow,
+  /// price of a one hour booking, kept up to date by the resource
+  pub base_price: U128,
+  /// `[lat, lon]`
+  pub coordinates: [f32; 2],
+  /// how quickly the owner answers booking requests, for ranking responsive hosts
+  pub avg_response_ms: Option<u64>,
+  pub unanswered_requests: u64,
+  pub reliability_bps: Option<u16>,
+  /// partner marketplace the resource was created through
+  pub tenant: Option<String>,
+  /// key placed on the resource account at creation, `None` once removed
+  pub full_access_key: Option<String>,
+}
+
+impl ResourceRecord {
+  /// Whether the resource may show up in registry views at all.
+  pub fn is_public(&self) -> bool {
+    self.visibility == Visibility::Public
+      && self.status != ResourceStatus::Draft
+      && self.active_window.is_open(env::block_timestamp() / 1_000_000)
+  }
+
+  /// Whether the resource shows up in the platform wide registry and search,
+  /// tenant resources are only listed in their tenant's registry.
+  pub fn is_listed(&self) -> bool {
+    self.is_public() && self.tenant.is_none()
+  }
+}
+
+/// Prefixes of all persistent collections. New collections get a new variant at
+/// the end, never reorder existing ones. The first version kept its resource
+/// names under `b"t"`, which no variant can collide with, see `migrate`.
+#[derive(BorshSerialize, BorshStorageKey)]
+pub(crate) enum FactoryStorageKey {
+  // replaced by `ResourceRecords`, an `UnorderedMap` whose pages shifted on removals
+  #[allow(dead_code)]
+  Resources,
+  FeeCategoryBps,
+  FeeResourceBps,
+  PendingFeeChanges,
+  PriceIndex,
+  GeoIndex,
+  KeywordIndex,
+  ResourceKeywords,
+  Delistings,
+  LockedNames,
+  OwnerResources,
+  Tenants,
+  TenantResources,
+  TenantHidden,
+  Bundles,
+  ResourceRecords,
+  Aliases,
+  ResourceAliases,
+  ConfigPresets,
+  CodeVersions,
+  Code,
+  DryRuns,
+  Rollouts,
+  ResourceCodeVersions,
+  OperatorProfiles,
+}
+
 #[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct ChershareResourceFactory {
   /// The `Resources`s this `Factory` has produced.
-  pub resources: LookupSet<String>,
+  pub resources: TreeMap<String, ResourceRecord>,
   pub test_msg: String, 
+  /// Account allowed to change platform wide settings like fees.
+  pub governance: AccountId,
+  pub fee_default_bps: u16,
+  pub fee_category_bps: UnorderedMap<String, u16>,
+  /// Per resource fee, an exemption is an override of 0.
+  pub fee_resource_bps: LookupMap<String, u16>,
+  pub pending_fee_changes: UnorderedMap<u64, PendingFeeChange>,
+  pub next_fee_change_id: u64,
+  /// `(base_price, name)` of every resource, for range queries by price
+  pub price_index: TreeMap<(u128, String), ()>,
+  /// names of the resources in each one degree grid cell
+  pub geo_index: LookupMap<(i16, i16), Vec<String>>,
+  /// lowercase title/tag token -> names of up to 1000 resources containing it
+  pub keyword_index: LookupMap<String, Vec<String>>,
+  /// the tokens each resource is currently indexed under
+  pub resource_keywords: LookupMap<String, Vec<String>>,
+  pub delistings: UnorderedMap<String, Delisting>,
+  /// names of delisted resources, they can never be registered again
+  pub locked_names: LookupSet<String>,
+  /// names of the resources created by each owner
+  pub owner_resources: LookupMap<String, Vec<String>>,
+  pub tenants: UnorderedMap<String, Tenant>,
+  /// names of the resources created through each tenant
+  pub tenant_resources: LookupMap<String, Vec<String>>,
+  /// tenant resources hidden from their tenant registry by its curators
+  pub tenant_hidden: LookupSet<String>,
+  pub bundles: LookupMap<u64, Bundle>,
+  pub next_bundle_id: u64,
+  /// sequence number of the next emitted event
+  pub event_seq: u64,
+  /// alias -> name of the resource it resolves to
+  pub aliases: LookupMap<String, String>,
+  pub resource_aliases: LookupMap<String, Vec<String>>,
+  /// on-chain aggregator children register themselves with
+  pub indexer_account: Option<AccountId>,
+  /// every version of the platform defaults, the last one is current
+  pub config_presets: Vector<ConfigPreset>,
+  /// operator profile of each creator that registered one
+  pub operator_profiles: LookupMap<String, OperatorProfile>,
+  /// resource versions published for upgrades
+  pub code_versions: Vector<CodeVersion>,
+  /// wasm of each published version
+  pub code: LookupMap<u64, Vec<u8>>,
+  /// latest rehearsal of each `(name, version)`, see `dry_run_upgrade`
+  pub dry_runs: LookupMap<(String, u64), DryRunOutcome>,
+  pub rollouts: LookupMap<u64, Rollout>,
+  /// version each upgraded resource runs, the others run `RESOURCE_WASM`
+  pub resource_code_versions: LookupMap<String, u64>,
 }
 
-impl Default for ChershareResourceFactory {
-  fn default() -> ChershareResourceFactory {
-    ChershareResourceFactory {
-      resources: LookupSet::new(b"t".to_vec()),
+#[near_bindgen]
+impl ChershareResourceFactory {
+  #[init]
+  pub fn new(governance: AccountId) -> Self {
+    Self {
+      resources: TreeMap::new(FactoryStorageKey::ResourceRecords),
       test_msg: "hi!".into(), 
+      governance,
+      fee_default_bps: 0,
+      fee_category_bps: UnorderedMap::new(FactoryStorageKey::FeeCategoryBps),
+      fee_resource_bps: LookupMap::new(FactoryStorageKey::FeeResourceBps),
+      pending_fee_changes: UnorderedMap::new(FactoryStorageKey::PendingFeeChanges),
+      next_fee_change_id: 0,
+      price_index: TreeMap::new(FactoryStorageKey::PriceIndex),
+      geo_index: LookupMap::new(FactoryStorageKey::GeoIndex),
+      keyword_index: LookupMap::new(FactoryStorageKey::KeywordIndex),
+      resource_keywords: LookupMap::new(FactoryStorageKey::ResourceKeywords),
+      delistings: UnorderedMap::new(FactoryStorageKey::Delistings),
+      locked_names: LookupSet::new(FactoryStorageKey::LockedNames),
+      owner_resources: LookupMap::new(FactoryStorageKey::OwnerResources),
+      tenants: UnorderedMap::new(FactoryStorageKey::Tenants),
+      tenant_resources: LookupMap::new(FactoryStorageKey::TenantResources),
+      tenant_hidden: LookupSet::new(FactoryStorageKey::TenantHidden),
+      bundles: LookupMap::new(FactoryStorageKey::Bundles),
+      next_bundle_id: 0,
+      event_seq: 0,
+      aliases: LookupMap::new(FactoryStorageKey::Aliases),
+      resource_aliases: LookupMap::new(FactoryStorageKey::ResourceAliases),
+      indexer_account: None,
+      config_presets: Vector::new(FactoryStorageKey::ConfigPresets),
+      operator_profiles: LookupMap::new(FactoryStorageKey::OperatorProfiles),
+      code_versions: Vector::new(FactoryStorageKey::CodeVersions),
+      code: LookupMap::new(FactoryStorageKey::Code),
+      dry_runs: LookupMap::new(FactoryStorageKey::DryRuns),
+      rollouts: LookupMap::new(FactoryStorageKey::Rollouts),
+      resource_code_versions: LookupMap::new(FactoryStorageKey::ResourceCodeVersions),
     }
   }
 }
@@ -47,7 +259,8 @@ impl Default for ChershareResourceFactory {
 #[derive(Deserialize, Serialize)]
 struct ResourceInitParamsCallWrapper {
   owner: String, 
-  init_params: ResourceInitParams
+  init_params: ResourceInitParams,
+  indexer_account: Option<AccountId>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -57,13 +270,82 @@ struct ResourceCreationLog {
   init_params: ResourceInitParams, 
 }
 
+impl ChershareResourceFactory {
+  /// Name of the registered resource calling the factory, i.e. the predecessor
+  /// has to be `<name>.<factory>`.
+  pub(crate) fn assert_registered_caller(&self) -> String {
+    let caller = env::predecessor_account_id().to_string();
+    let name = caller
+      .strip_suffix(&format!(".{}", env::current_account_id()))
+      .expect("only resources of this factory can do this")
+      .to_string();
+    assert!(self.check_resource_contained(&name), "resource is not registered");
+    name
+  }
+
+  pub(crate) fn is_listed(&self, name: &String) -> bool {
+    self.resources.get(name).is_some_and(|record| record.is_listed())
+  }
+
+  pub(crate) fn resource_account_id(&self, name: &str) -> AccountId {
+    AccountId::from_str(&format!("{}.{}", name, env::current_account_id())).unwrap()
+  }
+
+  /// Creation fee of `tenant`, if any.
+  pub(crate) fn creation_fee(&self, tenant: &Option<String>) -> u128 {
+    tenant.as_ref()
+      .map(|tenant_id| self.tenants.get(tenant_id).expect("tenant not found").creation_fee.0)
+      .unwrap_or(0)
+  }
+
+  /// Creates the account of a new resource, deploys and initializes it. `deposit`
+  /// includes `creation_fee`, the rest funds the new account.
+  pub(crate) fn deploy_resource(
+    &self,
+    name: String,
+    owner: String,
+    init_params: ResourceInitParams,
+    tenant: Option<String>,
+    deposit: u128,
+    creation_fee: u128,
+  ) -> Promise {
+    // prepare arguments as json byte vector
+    let init_args = serde_json::ser::to_string(&ResourceInitParamsCallWrapper {
+      owner: owner.clone(), 
+      init_params: init_params.clone(), 
+      indexer_account: self.indexer_account.clone(),
+    }).unwrap().as_bytes().to_vec();
+
+    // ResourceId is only the subaccount. resource_account_id is the full near qualified name.
+    let resource_account_id = self.resource_account_id(&name);
+    // an account created without the gas to register it would be lost to the registry
+    assert!(
+      env::prepaid_gas() - env::used_gas() >= CREATE_RESOURCE_GAS + CREATE_CALLBACK_GAS + tgas(10),
+      "not enough gas attached to register the resource"
+    );
+
+    Promise::new(resource_account_id)
+      .create_account()
+      .transfer(deposit - creation_fee) 
+      .add_full_access_key(env::signer_account_pk()) // TODO maybe use predecessor_account_key instead - but not sure how
+      .deploy_contract(RESOURCE_WASM.to_vec())
+      .function_call("init".to_string(), init_args, 0, CREATE_RESOURCE_GAS)
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(CREATE_CALLBACK_GAS)
+          .with_unused_gas_weight(1)
+          .create_resource_callback(name, owner, init_params, tenant, U128(creation_fee))
+      )
+  }
+}
+
 #[near_bindgen]
 impl ChershareResourceFactory {
   pub fn check_resource_contained(
     &self,
     resource_id: &String,
   ) -> bool {
-    self.resources.contains(resource_id)
+    self.resources.get(resource_id).is_some()
   }
 
   pub fn assert_name_available(
@@ -74,6 +356,18 @@ impl ChershareResourceFactory {
       !self.check_resource_contained(resource_id),
       "Resource with that ID already exists"
     );
+    assert!(
+      !self.locked_names.contains(resource_id),
+      "Resource name is locked"
+    );
+    assert!(
+      self.aliases.get(resource_id).is_none(),
+      "Resource name is used as an alias"
+    );
+    assert!(
+      !resource_id.starts_with(SHADOW_PREFIX),
+      "Resource name is reserved for upgrade rehearsals"
+    );
   }
 
   pub fn get_test(&self) -> String {
@@ -81,65 +375,268 @@ impl ChershareResourceFactory {
   }
 
   pub fn name_exists(&self, name: String) -> bool {
-    self.resources.contains(&name)
+    self.check_resource_contained(&name)
+  }
+
+  pub fn get_resource(&self, name: String) -> Option<ResourceListing> {
+    self.resources.get(&name).map(|record| self.listing(record))
+  }
+
+  pub fn get_owner_resources(&self, owner: String) -> Vec<String> {
+    self.owner_resources.get(&owner).unwrap_or_default()
+  }
+
+  /// Average reliability score (basis points) over all scored resources of a host.
+  pub fn get_host_reliability(&self, owner: String) -> Option<u16> {
+    let scores: Vec<u64> = self.owner_resources.get(&owner).unwrap_or_default()
+      .iter()
+      .filter_map(|name| self.resources.get(name)?.reliability_bps)
+      .map(u64::from)
+      .collect();
+    if scores.is_empty() {
+      return None;
+    }
+    Some((scores.iter().sum::<u64>() / scores.len() as u64) as u16)
+  }
+
+  /// Bulk dump of the listed resources by name, for crawlers and backups,
+  /// continuing after `cursor` of the previous page. Tuples are
+  /// `(name, owner, created_at, status, category)`.
+  pub fn export_registry(
+    &self,
+    cursor: Option<String>,
+    limit: u64,
+  ) -> Page<(String, String, u64, ResourceStatus, Option<String>)> {
+    let lower = cursor.map_or(Bound::Included(String::new()), |cursor| Bound::Excluded(decode_cursor::<String>(&cursor)));
+    let entries = self.resources
+      .range((lower, Bound::Unbounded))
+      .filter(|(_, record)| record.is_listed())
+      .map(|(name, record)| (name.clone(), (name, record.owner, record.created_at, record.status, record.category)));
+    paginate(entries, limit.min(MAX_EXPORT_PAGE_SIZE) as usize)
+  }
+
+  pub fn get_registry_size(&self) -> u64 {
+    self.resources.len()
   }
 
   #[payable]
   pub fn create_resource(
     &mut self,
     name: String,
-    resource_init_params: ResourceInitParams 
+    resource_init_params: ResourceInitParams,
+    tenant: Option<String>,
   ) -> Promise {
     self.assert_name_available(&name);
+    let creation_fee = self.creation_fee(&tenant);
+    assert!(env::attached_deposit() >= creation_fee, "deposit does not cover the creation fee");
 
     let resource_owner = env::signer_account_id(); 
+    let resource_init_params = self.apply_config_preset(resource_init_params);
+    self.deploy_resource(
+      name,
+      resource_owner.to_string(),
+      resource_init_params,
+      tenant,
+      env::attached_deposit(),
+      creation_fee,
+    )
+  }
 
-    // prepare arguments as json byte vector
-    let init_args = serde_json::ser::to_string(&ResourceInitParamsCallWrapper {
-      owner: resource_owner.to_string(), 
-      init_params: resource_init_params.clone(), 
-    }).unwrap().as_bytes().to_vec();
-
-    // ResourceId is only the subaccount. resource_account_id is the full near qualified name.
-    let resource_account_id =
-      AccountId::from_str(&*format!("{}.{}", name, env::current_account_id()))
-        .unwrap();
-
-    Promise::new(resource_account_id.clone())
-      .create_account()
-      .transfer(env::attached_deposit()) 
-      .add_full_access_key(env::signer_account_pk()) // TODO maybe use predecessor_account_key instead - but not sure how
-      .deploy_contract(include_bytes!("../../target/wasm32-unknown-unknown/release/chershare_resource.wasm").to_vec())
-      .function_call("init".to_string(), init_args, 0, CREATE_RESOURCE_GAS)
-      .then(
-        Self::ext(env::current_account_id())
-          .with_static_gas(tgas(10))
-          .create_resource_callback(name, resource_owner.to_string(), resource_init_params)
-      )
+  /// Called by a resource whenever its pricing, metadata or status changed.
+  pub fn report_update(&mut self, summary: ResourceSummary) {
+    let name = self.assert_registered_caller();
+    let record = self.resources.get(&name).unwrap();
+    self.geo_index_remove(&name, record.coordinates);
+    self.geo_index_add(&name, summary.coordinates);
+    self.index_keywords(&name, &summary.title, &summary.tags);
+    self.resources.insert(&name, &ResourceRecord {
+      status: summary.status,
+      visibility: summary.visibility,
+      active_window: summary.active_window,
+      category: summary.category,
+      coordinates: summary.coordinates,
+      avg_response_ms: summary.avg_response_ms,
+      unanswered_requests: summary.unanswered_requests,
+      reliability_bps: summary.reliability_bps,
+      ..record
+    });
+    self.index_base_price(&name, summary.base_price.0);
   }
 
   #[private] 
+  #[allow(clippy::unused_unit)]
   pub fn create_resource_callback(
     &mut self, 
     name: String,
     owner: String, 
     init_params: ResourceInitParams, 
+    tenant: Option<String>,
+    creation_fee: U128,
     #[callback_result] call_result: Result<(), PromiseError>) -> () {
       match call_result {
         // TODO: indexer should only record succesful resource creations
         Ok(_string) => {
-          self.resources.insert(&name);// &env::signer_account_id().to_string());
-          env::log_str(
-            &*format!("ResourceCreation: {}", serde_json::ser::to_string(&ResourceCreationLog {
-              name, 
-              owner, 
-              init_params, 
-            }).unwrap())
-          ); 
+          self.resources.insert(&name, &ResourceRecord {
+            owner: owner.clone(),
+            created_at: env::block_timestamp() / 1_000_000,
+            category: init_params.category.clone(),
+            status: if init_params.draft { ResourceStatus::Draft } else { ResourceStatus::Active },
+            visibility: init_params.visibility,
+            active_window: ActiveWindow::default(),
+            base_price: U128::from(0),
+            coordinates: init_params.coordinates,
+            avg_response_ms: None,
+            unanswered_requests: 0,
+            reliability_bps: None,
+            tenant: tenant.clone(),
+            full_access_key: Some(String::from(&env::signer_account_pk())),
+          });
+          if let Some(tenant_id) = tenant {
+            self.add_tenant_resource(&tenant_id, &name);
+            let tenant = self.tenants.get(&tenant_id).unwrap();
+            if creation_fee.0 > 0 {
+              Promise::new(AccountId::from_str(&tenant.admin).unwrap()).transfer(creation_fee.0);
+            }
+          }
+          let mut owned = self.owner_resources.get(&owner).unwrap_or_default();
+          owned.push(name.clone());
+          self.owner_resources.insert(&owner, &owned);
+          Self::ext(env::current_account_id())
+            .with_static_gas(INDEX_RESOURCE_GAS)
+            .with_unused_gas_weight(1)
+            .index_resource(
+              name.clone(),
+              init_params.title.clone(),
+              init_params.tags.clone(),
+              U128(Pricing::new(init_params.pricing.clone()).get_base_price()),
+            );
+          self.emit("ResourceCreation", &ResourceCreationLog {
+            name, 
+            owner, 
+            init_params, 
+          }); 
         }, 
         Err(_err) => {
+          // the creation fee stayed with the factory, give it back
+          if creation_fee.0 > 0 {
+            Promise::new(AccountId::from_str(&owner).unwrap()).transfer(creation_fee.0);
+          }
         }
       }
   }
+
+  /// Adds a new resource to the search indexes. Kept out of the registration so a
+  /// failure here only delays search until the resource's next `report_update`.
+  #[private]
+  pub fn index_resource(
+    &mut self,
+    name: String,
+    title: String,
+    tags: Vec<String>,
+    base_price: U128,
+  ) {
+    // the record is current even if the resource reported an update in between
+    let coordinates = self.resources.get(&name).expect("resource is not registered").coordinates;
+    self.geo_index_remove(&name, coordinates);
+    self.geo_index_add(&name, coordinates);
+    self.index_keywords(&name, &title, &tags);
+    self.index_base_price(&name, base_price.0);
+  }
 }
 
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::test_utils::VMContextBuilder;
+  use near_sdk::testing_env;
+
+  use super::*;
+
+  pub(crate) fn context(predecessor: &str) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+      .current_account_id("factory.near".parse().unwrap())
+      .signer_account_id(predecessor.parse().unwrap())
+      .predecessor_account_id(predecessor.parse().unwrap());
+    builder
+  }
+
+  pub(crate) fn factory() -> ChershareResourceFactory {
+    testing_env!(context("factory.near").build());
+    ChershareResourceFactory::new("governance.near".parse().unwrap())
+  }
+
+  pub(crate) fn init_params(title: &str) -> ResourceInitParams {
+    serde_json::from_value(serde_json::json!({
+      "title": title,
+      "description": "",
+      "image_urls": [],
+      "contact": "",
+      "tags": ["cargo"],
+      "category": null,
+      "pricing": {
+        "price_per_ms": "1",
+        "price_per_booking": "0",
+        "full_refund_period_ms": 0,
+        "commitment_deposit_per_ms": null,
+        "price_per_unit": null,
+        "nightly": null,
+      },
+      "coordinates": [52.5, 13.4],
+      "min_duration_ms": 1,
+      "approval_timeout_ms": null,
+      "timelock_ms": null,
+      "grace_period_ms": null,
+    })).unwrap()
+  }
+
+  /// Runs the callbacks of a successful `create_resource` of `name` by alice.
+  pub(crate) fn register(factory: &mut ChershareResourceFactory, name: &str, title: &str) {
+    testing_env!(context("factory.near").build());
+    factory.create_resource_callback(name.to_string(), "alice.near".to_string(), init_params(title), None, U128(0), Ok(()));
+    factory.index_resource(name.to_string(), title.to_string(), vec!["cargo".to_string()], U128(1));
+  }
+
+  #[test]
+  fn created_resource_is_registered_and_indexed() {
+    let mut factory = factory();
+    testing_env!(context("alice.near").prepaid_gas(tgas(300)).build());
+    factory.create_resource("bike".to_string(), init_params("city bike"), None);
+
+    register(&mut factory, "bike", "city bike");
+    assert!(factory.check_resource_contained(&"bike".to_string()));
+    assert_eq!(factory.owner_resources.get(&"alice.near".to_string()).unwrap(), vec!["bike"]);
+    assert_eq!(factory.search("City".to_string(), 0, 10), vec!["bike"]);
+    assert_eq!(factory.search("cargo bike".to_string(), 0, 10), vec!["bike"]);
+    assert_eq!(factory.find_in_bbox(52.0, 13.0, 53.0, 14.0, 0, 10), vec!["bike"]);
+  }
+
+  #[test]
+  #[should_panic(expected = "not enough gas attached to register the resource")]
+  fn creation_without_gas_for_the_registration_is_rejected() {
+    let mut factory = factory();
+    testing_env!(context("alice.near").prepaid_gas(CREATE_RESOURCE_GAS).build());
+    factory.create_resource("bike".to_string(), init_params("city bike"), None);
+  }
+
+  #[test]
+  fn registry_export_pages_skip_unlisted_resources() {
+    let mut factory = factory();
+    for name in ["anvil", "bike", "canoe", "drill"] {
+      register(&mut factory, name, name);
+    }
+    let mut record = factory.resources.get(&"bike".to_string()).unwrap();
+    record.visibility = Visibility::Unlisted;
+    factory.resources.insert(&"bike".to_string(), &record);
+
+    let first = factory.export_registry(None, 2);
+    let names: Vec<String> = first.items.into_iter().map(|(name, ..)| name).collect();
+    assert_eq!(names, vec!["anvil", "canoe"]);
+    // a resource removed in between doesn't shift the next page
+    factory.resources.remove(&"anvil".to_string());
+    let second = factory.export_registry(first.next_cursor, 2);
+    let names: Vec<String> = second.items.into_iter().map(|(name, ..)| name).collect();
+    assert_eq!(names, vec!["drill"]);
+    assert!(second.next_cursor.is_none());
+  }
+}
@@ -0,0 +1,65 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupSet;
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::{Deserialize, Serialize};
+
+use chershare_resource::{ActiveWindow, ResourceStatus, Visibility};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt, ResourceRecord};
+
+/// State of the first deployed version.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldFactory {
+  resources: LookupSet<String>,
+  test_msg: String,
+}
+
+/// A resource created by the first version, which didn't record its owner.
+#[derive(Deserialize, Serialize)]
+pub struct LegacyResource {
+  pub name: String,
+  pub owner: String,
+  /// key placed on the resource account at creation, if it is still there
+  pub full_access_key: Option<String>,
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Rewrites the state of a factory deployed with the first version. Its
+  /// resource names were kept in a `LookupSet` which cannot be enumerated, they
+  /// are passed in with their owners and checked against the old set. The
+  /// records start out with defaults until each resource is migrated itself
+  /// and reports its summary.
+  #[private]
+  #[init(ignore_state)]
+  pub fn migrate(governance: AccountId, resources: Vec<LegacyResource>) -> Self {
+    let mut old: OldFactory = env::state_read().expect("no state to migrate");
+    let mut factory = Self::new(governance);
+    factory.test_msg = old.test_msg;
+    for LegacyResource { name, owner, full_access_key } in resources {
+      assert!(old.resources.remove(&name), "unknown resource {}", name);
+      factory.resources.insert(&name, &ResourceRecord {
+        owner: owner.clone(),
+        created_at: env::block_timestamp() / 1_000_000,
+        category: None,
+        status: ResourceStatus::Active,
+        visibility: Visibility::Public,
+        active_window: ActiveWindow::default(),
+        base_price: U128::from(0),
+        coordinates: [0.0, 0.0],
+        avg_response_ms: None,
+        unanswered_requests: 0,
+        reliability_bps: None,
+        tenant: None,
+        full_access_key,
+      });
+      let mut owned = factory.owner_resources.get(&owner).unwrap_or_default();
+      owned.push(name.clone());
+      factory.owner_resources.insert(&owner, &owned);
+      factory.geo_index_add(&name, [0.0, 0.0]);
+      factory.index_base_price(&name, 0);
+    }
+    factory
+  }
+}
@@ -0,0 +1,257 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, Promise};
+use serde::{Deserialize, Serialize};
+
+use crate::{tgas, ChershareResourceFactory, ChershareResourceFactoryExt};
+
+const APPEAL_WINDOW_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+/// Kept by the platform if the appeal is rejected, so appeals aren't free stalling.
+const APPEAL_BOND: u128 = 10u128.pow(24);
+/// Keeps a single force majeure call within gas.
+const MAX_FORCE_MAJEURE_BATCH: usize = 20;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Delisting {
+  pub reason_hash: String,
+  pub appeal_deadline: u64,
+  pub appeal_bond: Option<U128>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DelistingLog {
+  name: String,
+  delisting: Delisting,
+}
+
+impl ChershareResourceFactory {
+  pub(crate) fn call_resource(&self, name: &str, method: &str, args: serde_json::Value) -> Promise {
+    Promise::new(self.resource_account_id(name)).function_call(
+      method.to_string(),
+      args.to_string().into_bytes(),
+      0,
+      tgas(10),
+    )
+  }
+
+  fn log_delisting(&mut self, event: &str, name: String, delisting: Delisting) {
+    self.emit(event, &DelistingLog {
+      name,
+      delisting,
+    });
+  }
+
+  /// Drops the resource from the registry and all indexes and locks its name.
+  fn unregister(&mut self, name: &String) {
+    let record = self.resources.remove(name).expect("resource is not registered");
+    if let Some(mut owned) = self.owner_resources.get(&record.owner) {
+      owned.retain(|owned_name| owned_name != name);
+      self.owner_resources.insert(&record.owner, &owned);
+    }
+    self.price_index.remove(&(record.base_price.0, name.clone()));
+    self.geo_index_remove(name, record.coordinates);
+    self.index_keywords(name, "", &[]);
+    self.resource_keywords.remove(name);
+    if let Some(tenant_id) = &record.tenant {
+      self.remove_tenant_resource(tenant_id, name);
+    }
+    self.remove_aliases(name);
+    self.locked_names.insert(name);
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Flags something to the owner of a resource, e.g. a policy violation. The message
+  /// itself is delivered off chain, the resource keeps its hash as an unread notice.
+  pub fn notify_owner(&mut self, name: String, message_hash: String) -> Promise {
+    self.assert_governance();
+    assert!(self.check_resource_contained(&name), "resource is not registered");
+    self.emit("OwnerNotification", serde_json::json!({
+      "name": name,
+      "message_hash": message_hash,
+    }));
+    self.call_resource(&name, "receive_notice", serde_json::json!({ "message_hash": message_hash }))
+  }
+
+  /// Declares force majeure on resources hit by an event outside their owners'
+  /// control, see the resource's `declare_force_majeure`.
+  pub fn declare_force_majeure(&mut self, names: Vec<String>, start: u64, end: u64) {
+    self.assert_governance();
+    assert!(names.len() <= MAX_FORCE_MAJEURE_BATCH, "too many resources in one batch");
+    for name in names.iter() {
+      assert!(self.check_resource_contained(name), "resource is not registered");
+      self.call_resource(name, "declare_force_majeure", serde_json::json!({ "start": start, "end": end }));
+    }
+    self.emit("ForceMajeureDeclared", serde_json::json!({
+      "names": names,
+      "start": start,
+      "end": end,
+    }));
+  }
+
+  pub fn lift_force_majeure(&mut self, names: Vec<String>) {
+    self.assert_governance();
+    assert!(names.len() <= MAX_FORCE_MAJEURE_BATCH, "too many resources in one batch");
+    for name in names.iter() {
+      assert!(self.check_resource_contained(name), "resource is not registered");
+      self.call_resource(name, "lift_force_majeure", serde_json::json!({}));
+    }
+    self.emit("ForceMajeureLifted", serde_json::json!({ "names": names }));
+  }
+
+  /// Decides a booker's dispute of a usage report on a resource, see the
+  /// resource's `resolve_usage_dispute`.
+  pub fn resolve_usage_dispute(&mut self, name: String, booking_id: U128, surcharge: U128) -> Promise {
+    self.assert_governance();
+    assert!(self.check_resource_contained(&name), "resource is not registered");
+    self.emit("UsageDisputeResolution", serde_json::json!({
+      "name": name,
+      "booking_id": booking_id,
+      "surcharge": surcharge,
+    }));
+    // the resource pays the booking out from there, which queries the fee split
+    Promise::new(self.resource_account_id(&name)).function_call(
+      "resolve_usage_dispute".to_string(),
+      serde_json::json!({ "booking_id": booking_id, "surcharge": surcharge }).to_string().into_bytes(),
+      0,
+      tgas(40),
+    )
+  }
+
+  /// Suspends the resource and gives its owner `APPEAL_WINDOW_MS` to appeal.
+  pub fn delist(&mut self, name: String, reason_hash: String) -> Promise {
+    self.assert_governance();
+    assert!(self.check_resource_contained(&name), "resource is not registered");
+    assert!(self.delistings.get(&name).is_none(), "delisting already in progress");
+    let delisting = Delisting {
+      reason_hash,
+      appeal_deadline: env::block_timestamp() / 1_000_000 + APPEAL_WINDOW_MS,
+      appeal_bond: None,
+    };
+    self.delistings.insert(&name, &delisting);
+    self.log_delisting("DelistingOpened", name.clone(), delisting);
+    self.call_resource(&name, "suspend", serde_json::json!({}))
+  }
+
+  /// The owner contests a delisting by attaching `APPEAL_BOND`.
+  #[payable]
+  pub fn appeal_delisting(&mut self, name: String) {
+    let record = self.resources.get(&name).expect("resource is not registered");
+    assert_eq!(env::predecessor_account_id().to_string(), record.owner, "only the owner can appeal");
+    let mut delisting = self.delistings.get(&name).expect("resource is not being delisted");
+    assert!(delisting.appeal_bond.is_none(), "already appealed");
+    assert!(
+      env::block_timestamp() / 1_000_000 < delisting.appeal_deadline,
+      "appeal window is over"
+    );
+    assert!(env::attached_deposit() >= APPEAL_BOND, "appeal bond is {}", APPEAL_BOND);
+    delisting.appeal_bond = Some(U128::from(env::attached_deposit()));
+    self.delistings.insert(&name, &delisting);
+    self.log_delisting("DelistingAppealed", name, delisting);
+  }
+
+  /// Governance decides an appeal: either the delisting goes through (bond kept) or
+  /// the resource is reinstated and the bond returned.
+  pub fn resolve_appeal(&mut self, name: String, uphold_delisting: bool) {
+    self.assert_governance();
+    let delisting = self.delistings.remove(&name).expect("resource is not being delisted");
+    let bond = delisting.appeal_bond.expect("delisting was not appealed");
+    let owner = self.resources.get(&name).unwrap().owner;
+    self.log_delisting(
+      if uphold_delisting { "DelistingUpheld" } else { "DelistingOverturned" },
+      name.clone(),
+      delisting,
+    );
+    if uphold_delisting {
+      self.unregister(&name);
+    } else {
+      self.call_resource(&name, "reinstate", serde_json::json!({}));
+      Promise::new(owner.parse().unwrap()).transfer(bond.0);
+    }
+  }
+
+  /// Anyone can complete a delisting that was not appealed in time.
+  pub fn finalize_delisting(&mut self, name: String) {
+    let delisting = self.delistings.get(&name).expect("resource is not being delisted");
+    assert!(delisting.appeal_bond.is_none(), "delisting was appealed");
+    assert!(
+      env::block_timestamp() / 1_000_000 >= delisting.appeal_deadline,
+      "appeal window is still open"
+    );
+    self.delistings.remove(&name);
+    self.unregister(&name);
+    self.log_delisting("DelistingFinalized", name, delisting);
+  }
+
+  pub fn get_delisting(&self, name: String) -> Option<Delisting> {
+    self.delistings.get(&name)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::{context, factory, register};
+
+  fn delisted_bike() -> ChershareResourceFactory {
+    let mut factory = factory();
+    register(&mut factory, "bike", "city bike");
+    testing_env!(context("governance.near").build());
+    factory.delist("bike".to_string(), "hash".to_string());
+    factory
+  }
+
+  #[test]
+  #[should_panic(expected = "appeal window is still open")]
+  fn delisting_waits_for_the_appeal_window() {
+    let mut factory = delisted_bike();
+    testing_env!(context("carol.near").block_timestamp((APPEAL_WINDOW_MS - 1) * 1_000_000).build());
+    factory.finalize_delisting("bike".to_string());
+  }
+
+  #[test]
+  fn unappealed_delisting_is_finalized_after_the_window() {
+    let mut factory = delisted_bike();
+    testing_env!(context("carol.near").block_timestamp(APPEAL_WINDOW_MS * 1_000_000).build());
+    factory.finalize_delisting("bike".to_string());
+    assert!(!factory.check_resource_contained(&"bike".to_string()));
+    assert!(factory.locked_names.contains(&"bike".to_string()));
+    assert!(factory.search("city".to_string(), 0, 10).is_empty());
+    assert!(factory.get_delisting("bike".to_string()).is_none());
+  }
+
+  #[test]
+  #[should_panic(expected = "delisting was appealed")]
+  fn appealed_delisting_cant_be_finalized() {
+    let mut factory = delisted_bike();
+    testing_env!(context("alice.near").attached_deposit(APPEAL_BOND).build());
+    factory.appeal_delisting("bike".to_string());
+    testing_env!(context("carol.near").block_timestamp(APPEAL_WINDOW_MS * 1_000_000).build());
+    factory.finalize_delisting("bike".to_string());
+  }
+
+  #[test]
+  fn overturned_appeal_keeps_the_resource() {
+    let mut factory = delisted_bike();
+    testing_env!(context("alice.near").attached_deposit(APPEAL_BOND).build());
+    factory.appeal_delisting("bike".to_string());
+    testing_env!(context("governance.near").build());
+    factory.resolve_appeal("bike".to_string(), false);
+    assert!(factory.check_resource_contained(&"bike".to_string()));
+    assert!(factory.get_delisting("bike".to_string()).is_none());
+  }
+
+  #[test]
+  #[should_panic(expected = "appeal window is over")]
+  fn late_appeal_is_rejected() {
+    let mut factory = delisted_bike();
+    testing_env!(context("alice.near")
+      .attached_deposit(APPEAL_BOND)
+      .block_timestamp(APPEAL_WINDOW_MS * 1_000_000)
+      .build());
+    factory.appeal_delisting("bike".to_string());
+  }
+}
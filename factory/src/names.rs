@@ -0,0 +1,73 @@
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+const MAX_ALIASES_PER_RESOURCE: usize = 5;
+
+impl ChershareResourceFactory {
+  /// Drops all aliases of a resource that is being unregistered.
+  pub(crate) fn remove_aliases(&mut self, name: &String) {
+    for alias in self.resource_aliases.remove(name).unwrap_or_default() {
+      self.aliases.remove(&alias);
+    }
+  }
+
+  fn assert_resource_owner(&self, name: &String) {
+    let record = self.resources.get(name).expect("resource is not registered");
+    assert_eq!(
+      env::predecessor_account_id().to_string(),
+      record.owner,
+      "only the owner of the resource can do this"
+    );
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Account of the resource registered as `name` or under the alias `name`.
+  pub fn resolve(&self, name: String) -> AccountId {
+    if self.check_resource_contained(&name) {
+      return self.resource_account_id(&name);
+    }
+    if let Some(target) = self.aliases.get(&name) {
+      return self.resource_account_id(&target);
+    }
+    assert!(!self.locked_names.contains(&name), "resource was removed");
+    panic!("resource is not registered");
+  }
+
+  /// Registered name of a resource account of this factory. `None` for accounts of
+  /// other factories, removed resources and names that were never registered.
+  pub fn reverse_resolve(&self, account_id: AccountId) -> Option<String> {
+    let name = account_id.as_str().strip_suffix(&format!(".{}", env::current_account_id()))?;
+    self.check_resource_contained(&name.to_string()).then(|| name.to_string())
+  }
+
+  /// Lets `alias` resolve to the caller's resource `name`. Aliases share the name
+  /// space of resources.
+  pub fn add_alias(&mut self, name: String, alias: String) {
+    self.assert_resource_owner(&name);
+    self.assert_name_available(&alias);
+    assert!(self.aliases.get(&alias).is_none(), "alias is taken");
+    let mut aliases = self.resource_aliases.get(&name).unwrap_or_default();
+    assert!(aliases.len() < MAX_ALIASES_PER_RESOURCE, "too many aliases");
+    aliases.push(alias.clone());
+    self.resource_aliases.insert(&name, &aliases);
+    self.aliases.insert(&alias, &name);
+    self.emit("AliasAdded", serde_json::json!({ "name": name, "alias": alias }));
+  }
+
+  pub fn remove_alias(&mut self, alias: String) {
+    let name = self.aliases.get(&alias).expect("alias not found");
+    self.assert_resource_owner(&name);
+    self.aliases.remove(&alias);
+    let mut aliases = self.resource_aliases.get(&name).unwrap_or_default();
+    aliases.retain(|existing| *existing != alias);
+    self.resource_aliases.insert(&name, &aliases);
+    self.emit("AliasRemoved", serde_json::json!({ "name": name, "alias": alias }));
+  }
+
+  pub fn get_aliases(&self, name: String) -> Vec<String> {
+    self.resource_aliases.get(&name).unwrap_or_default()
+  }
+}
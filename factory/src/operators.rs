@@ -0,0 +1,78 @@
+// Business details of a creator, kept once per owner and shown with every one of
+// their resources. Only hashes of the name and contact go on chain.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt, ResourceRecord};
+
+const MAX_HASH_LENGTH: usize = 128;
+const MAX_JURISDICTION_LENGTH: usize = 16;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct OperatorProfile {
+  pub business_name_hash: String,
+  /// e.g. an ISO 3166 country or subdivision code
+  pub jurisdiction: String,
+  pub contact_hash: String,
+  pub updated_at: u64,
+}
+
+/// A registry entry together with its owner's operator profile.
+#[derive(Deserialize, Serialize)]
+pub struct ResourceListing {
+  #[serde(flatten)]
+  pub record: ResourceRecord,
+  pub operator: Option<OperatorProfile>,
+}
+
+impl ChershareResourceFactory {
+  pub(crate) fn listing(&self, record: ResourceRecord) -> ResourceListing {
+    ResourceListing {
+      operator: self.operator_profiles.get(&record.owner),
+      record,
+    }
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Registers or replaces the caller's operator profile. Only creators with at
+  /// least one resource can have one.
+  pub fn set_operator_profile(&mut self, business_name_hash: String, jurisdiction: String, contact_hash: String) {
+    let owner = env::predecessor_account_id().to_string();
+    assert!(
+      !self.owner_resources.get(&owner).unwrap_or_default().is_empty(),
+      "only resource owners can register a profile"
+    );
+    for hash in [&business_name_hash, &contact_hash] {
+      assert!(!hash.is_empty() && hash.len() <= MAX_HASH_LENGTH, "invalid hash");
+    }
+    assert!(
+      !jurisdiction.is_empty() && jurisdiction.len() <= MAX_JURISDICTION_LENGTH,
+      "invalid jurisdiction"
+    );
+    let profile = OperatorProfile {
+      business_name_hash,
+      jurisdiction,
+      contact_hash,
+      updated_at: env::block_timestamp() / 1_000_000,
+    };
+    self.operator_profiles.insert(&owner, &profile);
+    self.emit("OperatorProfileUpdate", serde_json::json!({
+      "owner": owner,
+      "profile": profile,
+    }));
+  }
+
+  pub fn remove_operator_profile(&mut self) {
+    let owner = env::predecessor_account_id().to_string();
+    self.operator_profiles.remove(&owner).expect("no operator profile");
+    self.emit("OperatorProfileRemoval", serde_json::json!({ "owner": owner }));
+  }
+
+  pub fn get_operator_profile(&self, owner: String) -> Option<OperatorProfile> {
+    self.operator_profiles.get(&owner)
+  }
+}
@@ -0,0 +1,73 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use serde::{Deserialize, Serialize};
+
+use chershare_resource::{ModificationPolicy, ResourceInitParams};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+/// Platform defaults for settings a creator leaves out. Each update by governance
+/// is a new version, resources keep what they were created with.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Default)]
+pub struct ConfigPreset {
+  pub approval_timeout_ms: Option<u64>,
+  pub timelock_ms: Option<u64>,
+  pub grace_period_ms: Option<u64>,
+  pub modification_policy: Option<ModificationPolicy>,
+  /// for tenants registered without a fee split
+  pub tenant_fee_split_bps: Option<u16>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ConfigPresetLog {
+  version: u64,
+  preset: ConfigPreset,
+}
+
+impl ChershareResourceFactory {
+  pub(crate) fn current_config_preset(&self) -> ConfigPreset {
+    self.config_presets.len()
+      .checked_sub(1)
+      .and_then(|version| self.config_presets.get(version))
+      .unwrap_or_default()
+  }
+
+  /// Fills the settings the creator left out from the current preset.
+  pub(crate) fn apply_config_preset(&self, init_params: ResourceInitParams) -> ResourceInitParams {
+    let preset = self.current_config_preset();
+    ResourceInitParams {
+      approval_timeout_ms: init_params.approval_timeout_ms.or(preset.approval_timeout_ms),
+      timelock_ms: init_params.timelock_ms.or(preset.timelock_ms),
+      grace_period_ms: init_params.grace_period_ms.or(preset.grace_period_ms),
+      modification_policy: init_params.modification_policy.or(preset.modification_policy),
+      ..init_params
+    }
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Makes `preset` the current one and returns its version.
+  pub fn publish_config_preset(&mut self, preset: ConfigPreset) -> u64 {
+    self.assert_governance();
+    if let Some(bps) = preset.tenant_fee_split_bps {
+      assert!(bps <= 10_000, "fee split above 100%");
+    }
+    self.config_presets.push(&preset);
+    let version = self.config_presets.len() - 1;
+    self.emit("ConfigPresetPublished", &ConfigPresetLog { version, preset });
+    version
+  }
+
+  /// A preset by version, the current one without a version.
+  pub fn get_config_preset(&self, version: Option<u64>) -> Option<ConfigPreset> {
+    match version {
+      Some(version) => self.config_presets.get(version),
+      None => Some(self.current_config_preset()),
+    }
+  }
+
+  pub fn get_config_preset_versions(&self) -> u64 {
+    self.config_presets.len()
+  }
+}
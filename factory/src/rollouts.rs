@@ -0,0 +1,286 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, Gas, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+
+use chershare_resource::Health;
+
+use crate::{tgas, ChershareResourceFactory, ChershareResourceFactoryExt};
+
+/// deploying the code and running `migrate`
+const UPGRADE_GAS: Gas = tgas(80);
+const HEALTH_GAS: Gas = tgas(10);
+const HEALTH_CALLBACK_GAS: Gas = tgas(10);
+const UPGRADE_CALLBACK_GAS: Gas = tgas(10 + HEALTH_GAS.0 / 10u64.pow(12) + HEALTH_CALLBACK_GAS.0 / 10u64.pow(12));
+
+/// Which resources may be upgraded to a version. Stages only move forward, the
+/// canaries stay eligible in all of them.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum RolloutStage {
+  Canary,
+  /// resources whose name hashes into the first `bps` of 10_000 buckets
+  Percentage { bps: u16 },
+  All,
+}
+
+impl RolloutStage {
+  fn rank(self) -> (u8, u16) {
+    match self {
+      RolloutStage::Canary => (0, 0),
+      RolloutStage::Percentage { bps } => (1, bps),
+      RolloutStage::All => (2, 0),
+    }
+  }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Rollout {
+  pub stage: RolloutStage,
+  pub canaries: Vec<String>,
+  /// resources running the version
+  pub upgraded: u64,
+  /// why upgrades stopped, set when an upgrade or the health check after it failed
+  pub halted: Option<String>,
+  pub started_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct RolloutLog {
+  version: u64,
+  stage: RolloutStage,
+}
+
+#[derive(Deserialize, Serialize)]
+struct RolloutHaltLog {
+  version: u64,
+  name: String,
+  reason: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ResourceUpgradeLog {
+  name: String,
+  version: u64,
+}
+
+/// Bucket in `[0, 10_000)` of a resource, stable across rollouts so a resource
+/// in the first percent gets every version early.
+fn rollout_bucket(name: &str) -> u16 {
+  let hash = env::sha256(name.as_bytes());
+  (u16::from_le_bytes([hash[0], hash[1]]) as u32 * 10_000 / 65_536) as u16
+}
+
+impl ChershareResourceFactory {
+  fn rollout(&self, version: u64) -> Rollout {
+    self.rollouts.get(&version).expect("version is not rolled out")
+  }
+
+  fn halt_rollout(&mut self, version: u64, name: String, reason: String) {
+    let mut rollout = self.rollout(version);
+    if rollout.halted.is_none() {
+      rollout.halted = Some(format!("{}: {}", name, reason));
+      self.rollouts.insert(&version, &rollout);
+    }
+    self.emit("RolloutHalted", &RolloutHaltLog { version, name, reason });
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Opens `version` to the `canaries` only.
+  pub fn start_rollout(&mut self, version: u64, canaries: Vec<String>) {
+    self.assert_governance();
+    assert!(version < self.code_versions.len(), "code version not found");
+    assert!(self.rollouts.get(&version).is_none(), "version is already rolled out");
+    for name in &canaries {
+      assert!(self.check_resource_contained(name), "{} is not registered", name);
+    }
+    self.rollouts.insert(&version, &Rollout {
+      stage: RolloutStage::Canary,
+      canaries,
+      upgraded: 0,
+      halted: None,
+      started_at: env::block_timestamp() / 1_000_000,
+    });
+    self.emit("RolloutStage", &RolloutLog { version, stage: RolloutStage::Canary });
+  }
+
+  pub fn advance_rollout(&mut self, version: u64, stage: RolloutStage) {
+    self.assert_governance();
+    let mut rollout = self.rollout(version);
+    assert!(rollout.halted.is_none(), "rollout is halted");
+    assert!(stage.rank() > rollout.stage.rank(), "stages only move forward");
+    if let RolloutStage::Percentage { bps } = stage {
+      assert!(bps <= 10_000, "percentage above 100%");
+    }
+    rollout.stage = stage;
+    self.rollouts.insert(&version, &rollout);
+    self.emit("RolloutStage", &RolloutLog { version, stage });
+  }
+
+  /// Lets upgrades continue after the cause of a halt was looked into.
+  pub fn resume_rollout(&mut self, version: u64) {
+    self.assert_governance();
+    let mut rollout = self.rollout(version);
+    assert!(rollout.halted.take().is_some(), "rollout is not halted");
+    self.rollouts.insert(&version, &rollout);
+    self.emit("RolloutStage", &RolloutLog { version, stage: rollout.stage });
+  }
+
+  pub fn get_rollout_status(&self, version: u64) -> Option<Rollout> {
+    self.rollouts.get(&version)
+  }
+
+  /// Version `name` runs, `None` for the code it was created with.
+  pub fn get_resource_code_version(&self, name: String) -> Option<u64> {
+    self.resource_code_versions.get(&name)
+  }
+
+  /// Upgrades `name` to `version` if the rollout reached it. The resource's
+  /// health is checked right after and a failing check halts the rollout.
+  pub fn upgrade_resource(&mut self, name: String, version: u64) -> Promise {
+    let record = self.resources.get(&name).expect("resource is not registered");
+    let caller = env::predecessor_account_id();
+    assert!(
+      caller == self.governance || caller.as_str() == record.owner,
+      "only governance or the owner can upgrade a resource"
+    );
+    let rollout = self.rollout(version);
+    assert!(rollout.halted.is_none(), "rollout is halted");
+    let eligible = rollout.canaries.contains(&name) || match rollout.stage {
+      RolloutStage::Canary => false,
+      RolloutStage::Percentage { bps } => rollout_bucket(&name) < bps,
+      RolloutStage::All => true,
+    };
+    assert!(eligible, "rollout hasn't reached the resource yet");
+    assert!(
+      self.resource_code_versions.get(&name).is_none_or(|current| current < version),
+      "resource already runs this or a later version"
+    );
+    let candidate = self.code_versions.get(version).unwrap();
+    let args = (self.code.get(&version).unwrap(), candidate.migrate_args).try_to_vec().unwrap();
+    Promise::new(self.resource_account_id(&name))
+      .function_call("upgrade".to_string(), args, 0, UPGRADE_GAS)
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(UPGRADE_CALLBACK_GAS)
+          .upgrade_resource_callback(name, version)
+      )
+  }
+
+  #[private]
+  pub fn upgrade_resource_callback(
+    &mut self,
+    name: String,
+    version: u64,
+    #[callback_result] call_result: Result<(), PromiseError>,
+  ) {
+    if call_result.is_err() {
+      self.halt_rollout(version, name, "upgrade failed".to_string());
+      return;
+    }
+    self.resource_code_versions.insert(&name, &version);
+    let mut rollout = self.rollout(version);
+    rollout.upgraded += 1;
+    self.rollouts.insert(&version, &rollout);
+    self.emit("ResourceUpgrade", &ResourceUpgradeLog { name: name.clone(), version });
+    Promise::new(self.resource_account_id(&name))
+      .function_call("health".to_string(), vec![], 0, HEALTH_GAS)
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(HEALTH_CALLBACK_GAS)
+          .rollout_health_callback(name, version)
+      );
+  }
+
+  #[private]
+  pub fn rollout_health_callback(
+    &mut self,
+    name: String,
+    version: u64,
+    #[callback_result] health: Result<Health, PromiseError>,
+  ) {
+    match health {
+      Ok(health) if health.warnings.is_empty() => {},
+      Ok(health) => self.halt_rollout(version, name, health.warnings.join(", ")),
+      Err(_) => self.halt_rollout(version, name, "health check failed".to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::json_types::U128;
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::{context, factory, register};
+
+  /// A factory whose version 0 is rolled out to the canary `bike`.
+  fn canary_rollout() -> ChershareResourceFactory {
+    let mut factory = factory();
+    register(&mut factory, "bike", "city bike");
+    testing_env!(context("governance.near").attached_deposit(10u128.pow(24)).build());
+    factory.publish_code_version(vec![0; 100], "{}".to_string());
+    factory.start_rollout(0, vec!["bike".to_string()]);
+    factory
+  }
+
+  fn health(warnings: &[&str]) -> Health {
+    Health {
+      version: "1".to_string(),
+      storage_usage: 0,
+      storage_cost: U128(0),
+      balance: U128(0),
+      liquid_balance: U128(0),
+      refund_liabilities: U128(0),
+      bookings: U128(0),
+      open_bookings: 0,
+      warnings: warnings.iter().map(|warning| warning.to_string()).collect(),
+    }
+  }
+
+  #[test]
+  fn healthy_upgrade_keeps_the_rollout_going() {
+    let mut factory = canary_rollout();
+    testing_env!(context("factory.near").build());
+    factory.upgrade_resource_callback("bike".to_string(), 0, Ok(()));
+    factory.rollout_health_callback("bike".to_string(), 0, Ok(health(&[])));
+    let rollout = factory.get_rollout_status(0).unwrap();
+    assert_eq!(rollout.upgraded, 1);
+    assert!(rollout.halted.is_none());
+    assert_eq!(factory.get_resource_code_version("bike".to_string()), Some(0));
+  }
+
+  #[test]
+  fn health_warnings_halt_the_rollout() {
+    let mut factory = canary_rollout();
+    testing_env!(context("factory.near").build());
+    factory.upgrade_resource_callback("bike".to_string(), 0, Ok(()));
+    factory.rollout_health_callback("bike".to_string(), 0, Ok(health(&["balance below storage cost"])));
+    assert_eq!(
+      factory.get_rollout_status(0).unwrap().halted,
+      Some("bike: balance below storage cost".to_string())
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "rollout is halted")]
+  fn failed_health_check_stops_further_upgrades() {
+    let mut factory = canary_rollout();
+    testing_env!(context("factory.near").build());
+    factory.rollout_health_callback("bike".to_string(), 0, Err(PromiseError::Failed));
+    testing_env!(context("governance.near").build());
+    factory.advance_rollout(0, RolloutStage::All);
+  }
+
+  #[test]
+  fn failed_upgrade_halts_the_rollout() {
+    let mut factory = canary_rollout();
+    testing_env!(context("factory.near").build());
+    factory.upgrade_resource_callback("bike".to_string(), 0, Err(PromiseError::Failed));
+    let rollout = factory.get_rollout_status(0).unwrap();
+    assert_eq!(rollout.halted, Some("bike: upgrade failed".to_string()));
+    assert_eq!(rollout.upgraded, 0);
+    assert!(factory.get_resource_code_version("bike".to_string()).is_none());
+  }
+}
@@ -0,0 +1,63 @@
+use std::ops::Bound;
+
+use near_sdk::json_types::U128;
+use near_sdk::near_bindgen;
+
+use chershare_common::pagination::{decode_cursor, paginate, Page};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt};
+
+const MAX_SEARCH_PAGE_SIZE: u64 = 100;
+
+impl ChershareResourceFactory {
+  pub(crate) fn index_base_price(&mut self, name: &String, base_price: u128) {
+    let mut record = self.resources.get(name).expect("resource is not registered");
+    self.price_index.remove(&(record.base_price.0, name.clone()));
+    self.price_index.insert(&(base_price, name.clone()), &());
+    record.base_price = U128::from(base_price);
+    self.resources.insert(name, &record);
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Resources with a base price (price of a one hour booking) in `[min, max]`,
+  /// cheapest first. Offsets shift when resources are listed or change their
+  /// price in between calls, `find_page_by_price_range` doesn't.
+  pub fn find_by_price_range(&self, min: U128, max: U128, from_index: u64, limit: u64) -> Vec<(String, U128)> {
+    let upper = match max.0.checked_add(1) {
+      Some(bound) => Bound::Excluded((bound, String::new())),
+      None => Bound::Unbounded,
+    };
+    self.price_index
+      .range((Bound::Included((min.0, String::new())), upper))
+      .filter(|((_, name), _)| self.is_listed(name))
+      .skip(from_index as usize)
+      .take(limit.min(MAX_SEARCH_PAGE_SIZE) as usize)
+      .map(|((price, name), _)| (name, U128::from(price)))
+      .collect()
+  }
+
+  /// Like `find_by_price_range`, continuing after `cursor` of the previous page.
+  pub fn find_page_by_price_range(
+    &self,
+    min: U128,
+    max: U128,
+    cursor: Option<String>,
+    limit: u64,
+  ) -> Page<(String, U128)> {
+    let lower = match cursor {
+      Some(cursor) => Bound::Excluded(decode_cursor::<(u128, String)>(&cursor)),
+      None => Bound::Included((min.0, String::new())),
+    };
+    let upper = match max.0.checked_add(1) {
+      Some(bound) => Bound::Excluded((bound, String::new())),
+      None => Bound::Unbounded,
+    };
+    let entries = self.price_index
+      .range((lower, upper))
+      .filter(|((_, name), _)| self.is_listed(name))
+      .map(|((price, name), _)| ((price, name.clone()), (name, U128::from(price))));
+    paginate(entries, limit.min(MAX_SEARCH_PAGE_SIZE) as usize)
+  }
+}
@@ -0,0 +1,171 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChershareResourceFactory, ChershareResourceFactoryExt, ResourceListing};
+
+const MAX_TENANT_PAGE_SIZE: u64 = 100;
+const MAX_SPLIT_BPS: u16 = 10_000;
+
+/// A partner marketplace running its own frontend on top of this factory.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Tenant {
+  pub admin: String,
+  /// charged on top of the account funding when a resource is created under the tenant
+  pub creation_fee: U128,
+  /// share of the platform fee passed on to the tenant admin
+  pub fee_split_bps: u16,
+  /// accounts allowed to hide resources from the tenant registry
+  pub curators: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct TenantLog {
+  tenant_id: String,
+  tenant: Tenant,
+}
+
+impl ChershareResourceFactory {
+  fn assert_tenant_admin(&self, tenant_id: &String) -> Tenant {
+    let tenant = self.tenants.get(tenant_id).expect("tenant not found");
+    assert_eq!(
+      env::predecessor_account_id().to_string(),
+      tenant.admin,
+      "only the tenant admin can do this"
+    );
+    tenant
+  }
+
+  fn save_tenant(&mut self, tenant_id: String, tenant: Tenant) {
+    self.tenants.insert(&tenant_id, &tenant);
+    self.emit("TenantUpdate", &TenantLog {
+      tenant_id,
+      tenant,
+    });
+  }
+
+  pub(crate) fn add_tenant_resource(&mut self, tenant_id: &String, name: &str) {
+    let mut names = self.tenant_resources.get(tenant_id).unwrap_or_default();
+    names.push(name.to_string());
+    self.tenant_resources.insert(tenant_id, &names);
+  }
+
+  pub(crate) fn remove_tenant_resource(&mut self, tenant_id: &String, name: &String) {
+    if let Some(mut names) = self.tenant_resources.get(tenant_id) {
+      names.retain(|tenant_name| tenant_name != name);
+      self.tenant_resources.insert(tenant_id, &names);
+    }
+    self.tenant_hidden.remove(name);
+  }
+
+  fn set_tenant_hidden(&mut self, name: String, hidden: bool) {
+    let record = self.resources.get(&name).expect("resource is not registered");
+    let tenant_id = record.tenant.expect("resource does not belong to a tenant");
+    let tenant = self.tenants.get(&tenant_id).unwrap();
+    let caller = env::predecessor_account_id().to_string();
+    assert!(
+      caller == tenant.admin || tenant.curators.contains(&caller),
+      "only curators of the tenant can do this"
+    );
+    if hidden {
+      self.tenant_hidden.insert(&name);
+    } else {
+      self.tenant_hidden.remove(&name);
+    }
+    self.emit("TenantCuration", serde_json::json!({
+      "tenant_id": tenant_id,
+      "name": name,
+      "hidden": hidden,
+    }));
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  pub fn register_tenant(
+    &mut self,
+    tenant_id: String,
+    admin: String,
+    creation_fee: U128,
+    fee_split_bps: Option<u16>,
+  ) {
+    self.assert_governance();
+    assert!(self.tenants.get(&tenant_id).is_none(), "tenant already exists");
+    let fee_split_bps = fee_split_bps
+      .or(self.current_config_preset().tenant_fee_split_bps)
+      .unwrap_or(0);
+    assert!(fee_split_bps <= MAX_SPLIT_BPS, "fee split above 100%");
+    self.save_tenant(tenant_id, Tenant {
+      admin,
+      creation_fee,
+      fee_split_bps,
+      curators: vec![],
+    });
+  }
+
+  /// The split is negotiated with the platform, so only governance can change it.
+  pub fn set_tenant_fee_split(&mut self, tenant_id: String, fee_split_bps: u16) {
+    self.assert_governance();
+    assert!(fee_split_bps <= MAX_SPLIT_BPS, "fee split above 100%");
+    let tenant = self.tenants.get(&tenant_id).expect("tenant not found");
+    self.save_tenant(tenant_id, Tenant { fee_split_bps, ..tenant });
+  }
+
+  pub fn set_tenant_creation_fee(&mut self, tenant_id: String, creation_fee: U128) {
+    let tenant = self.assert_tenant_admin(&tenant_id);
+    self.save_tenant(tenant_id, Tenant { creation_fee, ..tenant });
+  }
+
+  pub fn set_tenant_curators(&mut self, tenant_id: String, curators: Vec<String>) {
+    let tenant = self.assert_tenant_admin(&tenant_id);
+    self.save_tenant(tenant_id, Tenant { curators, ..tenant });
+  }
+
+  pub fn hide_from_tenant(&mut self, name: String) {
+    self.set_tenant_hidden(name, true);
+  }
+
+  pub fn show_in_tenant(&mut self, name: String) {
+    self.set_tenant_hidden(name, false);
+  }
+
+  pub fn get_tenant(&self, tenant_id: String) -> Option<Tenant> {
+    self.tenants.get(&tenant_id)
+  }
+
+  pub fn get_tenants(&self) -> Vec<String> {
+    self.tenants.keys().collect()
+  }
+
+  /// The platform fee of `name` and, for tenant resources, the tenant admin's cut of it
+  /// as `(fee_bps, Some((admin, admin_bps)))`.
+  pub fn get_fee_split(&self, name: String) -> (u16, Option<(String, u16)>) {
+    let fee_bps = self.get_fee_bps(name.clone());
+    let tenant_cut = self.resources.get(&name)
+      .and_then(|record| record.tenant)
+      .and_then(|tenant_id| self.tenants.get(&tenant_id))
+      .map(|tenant| (tenant.admin, tenant.fee_split_bps));
+    (fee_bps, tenant_cut)
+  }
+
+  /// Registry of a tenant, in creation order and without resources hidden by its curators.
+  pub fn get_tenant_registry(
+    &self,
+    tenant_id: String,
+    from_index: u64,
+    limit: u64,
+  ) -> Vec<(String, ResourceListing)> {
+    self.tenant_resources.get(&tenant_id).unwrap_or_default()
+      .into_iter()
+      .filter(|name| !self.tenant_hidden.contains(name))
+      .filter_map(|name| {
+        let record = self.resources.get(&name)?;
+        record.is_public().then_some((name, record))
+      })
+      .skip(from_index as usize)
+      .take(limit.min(MAX_TENANT_PAGE_SIZE) as usize)
+      .map(|(name, record)| (name, self.listing(record)))
+      .collect()
+  }
+}
@@ -0,0 +1,313 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use chershare_resource::{
+  BookingStatus, BookingView, ImportedBooking, Page, ResourceConfig, ResourceInitParams,
+};
+
+use crate::{tgas, ChershareResourceFactory, ChershareResourceFactoryExt, CREATE_RESOURCE_GAS, RESOURCE_WASM};
+
+/// Upgrades are rehearsed on `<prefix><name>.<factory>`, no resource can be named so.
+pub(crate) const SHADOW_PREFIX: &str = "dry-run-";
+/// `import_bookings` takes at most this many per call.
+const MAX_SNAPSHOT_BOOKINGS: u64 = 50;
+/// Room for the state of the shadow besides its code.
+const SHADOW_STATE_BYTES: u128 = 100_000;
+const SNAPSHOT_GAS: Gas = tgas(10);
+const IMPORT_GAS: Gas = tgas(25);
+const MIGRATE_GAS: Gas = tgas(60);
+const DRY_RUN_RESULT_GAS: Gas = tgas(10);
+/// the snapshot callback sends the whole rehearsal
+const DRY_RUN_CALLBACK_GAS: Gas = tgas(
+  (CREATE_RESOURCE_GAS.0 + 2 * IMPORT_GAS.0 + MIGRATE_GAS.0 + DRY_RUN_RESULT_GAS.0) / 10u64.pow(12) + 10
+);
+
+/// A resource contract version published by governance.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct CodeVersion {
+  /// sha256 of the wasm
+  pub hash: Base58CryptoHash,
+  pub size: u64,
+  /// json arguments the version's `migrate` is called with
+  pub migrate_args: String,
+  pub published_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum DryRunOutcome {
+  Succeeded,
+  /// the resource couldn't be read, nothing was deployed
+  SnapshotFailed,
+  /// seeding the shadow or migrating it failed, see the receipts for which
+  UpgradeFailed,
+}
+
+/// A rehearsal in flight, passed along its callbacks.
+#[derive(Deserialize, Serialize)]
+pub struct DryRun {
+  name: String,
+  version: u64,
+  caller: AccountId,
+  /// funds the shadow account
+  deposit: U128,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CodeVersionLog {
+  version: u64,
+  hash: Base58CryptoHash,
+  size: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DryRunLog {
+  name: String,
+  version: u64,
+  outcome: DryRunOutcome,
+}
+
+impl ChershareResourceFactory {
+  fn shadow_account_id(&self, name: &str) -> AccountId {
+    format!("{}{}.{}", SHADOW_PREFIX, name, env::current_account_id())
+      .parse()
+      .expect("name is too long for a shadow account")
+  }
+
+  /// Code `name` runs right now.
+  fn resource_code(&self, name: &String) -> Vec<u8> {
+    match self.resource_code_versions.get(name) {
+      Some(version) => self.code.get(&version).unwrap(),
+      None => RESOURCE_WASM.to_vec(),
+    }
+  }
+
+  fn record_dry_run(&mut self, dry_run: DryRun, outcome: DryRunOutcome) -> DryRunOutcome {
+    let DryRun { name, version, caller, deposit } = dry_run;
+    if outcome != DryRunOutcome::Succeeded {
+      // nothing was deployed or the batch was reverted, its deposit is back here
+      Promise::new(caller).transfer(deposit.0);
+    }
+    self.dry_runs.insert(&(name.clone(), version), &outcome);
+    self.emit("UpgradeDryRun", &DryRunLog { name, version, outcome });
+    outcome
+  }
+}
+
+#[near_bindgen]
+impl ChershareResourceFactory {
+  /// Stores a new resource version and returns its number. Arguments are borsh
+  /// serialized to keep the wasm compact, the attached deposit pays its storage.
+  #[payable]
+  pub fn publish_code_version(
+    &mut self,
+    #[serializer(borsh)] code: Vec<u8>,
+    #[serializer(borsh)] migrate_args: String,
+  ) -> u64 {
+    self.assert_governance();
+    serde_json::from_str::<serde_json::Value>(&migrate_args).expect("migrate arguments are no json");
+    let initial_storage = env::storage_usage();
+    let version = self.code_versions.len();
+    let hash: [u8; 32] = env::sha256(&code).try_into().unwrap();
+    let size = code.len() as u64;
+    self.code_versions.push(&CodeVersion {
+      hash: hash.into(),
+      size,
+      migrate_args,
+      published_at: env::block_timestamp() / 1_000_000,
+    });
+    self.code.insert(&version, &code);
+    let storage_cost = (env::storage_usage() - initial_storage) as u128 * env::storage_byte_cost();
+    assert!(
+      env::attached_deposit() >= storage_cost,
+      "storage: {}, sent: {}",
+      storage_cost,
+      env::attached_deposit()
+    );
+    self.emit("CodeVersionPublished", &CodeVersionLog { version, hash: hash.into(), size });
+    version
+  }
+
+  pub fn get_code_version(&self, version: u64) -> Option<CodeVersion> {
+    self.code_versions.get(version)
+  }
+
+  pub fn get_code_version_count(&self) -> u64 {
+    self.code_versions.len()
+  }
+
+  /// Rehearses upgrading `name` to `version` on a throwaway shadow account. It
+  /// gets the code `name` runs, its init parameters, config and up to 50
+  /// confirmed bookings, then the candidate is deployed over it
+  /// and migrated. The outcome is logged and kept for `get_dry_run`. The attached
+  /// deposit funds the shadow and goes back to the caller afterwards.
+  #[payable]
+  pub fn dry_run_upgrade(&mut self, name: String, version: u64) -> Promise {
+    let record = self.resources.get(&name).expect("resource is not registered");
+    let caller = env::predecessor_account_id();
+    assert!(
+      caller == self.governance || caller.as_str() == record.owner,
+      "only governance or the owner can rehearse an upgrade"
+    );
+    let candidate = self.code_versions.get(version).expect("code version not found");
+    // fails early for names too long to prefix
+    self.shadow_account_id(&name);
+    let current_size = self.resource_code_versions
+      .get(&name)
+      .map_or(RESOURCE_WASM.len() as u64, |version| self.code_versions.get(version).unwrap().size);
+    let code_size = candidate.size.max(current_size) as u128;
+    let required = (code_size + SHADOW_STATE_BYTES) * env::storage_byte_cost();
+    assert!(
+      env::attached_deposit() >= required,
+      "required: {}, sent: {}",
+      required,
+      env::attached_deposit()
+    );
+
+    let resource_account_id = self.resource_account_id(&name);
+    let snapshot = |method: &str, args: Vec<u8>| {
+      Promise::new(resource_account_id.clone()).function_call(method.to_string(), args, 0, SNAPSHOT_GAS)
+    };
+    snapshot("get_init_params", vec![])
+      .and(snapshot("export_config", vec![]))
+      .and(snapshot(
+        "get_bookings",
+        json!({ "cursor": null, "limit": MAX_SNAPSHOT_BOOKINGS }).to_string().into_bytes(),
+      ))
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(DRY_RUN_CALLBACK_GAS)
+          .dry_run_upgrade_callback(DryRun { name, version, caller, deposit: U128(env::attached_deposit()) })
+      )
+  }
+
+  /// Seeds the shadow from the snapshot, upgrades it and deletes it again, all in
+  /// one batch so a failing step leaves no account behind.
+  #[private]
+  pub fn dry_run_upgrade_callback(
+    &mut self,
+    dry_run: DryRun,
+    #[callback_result] init_params: Result<ResourceInitParams, PromiseError>,
+    #[callback_result] config: Result<ResourceConfig, PromiseError>,
+    #[callback_result] bookings: Result<Page<BookingView>, PromiseError>,
+  ) {
+    let (Ok(init_params), Ok(config), Ok(bookings)) = (init_params, config, bookings) else {
+      self.record_dry_run(dry_run, DryRunOutcome::SnapshotFailed);
+      return;
+    };
+    let candidate = self.code_versions.get(dry_run.version).unwrap();
+    // only one unit, so the imported bookings can't collide
+    let bookings: Vec<ImportedBooking> = bookings.items
+      .into_iter()
+      .filter(|booking| booking.unit == 0)
+      .filter(|booking| matches!(booking.status, BookingStatus::Confirmed | BookingStatus::CheckedIn))
+      .map(|booking| ImportedBooking { account_id: booking.booker, start: booking.start.0, end: booking.end.0 })
+      .collect();
+    // the factory owns the shadow, so it may import into it
+    let init_args = json!({
+      "owner": env::current_account_id(),
+      "init_params": init_params,
+      "indexer_account": null,
+    });
+    Promise::new(self.shadow_account_id(&dry_run.name))
+      .create_account()
+      .transfer(dry_run.deposit.0)
+      .deploy_contract(self.resource_code(&dry_run.name))
+      .function_call("init".to_string(), init_args.to_string().into_bytes(), 0, CREATE_RESOURCE_GAS)
+      .function_call(
+        "import_bookings".to_string(),
+        json!({ "bookings": bookings }).to_string().into_bytes(),
+        0,
+        IMPORT_GAS,
+      )
+      .function_call(
+        "import_config".to_string(),
+        json!({ "config": config }).to_string().into_bytes(),
+        0,
+        IMPORT_GAS,
+      )
+      .deploy_contract(self.code.get(&dry_run.version).unwrap())
+      .function_call("migrate".to_string(), candidate.migrate_args.into_bytes(), 0, MIGRATE_GAS)
+      .delete_account(dry_run.caller.clone())
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(DRY_RUN_RESULT_GAS)
+          .dry_run_upgrade_result(dry_run)
+      );
+  }
+
+  #[private]
+  pub fn dry_run_upgrade_result(
+    &mut self,
+    dry_run: DryRun,
+    #[callback_result] call_result: Result<(), PromiseError>,
+  ) -> DryRunOutcome {
+    let outcome = if call_result.is_ok() { DryRunOutcome::Succeeded } else { DryRunOutcome::UpgradeFailed };
+    self.record_dry_run(dry_run, outcome)
+  }
+
+  pub fn get_dry_run(&self, name: String, version: u64) -> Option<DryRunOutcome> {
+    self.dry_runs.get(&(name, version))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::{context, factory, register};
+
+  fn dry_run() -> DryRun {
+    DryRun { name: "bike".to_string(), version: 0, caller: "alice.near".parse().unwrap(), deposit: U128(100) }
+  }
+
+  /// A factory with the resource `bike` and a published version 0.
+  fn published_version() -> ChershareResourceFactory {
+    let mut factory = factory();
+    register(&mut factory, "bike", "city bike");
+    testing_env!(context("governance.near").attached_deposit(10u128.pow(24)).build());
+    factory.publish_code_version(vec![0; 100], "{}".to_string());
+    factory
+  }
+
+  #[test]
+  fn unreadable_resource_fails_the_dry_run() {
+    let mut factory = published_version();
+    testing_env!(context("factory.near").build());
+    factory.dry_run_upgrade_callback(
+      dry_run(),
+      Err(PromiseError::Failed),
+      Err(PromiseError::Failed),
+      Err(PromiseError::Failed),
+    );
+    assert_eq!(factory.get_dry_run("bike".to_string(), 0), Some(DryRunOutcome::SnapshotFailed));
+  }
+
+  #[test]
+  fn failed_migration_fails_the_dry_run() {
+    let mut factory = published_version();
+    testing_env!(context("factory.near").build());
+    let outcome = factory.dry_run_upgrade_result(dry_run(), Err(PromiseError::Failed));
+    assert_eq!(outcome, DryRunOutcome::UpgradeFailed);
+    assert_eq!(factory.get_dry_run("bike".to_string(), 0), Some(DryRunOutcome::UpgradeFailed));
+  }
+
+  #[test]
+  fn completed_rehearsal_succeeds() {
+    let mut factory = published_version();
+    testing_env!(context("factory.near").build());
+    factory.dry_run_upgrade_result(dry_run(), Ok(()));
+    assert_eq!(factory.get_dry_run("bike".to_string(), 0), Some(DryRunOutcome::Succeeded));
+  }
+
+  #[test]
+  #[should_panic(expected = "only governance or the owner can rehearse an upgrade")]
+  fn strangers_cant_rehearse_an_upgrade() {
+    let mut factory = published_version();
+    testing_env!(context("carol.near").attached_deposit(10u128.pow(24)).build());
+    factory.dry_run_upgrade("bike".to_string(), 0);
+  }
+}
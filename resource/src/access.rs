@@ -0,0 +1,116 @@
+use std::ops::Bound;
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use chershare_common::pagination::{decode_cursor, paginate, Page};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+const MAX_ALLOWLIST_PAGE_SIZE: u64 = 100;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visibility {
+  /// listed in the factory registry and search
+  #[default]
+  Public,
+  /// not listed, but anyone with the name can book
+  Unlisted,
+  /// not listed, only accounts on the allowlist can book
+  Private,
+}
+
+impl Resource {
+  pub(crate) fn assert_may_book(&self, account_id: &String) {
+    if self.visibility == Visibility::Private {
+      assert!(self.allowlist.contains_key(account_id), "resource is private");
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn get_visibility(&self) -> Visibility {
+    self.visibility
+  }
+
+  pub fn set_visibility(&mut self, visibility: Visibility) {
+    self.assert_owner();
+    self.visibility = visibility;
+    self.record_admin_action(AdminAction::VisibilityChange { visibility });
+    self.report_update();
+  }
+
+  pub fn add_to_allowlist(&mut self, account_ids: Vec<String>) {
+    self.assert_owner();
+    for account_id in account_ids.iter() {
+      self.allowlist.insert(account_id, &());
+    }
+    self.record_admin_action(AdminAction::AllowlistAddition { account_ids });
+  }
+
+  pub fn remove_from_allowlist(&mut self, account_ids: Vec<String>) {
+    self.assert_owner();
+    for account_id in account_ids.iter() {
+      self.allowlist.remove(account_id);
+    }
+    self.record_admin_action(AdminAction::AllowlistRemoval { account_ids });
+  }
+
+  /// Allowed accounts in alphabetical order, continuing after `cursor` of the
+  /// previous page.
+  pub fn get_allowlist(&self, cursor: Option<String>, limit: u64) -> Page<String> {
+    let lower = cursor.map_or(Bound::Included(String::new()), |cursor| Bound::Excluded(decode_cursor::<String>(&cursor)));
+    let entries = self.allowlist
+      .range((lower, Bound::Unbounded))
+      .map(|(account_id, _)| (account_id.clone(), account_id));
+    paginate(entries, limit.min(MAX_ALLOWLIST_PAGE_SIZE) as usize)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::test_utils::VMContextBuilder;
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::{booking_context, resource};
+
+  fn private_resource() -> Resource {
+    let mut resource = resource(1);
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .build());
+    resource.set_visibility(Visibility::Private);
+    resource
+  }
+
+  #[test]
+  #[should_panic(expected = "resource is private")]
+  fn private_resource_rejects_unlisted_bookers() {
+    let mut resource = private_resource();
+    testing_env!(booking_context(0).build());
+    resource.book(100, 200, None, None, None);
+  }
+
+  #[test]
+  fn allowlisted_account_books_a_private_resource() {
+    let mut resource = private_resource();
+    resource.add_to_allowlist(vec!["alice.near".to_string()]);
+    testing_env!(booking_context(0).build());
+    resource.book(100, 200, None, None, None);
+  }
+
+  #[test]
+  fn allowlist_pages_dont_shift_on_removals() {
+    let mut resource = private_resource();
+    resource.add_to_allowlist(vec!["dave.near".to_string(), "bob.near".to_string(), "carol.near".to_string()]);
+    let first = resource.get_allowlist(None, 2);
+    assert_eq!(first.items, vec!["bob.near", "carol.near"]);
+    resource.remove_from_allowlist(vec!["bob.near".to_string()]);
+    let second = resource.get_allowlist(first.next_cursor, 2);
+    assert_eq!(second.items, vec!["dave.near"]);
+    assert!(second.next_cursor.is_none());
+  }
+}
@@ -0,0 +1,158 @@
+// Bookings of a resource with several units are pinned to one of them when they
+// start blocking. A resource takes any booking that leaves at most `capacity`
+// bookings overlapping at every moment, so one that fits on no unit as things
+// are gets room by moving overlapping bookings to other units. Open lotteries
+// hold one unit each until they are drawn.
+
+use std::ops::Bound;
+
+use near_sdk::json_types::U128;
+
+use crate::{Interval, Resource};
+
+/// Most bookings and lotteries looked at to make room for one booking.
+const MAX_REPACKED_BOOKINGS: usize = 50;
+
+/// A blocking booking or open lottery, extended by the buffer at its end so two
+/// collide exactly when they intersect.
+struct Occupant {
+  start: u64,
+  end: u64,
+  /// `(unit, booking_id)` of a booking, `None` for a lottery
+  blocker: Option<(u32, u128)>,
+}
+
+/// A booking starting at `start` that goes from unit `from` to unit `to`.
+pub(crate) struct UnitMove {
+  booking_id: u128,
+  start: u64,
+  from: u32,
+  to: u32,
+}
+
+impl Resource {
+  /// Bookings and open lotteries intersecting `[from, to)` once extended by the buffer.
+  fn occupants(&self, from: u64, to: u64) -> Vec<Occupant> {
+    let mut occupants = Vec::new();
+    for unit in 0..self.capacity {
+      // the intervals of a unit are a buffer apart, only the last one starting
+      // before `from` can reach into the range
+      let first = self.blockers
+        .lower(&(unit, from))
+        .filter(|(u, _)| *u == unit)
+        .unwrap_or((unit, from));
+      for ((_, start), interval) in self.blockers.range((Bound::Included(first), Bound::Excluded((unit, to)))) {
+        let end = interval.end.saturating_add(self.buffer_ms);
+        if end > from {
+          occupants.push(Occupant { start, end, blocker: Some((unit, interval.booking_id)) });
+        }
+      }
+    }
+    for lottery in self.lotteries.values() {
+      let end = lottery.end.saturating_add(self.buffer_ms);
+      if lottery.start < to && end > from {
+        occupants.push(Occupant { start: lottery.start, end, blocker: None });
+      }
+    }
+    occupants
+  }
+
+  /// Unit a booking of `[start, end)` goes on and the bookings that have to move
+  /// for it, `None` if more than `capacity` would overlap. Bookings that already
+  /// started stay on their unit.
+  pub(crate) fn plan_unit(&self, start: u64, end: u64) -> Option<(u32, Vec<UnitMove>)> {
+    if self.is_blacked_out(start, end) {
+      return None;
+    }
+    let end = end.saturating_add(self.buffer_ms);
+    let occupants = self.occupants(start, end);
+    if occupants.iter().all(|occupant| occupant.blocker.is_some()) {
+      let taken = |unit: &u32| occupants.iter().any(|occupant| occupant.blocker.is_some_and(|(u, _)| u == *unit));
+      if let Some(unit) = (0..self.capacity).find(|unit| !taken(unit)) {
+        return Some((unit, vec![]));
+      }
+    }
+
+    // everything overlapping the booking directly or through others may move
+    let (mut from, mut to) = (start, end);
+    let mut occupants = loop {
+      let occupants = self.occupants(from, to);
+      if occupants.len() > MAX_REPACKED_BOOKINGS {
+        return None;
+      }
+      let wider = occupants
+        .iter()
+        .fold((from, to), |(from, to), occupant| (from.min(occupant.start), to.max(occupant.end)));
+      if wider == (from, to) {
+        break occupants;
+      }
+      (from, to) = wider;
+    };
+    occupants.push(Occupant { start, end, blocker: None });
+
+    // going by start, a unit is free unless a booking on it runs past the start,
+    // so this only fails if more than `capacity` overlap at that moment
+    let now = Self::now_ms();
+    let mut order: Vec<usize> = (0..occupants.len()).collect();
+    order.sort_by_key(|index| occupants[*index].start);
+    let mut free_from = vec![0; self.capacity as usize];
+    let mut units = vec![0; occupants.len()];
+    for index in order {
+      let occupant = &occupants[index];
+      let is_free = |unit: &u32| free_from[*unit as usize] <= occupant.start;
+      let unit = match occupant.blocker {
+        Some((unit, _)) if occupant.start <= now => Some(unit).filter(is_free)?,
+        Some((unit, _)) if is_free(&unit) => unit,
+        _ => (0..self.capacity).find(is_free)?,
+      };
+      free_from[unit as usize] = occupant.end;
+      units[index] = unit;
+    }
+    let moves = occupants
+      .iter()
+      .zip(&units)
+      .filter_map(|(occupant, to)| {
+        let (from, booking_id) = occupant.blocker?;
+        (from != *to).then_some(UnitMove { booking_id, start: occupant.start, from, to: *to })
+      })
+      .collect();
+    Some((*units.last().unwrap(), moves))
+  }
+
+  /// Moves bookings planned by `plan_unit` to their new units.
+  pub(crate) fn move_units(&mut self, moves: Vec<UnitMove>) {
+    // all are taken out first, a booking may move onto the key another one leaves
+    let intervals: Vec<Interval> = moves
+      .iter()
+      .map(|unit_move| self.blockers.remove(&(unit_move.from, unit_move.start)).unwrap())
+      .collect();
+    for (unit_move, interval) in moves.into_iter().zip(intervals) {
+      self.blockers.insert(&(unit_move.to, unit_move.start), &interval);
+      let mut booking = self.bookings.get(&unit_move.booking_id).unwrap();
+      booking.unit = unit_move.to;
+      self.store_booking(unit_move.booking_id, &booking);
+      self.emit("UnitChange", serde_json::json!({
+        "id": U128::from(unit_move.booking_id),
+        "unit": unit_move.to,
+      }));
+    }
+  }
+
+  /// Most bookings and lotteries overlapping at any moment of `[start, end)`.
+  pub(crate) fn max_overlap(&self, start: u64, end: u64) -> u32 {
+    let end = end.saturating_add(self.buffer_ms);
+    let mut changes: Vec<(u64, i32)> = self.occupants(start, end)
+      .into_iter()
+      .flat_map(|occupant| [(occupant.start.max(start), 1), (occupant.end.min(end), -1)])
+      .collect();
+    // ends come first, intervals are half-open
+    changes.sort();
+    let mut overlapping = 0;
+    let mut max = 0;
+    for (_, change) in changes {
+      overlapping += change;
+      max = max.max(overlapping);
+    }
+    max as u32
+  }
+}
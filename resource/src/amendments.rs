@@ -0,0 +1,108 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, Promise};
+
+use crate::{AdminAction, Resource, ResourceExt, MAX_REASON_TEXT_LENGTH};
+
+/// A new price for an existing booking the owner asked for, e.g. because more
+/// guests showed up. Only takes effect once the consumer accepts it.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct PriceAmendment {
+  pub new_price: U128,
+  pub reason: String,
+  pub proposed_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PriceAmendmentLog {
+  id: U128,
+  old_price: U128,
+  amendment: PriceAmendment,
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Replaces any earlier proposal for the booking.
+  pub fn propose_price_amendment(&mut self, booking_id: u128, new_price: U128, reason: String) {
+    self.assert_owner();
+    assert!(reason.len() <= MAX_REASON_TEXT_LENGTH, "reason text too long");
+    let booking = self.get_active_booking(booking_id);
+    booking.assert_in_escrow();
+    self.assert_payment_open(booking_id);
+    assert!(new_price.0 != booking.price, "price unchanged");
+    assert!(new_price.0 >= booking.refunded, "new price below what was already refunded");
+    let amendment = PriceAmendment {
+      new_price,
+      reason,
+      proposed_at: Self::now_ms(),
+    };
+    self.price_amendments.insert(&booking_id, &amendment);
+    self.record_admin_action(AdminAction::PriceAmendmentProposed { booking_id: U128::from(booking_id), new_price });
+    self.emit("PriceAmendmentProposal", &PriceAmendmentLog {
+      id: U128::from(booking_id),
+      old_price: U128::from(booking.price),
+      amendment,
+    });
+  }
+
+  /// Accepts the proposed price. A higher price has to be paid with the attached
+  /// deposit, or is added to the invoice of an invoiced booking. A lower price is
+  /// refunded.
+  #[payable]
+  pub fn accept_price_amendment(&mut self, booking_id: u128) {
+    let mut booking = self.get_active_booking(booking_id);
+    assert!(
+      booking.consumer_account_id.eq(&env::signer_account_id().to_string()),
+      "not your booking"
+    );
+    let amendment = self.price_amendments.remove(&booking_id).expect("no price amendment proposed");
+    booking.assert_in_escrow();
+    self.assert_payment_open(booking_id);
+    let old_price = booking.price;
+    let new_price = amendment.new_price.0;
+    assert!(new_price >= booking.refunded, "new price below what was already refunded");
+    booking.price = new_price;
+    self.store_booking(booking_id, &booking);
+    if old_price == 0 && new_price > 0 {
+      self.payouts_due.insert(&booking_id, &());
+    }
+    self.emit("PriceAmendmentAcceptance", &PriceAmendmentLog {
+      id: U128::from(booking_id),
+      old_price: U128::from(old_price),
+      amendment,
+    });
+    if new_price > old_price {
+      let difference = new_price - old_price;
+      let mut change = env::attached_deposit();
+      if !self.add_to_invoice(booking_id, difference) {
+        assert!(change >= difference, "difference: {}, sent: {}", difference, change);
+        change -= difference;
+      }
+      if change > 0 {
+        Promise::new(env::signer_account_id()).transfer(change);
+      }
+    } else {
+      self.refund(booking_id, &booking.consumer_account_id, old_price - new_price + env::attached_deposit());
+    }
+  }
+
+  /// Either side can drop a pending proposal.
+  pub fn withdraw_price_amendment(&mut self, booking_id: u128) {
+    let booking = self.bookings.get(&booking_id).expect("booking not found");
+    let caller = env::predecessor_account_id().to_string();
+    assert!(
+      caller == self.owner || caller == booking.consumer_account_id,
+      "not your booking"
+    );
+    self.price_amendments.remove(&booking_id).expect("no price amendment proposed");
+    self.emit("PriceAmendmentWithdrawal", serde_json::json!({
+      "id": U128::from(booking_id),
+      "by_owner": caller == self.owner,
+    }));
+  }
+
+  pub fn get_price_amendment(&self, booking_id: U128) -> Option<PriceAmendment> {
+    self.price_amendments.get(&booking_id.0)
+  }
+}
@@ -0,0 +1,90 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{ActiveWindow, BookingPhase, LastMinuteRule, UsagePricing, Resource, ResourceExt, ResourceStatus, Visibility};
+
+const MAX_AUDIT_PAGE_SIZE: u64 = 100;
+
+/// Everything the owner (or an operator acting for the resource) changed.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize)]
+pub enum AdminAction {
+  BookingApproval { booking_id: U128 },
+  BookingRejection { booking_id: U128 },
+  BookingImport { bookings: u32 },
+  DepositForfeit { booking_id: U128, amount: U128 },
+  ChangeScheduled { id: u64 },
+  ChangeCancelled { id: u64 },
+  ChangeApplied { id: u64 },
+  StatusChange { status: ResourceStatus },
+  FeedTokenCreated,
+  FeedTokenRevoked,
+  PrivacyModeChange { enabled: bool },
+  VisibilityChange { visibility: Visibility },
+  AllowlistAddition { account_ids: Vec<String> },
+  AllowlistRemoval { account_ids: Vec<String> },
+  ActiveWindowChange { window: ActiveWindow },
+  BookingPhasesChange { phases: Vec<BookingPhase> },
+  MemberTierChange { account_ids: Vec<String>, tier: Option<String> },
+  LotteryCreated { id: u64 },
+  InvoiceCustomerChange { account_id: String, suspended: bool },
+  LateInterestChange { bps_per_day: u16 },
+  RefundIssued { booking_id: U128, amount: U128 },
+  PriceAmendmentProposed { booking_id: U128, new_price: U128 },
+  Withdrawal { amount: U128 },
+  OwnerCancellationPenaltyChange { bps: u16 },
+  PackageOffersChange,
+  OverlapCompensationChange { bps: u16 },
+  TwinChange { twin: Option<String> },
+  CampaignCreated { id: u64 },
+  CampaignEnded { id: u64 },
+  FirstBookingDiscountChange { bps: u16 },
+  LastMinuteRuleChange { rule: Option<LastMinuteRule> },
+  RangeCancellation { start: u64, end: u64, bookings: u32 },
+  PeriodBlocked { id: u64, start: u64, end: u64 },
+  PeriodUnblocked { id: u64 },
+  ForceMajeureDeclared { start: u64, end: u64 },
+  ForceMajeureLifted,
+  BufferChange { buffer_ms: u64 },
+  AccessKeyChange { removed: String, added: Option<String> },
+  MetadataChange,
+  UsagePricingChange { usage_pricing: Option<UsagePricing> },
+  PricingUpdate { version: u64 },
+  OracleChange { oracle: Option<String> },
+  DepositReleasePeriodChange { deposit_release_ms: u64 },
+  TermsPublished { version: u64 },
+  CouponCreated { code: String },
+  CouponRevoked { code: String },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize)]
+pub struct AuditEntry {
+  pub actor: String,
+  pub timestamp_ms: u64,
+  pub action: AdminAction,
+}
+
+impl Resource {
+  /// Appends to the audit log, the log is never rewritten.
+  pub(crate) fn record_admin_action(&mut self, action: AdminAction) {
+    self.audit_log.push(&AuditEntry {
+      actor: env::predecessor_account_id().to_string(),
+      timestamp_ms: env::block_timestamp() / 1_000_000,
+      action,
+    });
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn get_audit_log(&self, from_index: u64, limit: u64) -> Vec<AuditEntry> {
+    (from_index..std::cmp::min(from_index + limit.min(MAX_AUDIT_PAGE_SIZE), self.audit_log.len()))
+      .filter_map(|index| self.audit_log.get(index))
+      .collect()
+  }
+
+  pub fn get_audit_log_length(&self) -> u64 {
+    self.audit_log.len()
+  }
+}
@@ -0,0 +1,72 @@
+// Periods the owner takes the resource off the market, e.g. for maintenance or
+// personal use. They block every unit like a booking but nobody pays for them.
+
+use std::ops::Bound;
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{AdminAction, Resource, ResourceExt, MAX_REASON_TEXT_LENGTH};
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Blackout {
+  pub id: u64,
+  pub start: u64,
+  pub end: u64,
+  pub reason: String,
+}
+
+impl Resource {
+  /// Whether a blackout intersects `[start, end)`.
+  pub(crate) fn is_blacked_out(&self, start: u64, end: u64) -> bool {
+    self.blackouts
+      .lower(&end)
+      .and_then(|key| self.blackouts.get(&key))
+      .is_some_and(|blackout| blackout.end > start)
+  }
+
+  /// Blackouts intersecting `[from, to)`, ordered by start.
+  pub(crate) fn blackouts_between(&self, from: u64, to: u64) -> Vec<Blackout> {
+    let first = self.blackouts.floor_key(&from).unwrap_or(from);
+    self.blackouts
+      .range((Bound::Included(first), Bound::Excluded(to)))
+      .map(|(_, blackout)| blackout)
+      .filter(|blackout| blackout.end > from)
+      .collect()
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Blocks `[start, end)` on every unit. The period must be free, bookings in it
+  /// have to be cancelled first.
+  pub fn block_period(&mut self, start: u64, end: u64, reason: String) -> u64 {
+    self.assert_owner();
+    assert!(end > start, "end before start");
+    assert!(reason.len() <= MAX_REASON_TEXT_LENGTH, "reason text too long");
+    assert!(!self.is_blacked_out(start, end), "period is already blocked");
+    assert!(self.blocking_bookings(start, end).is_empty(), "period has bookings");
+    let id = self.next_blackout_id;
+    self.next_blackout_id += 1;
+    let blackout = Blackout { id, start, end, reason };
+    self.blackouts.insert(&start, &blackout);
+    self.blackout_starts.insert(&id, &start);
+    self.emit("PeriodBlocked", &blackout);
+    self.record_admin_action(AdminAction::PeriodBlocked { id, start, end });
+    id
+  }
+
+  pub fn unblock_period(&mut self, id: u64) {
+    self.assert_owner();
+    let start = self.blackout_starts.remove(&id).expect("blocked period not found");
+    let blackout = self.blackouts.remove(&start).unwrap();
+    self.emit("PeriodUnblocked", &blackout);
+    self.record_admin_action(AdminAction::PeriodUnblocked { id });
+  }
+
+  pub fn get_blocked_periods(&self, from: u64, to: u64) -> Vec<Blackout> {
+    assert!(to > from, "end before start");
+    self.blackouts_between(from, to)
+  }
+}
@@ -0,0 +1,120 @@
+// Read access to bookings for frontends that don't run an indexer.
+
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen};
+use near_sdk::serde::{Deserialize, Serialize};
+
+use chershare_common::pagination::{decode_cursor, paginate, Page};
+
+use crate::{Booking, BookingStatus, CancellationReason, ExchangeRate, Resource, ResourceExt};
+
+/// Longest page `get_bookings` returns.
+const MAX_BOOKINGS_PAGE: u64 = 100;
+
+#[derive(Deserialize, Serialize)]
+pub struct BookingView {
+  pub id: U128,
+  /// the account id, or its hash in privacy mode
+  pub booker: String,
+  pub start: U64,
+  pub end: U64,
+  pub price: U128,
+  pub commitment_deposit: U128,
+  pub refunded: U128,
+  pub status: BookingStatus,
+  pub created_at: U64,
+  pub unit: u32,
+  pub package_id: Option<U64>,
+  pub mirror_of: Option<U128>,
+  pub imported: bool,
+  pub campaign_id: Option<U64>,
+  pub metered: bool,
+  pub checked_in_at: Option<U64>,
+  pub checked_out_at: Option<U64>,
+  pub terms_version: Option<U64>,
+  pub coupon_code: Option<String>,
+  pub paid_out: bool,
+  pub exchange_rate: Option<ExchangeRate>,
+  pub cancelled_at: Option<U64>,
+  pub cancelled_by_owner: Option<bool>,
+  pub cancellation_reason: Option<CancellationReason>,
+}
+
+impl Resource {
+  fn booking_view(&self, booking_id: u128, booking: Booking) -> BookingView {
+    let cancellation = booking.cancellation.as_ref();
+    BookingView {
+      id: U128::from(booking_id),
+      booker: self.booker_label(&booking.consumer_account_id),
+      start: U64::from(booking.start),
+      end: U64::from(booking.end),
+      price: U128::from(booking.price),
+      commitment_deposit: U128::from(booking.commitment_deposit),
+      refunded: U128::from(booking.refunded),
+      status: booking.status,
+      created_at: U64::from(booking.created_at),
+      unit: booking.unit,
+      package_id: booking.package_id.map(U64::from),
+      mirror_of: booking.mirror_of.map(U128::from),
+      imported: booking.imported,
+      campaign_id: booking.campaign_id.map(U64::from),
+      metered: booking.metered,
+      checked_in_at: booking.checked_in_at.map(U64::from),
+      checked_out_at: booking.checked_out_at.map(U64::from),
+      terms_version: booking.terms_version.map(U64::from),
+      coupon_code: booking.coupon_code.clone(),
+      paid_out: booking.paid_out,
+      exchange_rate: booking.exchange_rate.clone(),
+      cancelled_at: cancellation.map(|cancellation| U64::from(cancellation.cancelled_at)),
+      cancelled_by_owner: cancellation.map(|cancellation| cancellation.by_owner),
+      cancellation_reason: cancellation.and_then(|cancellation| cancellation.reason),
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn get_booking(&self, booking_id: U128) -> Option<BookingView> {
+    self.bookings.get(&booking_id.0).map(|booking| self.booking_view(booking_id.0, booking))
+  }
+
+  /// Bookings including cancelled ones by id, at most 100 per call. Continues
+  /// after `cursor` of the previous page.
+  pub fn get_bookings(&self, cursor: Option<String>, limit: u64) -> Page<BookingView> {
+    let from = cursor.map_or(0, |cursor| decode_cursor::<u128>(&cursor) + 1);
+    let entries = (from..self.next_booking_id)
+      .filter_map(|booking_id| Some((booking_id, self.booking_view(booking_id, self.bookings.get(&booking_id)?))));
+    paginate(entries, limit.min(MAX_BOOKINGS_PAGE) as usize)
+  }
+
+  /// Bookings of `account_id` in the order they were made, at most 100 per call,
+  /// continuing after `cursor` of the previous page. In privacy mode only the
+  /// account itself and the owner may list them, views have no caller so it has
+  /// to be a signed call then.
+  pub fn get_bookings_by_account(&self, account_id: String, cursor: Option<String>, limit: u64) -> Page<BookingView> {
+    if self.privacy_mode {
+      let caller = env::predecessor_account_id().to_string();
+      assert!(caller == account_id || caller == self.owner, "bookings are private");
+    }
+    let after = cursor.map(|cursor| decode_cursor::<u128>(&cursor));
+    // booking ids are added in ascending order
+    let entries = self.account_bookings
+      .get(&account_id)
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|booking_id| after.is_none_or(|after| *booking_id > after))
+      .filter_map(|booking_id| Some((booking_id, self.booking_view(booking_id, self.bookings.get(&booking_id)?))));
+    paginate(entries, limit.min(MAX_BOOKINGS_PAGE) as usize)
+  }
+
+  /// Whether `account_id` made booking `booking_id`. Only the factory may ask, so
+  /// it can't be used to find out who booked in privacy mode.
+  pub fn is_booker(&self, booking_id: U128, account_id: String) -> bool {
+    self.assert_factory();
+    self.bookings.get(&booking_id.0).is_some_and(|booking| booking.consumer_account_id == account_id)
+  }
+
+  pub fn get_booking_count(&self) -> u64 {
+    self.bookings.len()
+  }
+}
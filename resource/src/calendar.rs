@@ -0,0 +1,176 @@
+use std::ops::Bound;
+
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{Booking, Resource, ResourceExt, MS_PER_DAY};
+
+/// Days since 1970-01-01 of a proleptic gregorian date (Howard Hinnant's algorithm).
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = if year >= 0 { year } else { year - 399 } / 400;
+  let year_of_era = year - era * 400;
+  let month = month as i64;
+  let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`, `(year, month, day)` of a day since 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let days = days + 719_468;
+  let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+  let day_of_era = days - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+  let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+  (year, month, day)
+}
+
+/// Longest window `get_availability` covers.
+const MAX_AVAILABILITY_WINDOW_MS: u64 = 366 * MS_PER_DAY;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum AvailabilityKind {
+  Free,
+  /// blocked by the owner with `block_period`
+  Blackout,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AvailabilityRange {
+  pub unit: u32,
+  pub start: u64,
+  pub end: u64,
+  pub kind: AvailabilityKind,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DayView {
+  pub day: u32,
+  pub start: u64,
+  /// share of the day that is booked, in basis points
+  pub booked_bps: u16,
+  /// earliest free moment of the day, `None` if the day is fully booked
+  pub first_free_slot: Option<u64>,
+}
+
+impl Resource {
+  /// Every booking blocking `unit` intersecting `[from, to)` with its id, ordered by start.
+  fn blocking_bookings_of_unit(&self, unit: u32, from: u64, to: u64) -> Vec<(u128, Booking)> {
+    let first = self.blockers
+      .floor_key(&(unit, from))
+      .filter(|(u, _)| *u == unit)
+      .unwrap_or((unit, from));
+    self.blockers
+      .range((Bound::Included(first), Bound::Excluded((unit, to))))
+      .filter(|(_, interval)| interval.end > from)
+      .filter_map(|(_, interval)| Some((interval.booking_id, self.bookings.get(&interval.booking_id)?)))
+      .collect()
+  }
+
+  /// Every blocking booking intersecting `[from, to)` with its id, ordered by start.
+  pub(crate) fn blocking_bookings(&self, from: u64, to: u64) -> Vec<(u128, Booking)> {
+    let mut bookings: Vec<_> = (0..self.capacity)
+      .flat_map(|unit| self.blocking_bookings_of_unit(unit, from, to))
+      .collect();
+    bookings.sort_by_key(|(_, booking)| booking.start);
+    bookings
+  }
+
+  /// `(start, end)` of every booking blocking `unit` intersecting `[from, to)`, ordered by start.
+  pub(crate) fn blocked_intervals(&self, unit: u32, from: u64, to: u64) -> Vec<(u64, u64)> {
+    self.blocking_bookings_of_unit(unit, from, to)
+      .into_iter()
+      .map(|(_, booking)| (booking.start, booking.end))
+      .collect()
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Free gaps and blocked periods of every unit inside `[from, to)`, ordered by
+  /// unit and start. Booked time and the buffer around it are left out. The window
+  /// is at most 366 days long.
+  pub fn get_availability(&self, from: u64, to: u64) -> Vec<AvailabilityRange> {
+    assert!(to > from, "end before start");
+    assert!(to - from <= MAX_AVAILABILITY_WINDOW_MS, "window too long");
+    let blackouts = self.blackouts_between(from, to);
+    let mut ranges = Vec::new();
+    for unit in 0..self.capacity {
+      let mut occupied: Vec<_> = self.blocked_intervals(unit, from, to)
+        .into_iter()
+        .map(|(start, end)| (start.saturating_sub(self.buffer_ms), end.saturating_add(self.buffer_ms), None))
+        .chain(blackouts.iter().map(|blackout| (blackout.start, blackout.end, Some(AvailabilityKind::Blackout))))
+        .collect();
+      occupied.sort_by_key(|(start, _, _)| *start);
+      let mut cursor = from;
+      for (start, end, kind) in occupied {
+        if start > cursor {
+          ranges.push(AvailabilityRange { unit, start: cursor, end: start, kind: AvailabilityKind::Free });
+        }
+        if let Some(kind) = kind {
+          ranges.push(AvailabilityRange { unit, start: start.max(from), end: end.min(to), kind });
+        }
+        cursor = cursor.max(end);
+      }
+      if cursor < to {
+        ranges.push(AvailabilityRange { unit, start: cursor, end: to, kind: AvailabilityKind::Free });
+      }
+    }
+    ranges
+  }
+
+  /// Occupancy of every (UTC) day of a month, enough to render a month grid in one call.
+  /// With several units the share is averaged over them and the first free slot is
+  /// the earliest one of any unit.
+  pub fn get_month_view(&self, year: u32, month: u32) -> Vec<DayView> {
+    assert!(year >= 1970, "year before 1970");
+    assert!((1..=12).contains(&month), "invalid month");
+    let first_day = days_from_civil(year as i64, month, 1);
+    let next_first_day = if month == 12 {
+      days_from_civil(year as i64 + 1, 1, 1)
+    } else {
+      days_from_civil(year as i64, month + 1, 1)
+    };
+    let month_start = first_day as u64 * MS_PER_DAY;
+    let month_end = next_first_day as u64 * MS_PER_DAY;
+    let unit_intervals: Vec<_> = (0..self.capacity)
+      .map(|unit| self.blocked_intervals(unit, month_start, month_end))
+      .collect();
+
+    (0..(next_first_day - first_day) as u64).map(|day_index| {
+      let day_start = month_start + day_index * MS_PER_DAY;
+      let day_end = day_start + MS_PER_DAY;
+      let mut booked_ms = 0;
+      let mut first_free_slot: Option<u64> = None;
+      for intervals in &unit_intervals {
+        let mut cursor = day_start;
+        let mut unit_free_slot = None;
+        for (start, end) in intervals.iter().filter(|(start, end)| *start < day_end && *end > day_start) {
+          if unit_free_slot.is_none() && *start > cursor {
+            unit_free_slot = Some(cursor);
+          }
+          booked_ms += end.min(&day_end) - start.max(&day_start);
+          cursor = cursor.max(*end);
+        }
+        if unit_free_slot.is_none() && cursor < day_end {
+          unit_free_slot = Some(cursor);
+        }
+        first_free_slot = match (first_free_slot, unit_free_slot) {
+          (Some(a), Some(b)) => Some(a.min(b)),
+          (a, b) => a.or(b),
+        };
+      }
+      DayView {
+        day: day_index as u32 + 1,
+        start: day_start,
+        booked_bps: (booked_ms * 10_000 / (MS_PER_DAY * self.capacity as u64)) as u16,
+        first_free_slot,
+      }
+    }).collect()
+  }
+}
@@ -0,0 +1,85 @@
+// Time limited discounts, e.g. 20% off September stays booked in August. Unlike
+// the pricing they apply without a timelock, they can only lower prices.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+const MAX_CAMPAIGNS: usize = 20;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Campaign {
+  pub id: u64,
+  pub name: String,
+  pub discount_bps: u16,
+  /// bookings made in `[booked_from, booked_until)` get the discount
+  pub booked_from: u64,
+  pub booked_until: u64,
+  /// if they start in `[stay_from, stay_until)`
+  pub stay_from: u64,
+  pub stay_until: u64,
+}
+
+impl Campaign {
+  fn applies(&self, now: u64, start: u64) -> bool {
+    self.booked_from <= now && now < self.booked_until && self.stay_from <= start && start < self.stay_until
+  }
+}
+
+impl Resource {
+  /// `price` of a booking starting at `start` made now, less the largest running
+  /// discount, and the campaign that gave it.
+  pub(crate) fn campaign_price(&self, start: u64, price: u128) -> (u128, Option<u64>) {
+    let now = Self::now_ms();
+    let best = self.campaigns
+      .iter()
+      .filter(|campaign| campaign.applies(now, start))
+      .max_by_key(|campaign| campaign.discount_bps);
+    match best {
+      Some(campaign) if price > 0 => (price - price * campaign.discount_bps as u128 / 10_000, Some(campaign.id)),
+      _ => (price, None),
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn add_campaign(
+    &mut self,
+    name: String,
+    discount_bps: u16,
+    booked_from: u64,
+    booked_until: u64,
+    stay_from: u64,
+    stay_until: u64,
+  ) -> u64 {
+    self.assert_owner();
+    assert!(discount_bps > 0 && discount_bps <= 10_000, "discount must be between 0 and 100%");
+    assert!(booked_from < booked_until && stay_from < stay_until, "end before start");
+    let now = Self::now_ms();
+    self.campaigns.retain(|campaign| campaign.booked_until > now);
+    assert!(self.campaigns.len() < MAX_CAMPAIGNS, "too many campaigns");
+    let id = self.next_campaign_id;
+    self.next_campaign_id += 1;
+    self.campaigns.push(Campaign { id, name, discount_bps, booked_from, booked_until, stay_from, stay_until });
+    self.record_admin_action(AdminAction::CampaignCreated { id });
+    id
+  }
+
+  /// Stops a campaign early, bookings already made keep their discount.
+  pub fn end_campaign(&mut self, id: u64) {
+    self.assert_owner();
+    let count = self.campaigns.len();
+    self.campaigns.retain(|campaign| campaign.id != id);
+    assert!(self.campaigns.len() < count, "campaign not found");
+    self.record_admin_action(AdminAction::CampaignEnded { id });
+  }
+
+  /// Campaigns that still accept bookings or will.
+  pub fn get_campaigns(&self) -> Vec<Campaign> {
+    let now = Self::now_ms();
+    self.campaigns.iter().filter(|campaign| campaign.booked_until > now).cloned().collect()
+  }
+}
@@ -0,0 +1,43 @@
+// Emergency closures, e.g. a flooded venue, where every booking in a period has
+// to go at once.
+
+use near_sdk::near_bindgen;
+
+use crate::{AdminAction, CancellationReason, Resource, ResourceExt};
+
+/// Keeps a batch of cancellations and their refunds within one transaction's gas.
+const MAX_CANCELLATION_BATCH: u64 = 50;
+
+#[near_bindgen]
+impl Resource {
+  /// Cancels up to `limit` (at most 50) bookings intersecting `[start, end)` that
+  /// haven't ended, as `owner_cancel_booking` does. If bookings are left the
+  /// start to continue from is returned, call again with it until `None`.
+  pub fn owner_cancel_range(&mut self, start: u64, end: u64, limit: u64) -> Option<u64> {
+    self.assert_owner();
+    assert!(end > start, "end before start");
+    let now = Self::now_ms();
+    let mut bookings: Vec<_> = self.blocking_bookings(start, end);
+    bookings.extend(
+      self.unblocked_requests
+        .iter()
+        .filter_map(|booking_id| Some((booking_id, self.bookings.get(&booking_id)?)))
+        .filter(|(_, booking)| booking.start < end && booking.end > start)
+    );
+    bookings.retain(|(_, booking)| booking.end > now);
+    bookings.sort_by_key(|(booking_id, booking)| (booking.start, *booking_id));
+
+    let limit = limit.min(MAX_CANCELLATION_BATCH) as usize;
+    let next_start = bookings.get(limit).map(|(_, booking)| booking.start.max(start));
+    let cancelled = bookings.len().min(limit) as u32;
+    let penalties = bookings.iter().take(limit).map(|(_, booking)| booking.owner_cancellation_penalty()).sum();
+    self.assert_withdrawable_covers(penalties, "penalties");
+    for (booking_id, booking) in bookings.into_iter().take(limit) {
+      self.cancel_by_owner(booking_id, booking, Some(CancellationReason::ResourceUnavailable), None);
+    }
+    self.record_admin_action(AdminAction::RangeCancellation { start, end, bookings: cancelled });
+    // once for the whole batch, every report is a cross-contract call
+    self.report_update();
+    next_start
+  }
+}
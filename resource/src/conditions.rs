@@ -0,0 +1,108 @@
+// Condition reports, hashes of photos and notes both parties take before a
+// booking starts and after it ended. The content itself stays off chain, the
+// hashes are evidence in usage disputes.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{Resource, ResourceExt, MS_PER_DAY};
+
+/// How long after the end of a booking its post-booking reports are accepted.
+const POST_REPORT_WINDOW_MS: u64 = 2 * MS_PER_DAY;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionPhase {
+  /// before the booking starts
+  Pre,
+  /// after the booking ended
+  Post,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct ConditionReport {
+  pub by_owner: bool,
+  pub phase: ConditionPhase,
+  pub content_hash: String,
+  pub submitted_at: u64,
+}
+
+/// Which reports exist for a booking and whether both parties saw the same.
+#[derive(Deserialize, Serialize)]
+pub struct ConditionEvidence {
+  pub owner_pre: bool,
+  pub owner_post: bool,
+  pub booker_pre: bool,
+  pub booker_post: bool,
+  /// `None` unless both parties reported the phase
+  pub pre_matches: Option<bool>,
+  pub post_matches: Option<bool>,
+}
+
+impl Resource {
+  fn condition_report(&self, booking_id: u128, by_owner: bool, phase: ConditionPhase) -> Option<ConditionReport> {
+    self.condition_reports
+      .get(&booking_id)
+      .unwrap_or_default()
+      .into_iter()
+      .find(|report| report.by_owner == by_owner && report.phase == phase)
+  }
+
+  pub(crate) fn condition_evidence(&self, booking_id: u128) -> ConditionEvidence {
+    let hash = |by_owner, phase| self.condition_report(booking_id, by_owner, phase).map(|report| report.content_hash);
+    let matches = |owner: &Option<String>, booker: &Option<String>| match (owner, booker) {
+      (Some(owner), Some(booker)) => Some(owner == booker),
+      _ => None,
+    };
+    let (owner_pre, owner_post) = (hash(true, ConditionPhase::Pre), hash(true, ConditionPhase::Post));
+    let (booker_pre, booker_post) = (hash(false, ConditionPhase::Pre), hash(false, ConditionPhase::Post));
+    ConditionEvidence {
+      pre_matches: matches(&owner_pre, &booker_pre),
+      post_matches: matches(&owner_post, &booker_post),
+      owner_pre: owner_pre.is_some(),
+      owner_post: owner_post.is_some(),
+      booker_pre: booker_pre.is_some(),
+      booker_post: booker_post.is_some(),
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Files the owner's or the booker's report for `phase`, each party reports a
+  /// phase once. Pre-booking reports are accepted until the start, post-booking
+  /// reports within two days after the end.
+  pub fn submit_condition_report(&mut self, booking_id: U128, phase: ConditionPhase, content_hash: String) {
+    let booking = self.get_active_booking(booking_id.0);
+    let caller = env::predecessor_account_id().to_string();
+    let by_owner = caller == self.owner;
+    assert!(by_owner || caller == booking.consumer_account_id, "not your booking");
+    assert!(!content_hash.is_empty() && content_hash.len() <= 128, "invalid content hash");
+    let now = Self::now_ms();
+    match phase {
+      ConditionPhase::Pre => assert!(now < booking.start, "booking has started"),
+      ConditionPhase::Post => assert!(
+        now >= booking.end && now < booking.end + POST_REPORT_WINDOW_MS,
+        "post-booking reports are accepted within two days after the end"
+      ),
+    }
+    assert!(self.condition_report(booking_id.0, by_owner, phase).is_none(), "already reported");
+    let report = ConditionReport { by_owner, phase, content_hash, submitted_at: now };
+    let mut reports = self.condition_reports.get(&booking_id.0).unwrap_or_default();
+    reports.push(report.clone());
+    self.condition_reports.insert(&booking_id.0, &reports);
+    self.emit("ConditionReport", serde_json::json!({
+      "id": booking_id,
+      "report": report,
+    }));
+  }
+
+  pub fn get_condition_reports(&self, booking_id: U128) -> Vec<ConditionReport> {
+    self.condition_reports.get(&booking_id.0).unwrap_or_default()
+  }
+
+  pub fn get_condition_evidence(&self, booking_id: U128) -> ConditionEvidence {
+    self.condition_evidence(booking_id.0)
+  }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::near_bindgen;
+
+use crate::{
+  ActiveWindow, BookingPhase, Campaign, Coupon, DraftUpdate, LastMinuteRule, MetadataUpdate, ModificationPolicy,
+  OverbookingPolicy, PackageOffer, PriceCurrency, PricingParams, Resource, ResourceExt, ResourceStatus,
+  SensitiveChange, TermsVersion, UsagePricing, Visibility,
+};
+
+/// Every setting the owner controls, for backups and for copying settings to
+/// another resource. Account lists like the allowlist, member tiers, invoice
+/// customers and the twin are not part of it. Unknown fields are rejected, so a
+/// typo can't pass for an applied setting.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceConfig {
+  /// all of it applies to drafts, published resources only take what
+  /// `update_metadata` changes and reject a different category or minimum duration
+  pub metadata: DraftUpdate,
+  pub visibility: Visibility,
+  pub active_window: ActiveWindow,
+  pub booking_phases: Vec<BookingPhase>,
+  pub privacy_mode: bool,
+  pub owner_cancellation_penalty_bps: u16,
+  pub overlap_compensation_bps: u16,
+  #[serde(default)]
+  pub first_booking_discount_bps: u16,
+  #[serde(default)]
+  pub last_minute_rule: Option<LastMinuteRule>,
+  #[serde(default)]
+  pub buffer_ms: u64,
+  pub package_offers: Vec<PackageOffer>,
+  #[serde(default)]
+  pub oracle: Option<String>,
+  /// replace the running campaigns, which get new ids
+  #[serde(default)]
+  pub campaigns: Vec<Campaign>,
+  /// unexpired coupons by code, they replace the existing ones
+  #[serde(default)]
+  pub coupons: BTreeMap<String, Coupon>,
+  /// latest terms, published anew unless they are the latest here already
+  #[serde(default)]
+  pub terms: Option<TermsVersion>,
+  // timelocked, see `SensitiveChange`
+  pub pricing: PricingParams,
+  pub approval_timeout_ms: u64,
+  pub grace_period_ms: u64,
+  pub modification_policy: ModificationPolicy,
+  pub overbooking_policy: OverbookingPolicy,
+  pub timelock_ms: u64,
+  #[serde(default)]
+  pub price_currency: Option<PriceCurrency>,
+  pub late_interest_bps_per_day: u16,
+  #[serde(default)]
+  pub usage_pricing: Option<UsagePricing>,
+  #[serde(default)]
+  pub deposit_release_ms: Option<u64>,
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn export_config(&self) -> ResourceConfig {
+    let now = Self::now_ms();
+    ResourceConfig {
+      metadata: DraftUpdate {
+        title: Some(self.title.clone()),
+        description: Some(self.get_description()),
+        image_urls: Some(self.image_urls.to_vec()),
+        contact: Some(self.contact.clone()),
+        tags: Some(self.tags.to_vec()),
+        category: self.category.clone(),
+        pricing: None,
+        coordinates: Some(self.coordinates),
+        min_duration_ms: Some(self.min_duration_ms),
+      },
+      visibility: self.visibility,
+      active_window: self.active_window,
+      booking_phases: self.booking_phases.clone(),
+      privacy_mode: self.privacy_mode,
+      late_interest_bps_per_day: self.late_interest_bps_per_day,
+      owner_cancellation_penalty_bps: self.owner_cancellation_penalty_bps,
+      overlap_compensation_bps: self.overlap_compensation_bps,
+      first_booking_discount_bps: self.first_booking_discount_bps,
+      last_minute_rule: self.last_minute_rule,
+      buffer_ms: self.buffer_ms,
+      package_offers: self.package_offers.clone(),
+      usage_pricing: self.usage_pricing.clone(),
+      deposit_release_ms: Some(self.deposit_release_ms),
+      oracle: self.oracle.clone(),
+      campaigns: self.get_campaigns(),
+      coupons: self.coupons
+        .iter()
+        .filter(|(_, coupon)| coupon.expires_at > now && coupon.remaining_uses > 0)
+        .collect(),
+      terms: self.terms.len().checked_sub(1).and_then(|version| self.terms.get(version)),
+      pricing: PricingParams::from(&self.pricing),
+      approval_timeout_ms: self.approval_timeout_ms,
+      grace_period_ms: self.grace_period_ms,
+      modification_policy: self.modification_policy.clone(),
+      overbooking_policy: self.overbooking_policy,
+      timelock_ms: self.timelock_ms,
+      price_currency: self.price_currency.clone(),
+    }
+  }
+
+  /// Applies a configuration from `export_config`. Timelocked settings that differ
+  /// are scheduled like with `schedule_change`, their ids are returned. Drafts take
+  /// metadata and pricing right away. Any invalid or unsupported setting rejects
+  /// the whole import.
+  pub fn import_config(&mut self, config: ResourceConfig) -> Vec<u64> {
+    self.assert_owner();
+    assert!(config.metadata.pricing.is_none(), "pricing goes in `pricing`, not in `metadata`");
+    let mut scheduled = Vec::new();
+    if self.status == ResourceStatus::Draft {
+      self.update_draft(DraftUpdate { pricing: Some(config.pricing.clone()), ..config.metadata });
+    } else {
+      let metadata = config.metadata;
+      assert!(
+        metadata.category.is_none() || metadata.category == self.category,
+        "the category can only change on drafts"
+      );
+      assert!(
+        metadata.min_duration_ms.is_none_or(|min_duration_ms| min_duration_ms == self.min_duration_ms),
+        "the minimum duration can only change on drafts"
+      );
+      self.update_metadata(MetadataUpdate {
+        title: metadata.title,
+        description: metadata.description,
+        contact: metadata.contact,
+        coordinates: metadata.coordinates,
+        image_urls: metadata.image_urls,
+        tags: metadata.tags,
+      });
+      if !same(&PricingParams::from(&self.pricing), &config.pricing) {
+        scheduled.push(self.schedule_change(SensitiveChange::Pricing(Box::new(config.pricing))));
+      }
+    }
+    if self.visibility != config.visibility {
+      self.set_visibility(config.visibility);
+    }
+    if !same(&self.active_window, &config.active_window) {
+      self.set_active_window(config.active_window.open_from, config.active_window.open_until);
+    }
+    self.set_booking_phases(config.booking_phases);
+    self.set_privacy_mode(config.privacy_mode);
+    self.set_owner_cancellation_penalty(config.owner_cancellation_penalty_bps);
+    self.set_overlap_compensation(config.overlap_compensation_bps);
+    self.set_first_booking_discount(config.first_booking_discount_bps);
+    self.set_last_minute_rule(config.last_minute_rule);
+    self.set_buffer(config.buffer_ms);
+    self.set_package_offers(config.package_offers);
+    if self.oracle != config.oracle {
+      self.set_oracle(config.oracle);
+    }
+    if !same(&self.get_campaigns(), &config.campaigns) {
+      for campaign in self.get_campaigns() {
+        self.end_campaign(campaign.id);
+      }
+      for campaign in config.campaigns {
+        self.add_campaign(
+          campaign.name,
+          campaign.discount_bps,
+          campaign.booked_from,
+          campaign.booked_until,
+          campaign.stay_from,
+          campaign.stay_until,
+        );
+      }
+    }
+    for code in self.coupons.keys().collect::<Vec<_>>() {
+      if !config.coupons.contains_key(&code) {
+        self.revoke_coupon(code);
+      }
+    }
+    for (code, coupon) in config.coupons {
+      if !self.coupons.get(&code).is_some_and(|existing| same(&existing, &coupon)) {
+        self.create_coupon(code, coupon.discount, coupon.remaining_uses, coupon.expires_at);
+      }
+    }
+    if let Some(terms) = config.terms {
+      let latest = self.terms.len().checked_sub(1).and_then(|version| self.terms.get(version));
+      if latest.is_none_or(|latest| latest.content_hash != terms.content_hash) {
+        self.publish_terms(terms.content_hash, terms.effective_from.max(Self::now_ms()));
+      }
+    }
+
+    if self.approval_timeout_ms != config.approval_timeout_ms {
+      scheduled.push(self.schedule_change(SensitiveChange::ApprovalTimeout(config.approval_timeout_ms)));
+    }
+    if self.grace_period_ms != config.grace_period_ms {
+      scheduled.push(self.schedule_change(SensitiveChange::GracePeriod(config.grace_period_ms)));
+    }
+    if !same(&self.modification_policy, &config.modification_policy) {
+      scheduled.push(self.schedule_change(SensitiveChange::ModificationPolicy(config.modification_policy)));
+    }
+    if self.overbooking_policy != config.overbooking_policy {
+      scheduled.push(self.schedule_change(SensitiveChange::OverbookingPolicy(config.overbooking_policy)));
+    }
+    if self.timelock_ms != config.timelock_ms {
+      scheduled.push(self.schedule_change(SensitiveChange::Timelock(config.timelock_ms)));
+    }
+    if !same(&self.usage_pricing, &config.usage_pricing) {
+      scheduled.push(self.schedule_change(SensitiveChange::UsagePricing(config.usage_pricing)));
+    }
+    let current_release_ms = self.deposit_release_ms;
+    if let Some(deposit_release_ms) = config.deposit_release_ms.filter(|ms| *ms != current_release_ms) {
+      scheduled.push(self.schedule_change(SensitiveChange::DepositReleasePeriod(deposit_release_ms)));
+    }
+    if self.late_interest_bps_per_day != config.late_interest_bps_per_day {
+      scheduled.push(self.schedule_change(SensitiveChange::LateInterest(config.late_interest_bps_per_day)));
+    }
+    if !same(&self.price_currency, &config.price_currency) {
+      scheduled.push(self.schedule_change(SensitiveChange::PriceCurrency(config.price_currency)));
+    }
+    scheduled
+  }
+}
+
+fn same(a: &impl Serialize, b: &impl Serialize) -> bool {
+  serde_json::to_value(a).unwrap() == serde_json::to_value(b).unwrap()
+}
@@ -0,0 +1,114 @@
+// Promo codes the owner hands out, e.g. to newsletter readers. Each code has a
+// limited number of uses and an expiry.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+const MAX_CODE_LENGTH: usize = 32;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy)]
+pub enum CouponDiscount {
+  Percentage { bps: u16 },
+  /// capped at the price
+  Fixed { amount: U128 },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Coupon {
+  pub discount: CouponDiscount,
+  pub remaining_uses: u32,
+  pub expires_at: u64,
+}
+
+impl Resource {
+  /// Redeems `code` on a booking costing `price` and returns the discount.
+  pub(crate) fn use_coupon(&mut self, code: &String, price: u128) -> u128 {
+    let mut coupon = self.coupons.get(code).expect("unknown coupon code");
+    assert!(Self::now_ms() < coupon.expires_at, "coupon has expired");
+    assert!(coupon.remaining_uses > 0, "coupon is used up");
+    assert!(price > 0, "coupon does not apply to free bookings");
+    coupon.remaining_uses -= 1;
+    self.coupons.insert(code, &coupon);
+    match coupon.discount {
+      CouponDiscount::Percentage { bps } => price * bps as u128 / 10_000,
+      CouponDiscount::Fixed { amount } => amount.0.min(price),
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Creates or replaces the coupon for `code`.
+  pub fn create_coupon(&mut self, code: String, discount: CouponDiscount, max_uses: u32, expires_at: u64) {
+    self.assert_owner();
+    assert!(!code.is_empty() && code.len() <= MAX_CODE_LENGTH, "invalid coupon code");
+    assert!(max_uses > 0, "coupon needs at least one use");
+    assert!(expires_at > Self::now_ms(), "coupon expires in the past");
+    match discount {
+      CouponDiscount::Percentage { bps } => assert!(bps > 0 && bps <= 10_000, "discount must be between 0 and 100%"),
+      CouponDiscount::Fixed { amount } => assert!(amount.0 > 0, "discount must not be zero"),
+    }
+    self.coupons.insert(&code, &Coupon { discount, remaining_uses: max_uses, expires_at });
+    self.record_admin_action(AdminAction::CouponCreated { code });
+  }
+
+  /// Bookings already made keep their discount.
+  pub fn revoke_coupon(&mut self, code: String) {
+    self.assert_owner();
+    self.coupons.remove(&code).expect("unknown coupon code");
+    self.record_admin_action(AdminAction::CouponRevoked { code });
+  }
+
+  pub fn get_coupon(&self, code: String) -> Option<Coupon> {
+    self.coupons.get(&code)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::test_utils::VMContextBuilder;
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::{booking_context, resource};
+  use crate::Pricing;
+
+  /// A resource charging 1 per ms with a full discount coupon usable once.
+  fn resource_with_coupon() -> Resource {
+    let mut resource = resource(1);
+    resource.pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": null,
+      "price_per_unit": null,
+      "nightly": null,
+    })).unwrap());
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .build());
+    resource.create_coupon("FREE".to_string(), CouponDiscount::Percentage { bps: 10_000 }, 1, u64::MAX);
+    resource
+  }
+
+  #[test]
+  fn full_discount_books_for_the_storage_only() {
+    let mut resource = resource_with_coupon();
+    testing_env!(booking_context(0).build());
+    let booking_id = resource.book(100, 200, None, None, Some("FREE".to_string()));
+    assert_eq!(resource.get_booking(booking_id).unwrap().price, U128::from(0));
+    assert_eq!(resource.get_coupon("FREE".to_string()).unwrap().remaining_uses, 0);
+  }
+
+  #[test]
+  #[should_panic(expected = "coupon is used up")]
+  fn coupon_is_used_up() {
+    let mut resource = resource_with_coupon();
+    testing_env!(booking_context(0).build());
+    resource.book(100, 200, None, None, Some("FREE".to_string()));
+    resource.book(300, 400, None, None, Some("FREE".to_string()));
+  }
+}
@@ -0,0 +1,135 @@
+// Discounts on top of the pricing and campaigns, for long stays, for slots about
+// to go unused and for new bookers.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::rates::to_yocto;
+use crate::{AdminAction, BookingStatus, Pricing, Resource, ResourceExt};
+
+const MAX_DURATION_DISCOUNTS: usize = 10;
+
+/// Discounts slots that start soon and are still free.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy)]
+pub struct LastMinuteRule {
+  /// slots starting within this long from now are discounted
+  pub within_ms: u64,
+  pub discount_bps: u16,
+}
+
+/// Long stay discount for bookings longer than `threshold_ms`.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy)]
+pub struct DurationDiscount {
+  pub threshold_ms: u64,
+  pub discount_bps: u16,
+}
+
+/// Sorts the discounts by descending threshold, so the first one that applies is
+/// the one for the longest threshold.
+pub(crate) fn sorted_duration_discounts(mut discounts: Vec<DurationDiscount>) -> Vec<DurationDiscount> {
+  assert!(discounts.len() <= MAX_DURATION_DISCOUNTS, "too many duration discounts");
+  assert!(discounts.iter().all(|discount| discount.discount_bps < 10_000), "discount must be below 100%");
+  discounts.sort_by_key(|discount| std::cmp::Reverse(discount.threshold_ms));
+  discounts
+}
+
+impl Pricing {
+  /// Long stay discount on the `gross` price of `[from, until)`. Duration presets
+  /// already are a price for their duration and get none.
+  pub fn get_duration_discount(&self, from: u64, until: u64, gross: u128) -> u128 {
+    if self.preset(from, until).is_some() {
+      return 0;
+    }
+    self.duration_discounts
+      .iter()
+      .find(|discount| until - from > discount.threshold_ms)
+      .map_or(0, |discount| gross * discount.discount_bps as u128 / 10_000)
+  }
+}
+
+impl Resource {
+  /// Discount on `price` of a booking starting at `start` made now.
+  pub(crate) fn last_minute_discount(&self, start: u64, price: u128) -> u128 {
+    let now = Self::now_ms();
+    match self.last_minute_rule {
+      Some(rule) if start >= now && start - now <= rule.within_ms => price * rule.discount_bps as u128 / 10_000,
+      _ => 0,
+    }
+  }
+
+  /// Price of `[start, end)` booked now by anyone, i.e. without the first booking discount.
+  pub(crate) fn quote_price(&self, start: u64, end: u64) -> u128 {
+    let list_price = to_yocto(self.pricing.get_price(start, end), self.current_exchange_rate().as_ref());
+    let (price, _) = self.campaign_price(start, list_price);
+    price - self.last_minute_discount(start, price)
+  }
+
+  /// Whether `account_id` has a booking here that ran to its end.
+  fn has_completed_booking(&self, account_id: &String, now: u64) -> bool {
+    self.account_bookings
+      .get(account_id)
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|booking_id| self.bookings.get(booking_id))
+      .any(|booking| match booking.status {
+        BookingStatus::Completed => true,
+        BookingStatus::Confirmed | BookingStatus::CheckedIn => booking.end <= now,
+        _ => false,
+      })
+  }
+
+  fn first_booking_discount_for(&self, account_id: &String) -> u16 {
+    if self.first_booking_discount_bps == 0
+      || self.first_booking_discounts_used.contains(account_id)
+      || self.first_booking_discounts_used.contains(&self.tombstone(account_id))
+      || self.has_completed_booking(account_id, Self::now_ms())
+    {
+      0
+    } else {
+      self.first_booking_discount_bps
+    }
+  }
+
+  /// `price` less the first booking discount of `account_id`, which is used up
+  /// by this. Returns the discount.
+  pub(crate) fn use_first_booking_discount(&mut self, account_id: &String, price: u128) -> u128 {
+    let bps = self.first_booking_discount_for(account_id);
+    if bps == 0 || price == 0 {
+      return 0;
+    }
+    self.first_booking_discounts_used.insert(account_id);
+    price * bps as u128 / 10_000
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// `None` turns last minute discounts off.
+  pub fn set_last_minute_rule(&mut self, rule: Option<LastMinuteRule>) {
+    self.assert_owner();
+    if let Some(rule) = rule {
+      assert!(rule.discount_bps < 10_000, "discount must be below 100%");
+    }
+    self.last_minute_rule = rule;
+    self.record_admin_action(AdminAction::LastMinuteRuleChange { rule });
+  }
+
+  pub fn get_last_minute_rule(&self) -> Option<LastMinuteRule> {
+    self.last_minute_rule
+  }
+
+  /// Discount in basis points for accounts that never completed a booking here,
+  /// each account gets it once. 0 turns it off.
+  pub fn set_first_booking_discount(&mut self, bps: u16) {
+    self.assert_owner();
+    assert!(bps < 10_000, "discount must be below 100%");
+    self.first_booking_discount_bps = bps;
+    self.record_admin_action(AdminAction::FirstBookingDiscountChange { bps });
+  }
+
+  /// The discount `account_id` would get on its next booking.
+  pub fn get_first_booking_discount(&self, account_id: String) -> u16 {
+    self.first_booking_discount_for(&account_id)
+  }
+}
@@ -0,0 +1,77 @@
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::near_bindgen;
+
+use crate::{Pricing, PricingParams, Resource, ResourceExt, ResourceStatus};
+
+/// Fields a host can still change freely while the resource is a draft.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DraftUpdate {
+  pub title: Option<String>,
+  pub description: Option<String>,
+  /// added to the existing images, see `remove_image`
+  pub image_urls: Option<Vec<String>>,
+  pub contact: Option<String>,
+  /// replaces the existing tags
+  pub tags: Option<Vec<String>>,
+  pub category: Option<String>,
+  /// applies right away, nobody can have booked yet
+  pub pricing: Option<PricingParams>,
+  pub coordinates: Option<[f32; 2]>,
+  pub min_duration_ms: Option<u64>,
+}
+
+impl Resource {
+  fn assert_draft(&self) {
+    assert!(self.status == ResourceStatus::Draft, "resource is not a draft");
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn update_draft(&mut self, update: DraftUpdate) {
+    self.assert_owner();
+    self.assert_draft();
+    if let Some(title) = update.title {
+      self.title = title;
+    }
+    if let Some(description) = update.description {
+      self.description.set(&description);
+    }
+    if let Some(image_urls) = update.image_urls {
+      self.image_urls.extend(image_urls);
+    }
+    if let Some(contact) = update.contact {
+      self.contact = contact;
+    }
+    if let Some(tags) = update.tags {
+      self.tags.clear();
+      self.tags.extend(tags);
+    }
+    if let Some(category) = update.category {
+      self.category = Some(category);
+    }
+    if let Some(pricing) = update.pricing {
+      self.pricing = Pricing::new(pricing);
+      self.record_pricing_version();
+    }
+    if let Some(coordinates) = update.coordinates {
+      self.coordinates = coordinates;
+    }
+    if let Some(min_duration_ms) = update.min_duration_ms {
+      self.min_duration_ms = min_duration_ms;
+    }
+  }
+
+  /// Makes a draft bookable and listed once it is complete.
+  pub fn publish(&mut self) {
+    self.assert_owner();
+    self.assert_draft();
+    assert!(!self.title.is_empty(), "title is missing");
+    assert!(!self.get_description().is_empty(), "description is missing");
+    assert!(!self.contact.is_empty(), "contact is missing");
+    assert!(self.min_duration_ms > 0, "minimum duration is missing");
+    self.set_status(ResourceStatus::Active);
+    self.emit("ResourcePublished", self.summary());
+  }
+}
@@ -0,0 +1,107 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{Resource, ResourceExt};
+
+/// How many booking changes are kept for `get_changes_since`.
+const CHANGE_BUFFER_SIZE: u64 = 256;
+const MAX_CHANGES_PAGE_SIZE: u64 = 100;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+  BookingCreated,
+  BookingCancelled,
+  BookingUpdated,
+}
+
+impl ChangeKind {
+  fn of_event(event: &str) -> Option<Self> {
+    match event {
+      "BookingCreation" | "BookingRequest" | "BookingImport" | "MirrorBlock" => Some(Self::BookingCreated),
+      "BookingCancellation" | "OwnerBookingCancellation" | "BookingRequestExpiry" | "BookingRejection"
+        | "OverlapResolution" | "MirrorRelease" | "MirrorConflict" => Some(Self::BookingCancelled),
+      "BookingApproval" | "BookingReschedule" | "CheckIn" | "DepositForfeit" | "RefundIssued" | "BookingCompletion"
+        | "PriceAmendmentAcceptance" | "CheckOut" | "UsageReport" | "UsageDispute"
+        | "UsageSettlement" | "ConditionReport" => Some(Self::BookingUpdated),
+      _ => None,
+    }
+  }
+}
+
+/// Compact form of a booking event, enough for an indexer to refetch the booking.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct ChangeRecord {
+  /// `event_seq` of the event this change was emitted with
+  pub seq: u64,
+  pub kind: ChangeKind,
+  pub booking_id: U128,
+  pub timestamp_ms: u64,
+}
+
+impl Resource {
+  fn record_change(&mut self, record: ChangeRecord) {
+    if self.recent_changes.len() < CHANGE_BUFFER_SIZE {
+      self.recent_changes.push(&record);
+    } else {
+      let slot = self.changes_recorded % CHANGE_BUFFER_SIZE;
+      let evicted = self.recent_changes.replace(slot, &record);
+      self.changes_lost_before = evicted.seq + 1;
+    }
+    self.changes_recorded += 1;
+  }
+
+  /// Logs `Event: {json}` with the next `event_seq` added to the data. Data that
+  /// isn't an object is wrapped as `{"value": data}`. Indexers can spot missed
+  /// receipts by gaps in the sequence. Booking events also get the booking's
+  /// `status` after the change, unless the booking was deleted.
+  pub(crate) fn emit(&mut self, event: &str, data: impl Serialize) {
+    let mut value = serde_json::to_value(data).unwrap();
+    if !value.is_object() {
+      value = serde_json::json!({ "value": value });
+    }
+    value["event_seq"] = self.event_seq.into();
+    if let Some(kind) = ChangeKind::of_event(event) {
+      // every booking event carries the booking id as `id`
+      let booking_id: U128 = serde_json::from_value(value["id"].clone()).unwrap();
+      if let Some(booking) = self.bookings.get(&booking_id.0) {
+        value["status"] = serde_json::to_value(booking.status).unwrap();
+      }
+      self.record_change(ChangeRecord {
+        seq: self.event_seq,
+        kind,
+        booking_id,
+        timestamp_ms: Self::now_ms(),
+      });
+    }
+    self.event_seq += 1;
+    env::log_str(&format!("{}: {}", event, value));
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Number of events emitted so far, i.e. the `event_seq` of the next one.
+  pub fn get_event_seq(&self) -> u64 {
+    self.event_seq
+  }
+
+  /// Booking changes with `seq` or a later sequence number, oldest first. Fails
+  /// once changes from `seq` on were dropped from the buffer, the caller then has
+  /// to resync from the state views.
+  pub fn get_changes_since(&self, seq: u64, limit: u64) -> Vec<ChangeRecord> {
+    assert!(
+      seq >= self.changes_lost_before,
+      "changes before {} are no longer buffered",
+      self.changes_lost_before
+    );
+    let len = self.recent_changes.len();
+    let oldest = if len < CHANGE_BUFFER_SIZE { 0 } else { self.changes_recorded % CHANGE_BUFFER_SIZE };
+    (0..len)
+      .filter_map(|offset| self.recent_changes.get((oldest + offset) % len))
+      .filter(|record| record.seq >= seq)
+      .take(limit.min(MAX_CHANGES_PAGE_SIZE) as usize)
+      .collect()
+  }
+}
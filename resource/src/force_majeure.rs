@@ -0,0 +1,62 @@
+// Events outside anyone's control, like a storm or a lockdown. While declared,
+// bookings in the affected period can be cancelled for a full refund and no new
+// ones are taken for it.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct ForceMajeure {
+  /// affected period
+  pub start: u64,
+  pub end: u64,
+  pub declared_by: String,
+  pub declared_at: u64,
+}
+
+impl Resource {
+  /// Whether force majeure is declared for a period intersecting `[start, end)`.
+  pub(crate) fn is_force_majeure(&self, start: u64, end: u64) -> bool {
+    self.force_majeure
+      .as_ref()
+      .is_some_and(|force_majeure| force_majeure.start < end && force_majeure.end > start)
+  }
+
+  pub(crate) fn assert_no_force_majeure(&self, start: u64, end: u64) {
+    assert!(!self.is_force_majeure(start, end), "bookings are paused for this period");
+  }
+
+  pub(crate) fn assert_owner_or_factory(&self) -> String {
+    let caller = env::predecessor_account_id().to_string();
+    assert!(caller == self.owner || caller == self.factory, "only the owner or the factory can do this");
+    caller
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Replaces a force majeure declared before. Callable by the owner and by the
+  /// factory for platform wide events.
+  pub fn declare_force_majeure(&mut self, start: u64, end: u64) {
+    let declared_by = self.assert_owner_or_factory();
+    assert!(end > start, "end before start");
+    let force_majeure = ForceMajeure { start, end, declared_by, declared_at: Self::now_ms() };
+    self.emit("ForceMajeureDeclared", &force_majeure);
+    self.force_majeure = Some(force_majeure);
+    self.record_admin_action(AdminAction::ForceMajeureDeclared { start, end });
+  }
+
+  pub fn lift_force_majeure(&mut self) {
+    self.assert_owner_or_factory();
+    let force_majeure = self.force_majeure.take().expect("no force majeure declared");
+    self.emit("ForceMajeureLifted", &force_majeure);
+    self.record_admin_action(AdminAction::ForceMajeureLifted);
+  }
+
+  pub fn get_force_majeure(&self) -> Option<ForceMajeure> {
+    self.force_majeure.clone()
+  }
+}
@@ -0,0 +1,115 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{Booking, BookingStatus, Resource, ResourceExt};
+
+#[derive(Deserialize, Serialize)]
+pub struct Health {
+  pub version: String,
+  pub storage_usage: u64,
+  /// balance locked for storage staking
+  pub storage_cost: U128,
+  pub balance: U128,
+  /// balance minus storage cost
+  pub liquid_balance: U128,
+  /// what bookers could get refunded at most, see `refund_liabilities`
+  pub refund_liabilities: U128,
+  pub bookings: U128,
+  /// bookings and requests that haven't ended yet
+  pub open_bookings: u64,
+  pub warnings: Vec<String>,
+}
+
+impl Resource {
+  /// What a booking holds for others: the paid part of the price until it is paid
+  /// out or cancelled, the commitment deposit until it is returned or forfeited.
+  fn booking_liability(&self, booking_id: u128) -> u128 {
+    let Some(booking) = self.bookings.get(&booking_id) else {
+      return 0;
+    };
+    let mut liability = 0;
+    if !matches!(booking.status, BookingStatus::Completed | BookingStatus::Cancelled) {
+      let invoiced = self.invoices.get(&booking_id).map_or(0, |invoice| invoice.amount.0);
+      liability += (booking.price - booking.refunded).saturating_sub(invoiced);
+    }
+    if booking.deposit_held || (booking.checked_in_at.is_none() && booking.status != BookingStatus::Cancelled) {
+      liability += booking.commitment_deposit;
+    }
+    liability
+  }
+
+  /// Runs `change`, which may write booking `booking_id` or its invoice, and keeps
+  /// `liabilities` in step. Changes must not nest.
+  pub(crate) fn track_liability(&mut self, booking_id: u128, change: impl FnOnce(&mut Self)) {
+    let before = self.booking_liability(booking_id);
+    change(self);
+    self.liabilities = self.liabilities + self.booking_liability(booking_id) - before;
+  }
+
+  /// Every booking write goes through here, see `track_liability`.
+  pub(crate) fn store_booking(&mut self, booking_id: u128, booking: &Booking) {
+    self.track_liability(booking_id, |resource| {
+      resource.bookings.insert(&booking_id, booking);
+    });
+  }
+
+  /// Worst case refunds owed to bookers and payouts held in escrow right now: the
+  /// bookings' liabilities, claimed payouts on their way and open lottery entries.
+  pub(crate) fn refund_liabilities(&self) -> u128 {
+    self.liabilities + self.payouts_in_flight + self.lottery_liabilities
+  }
+
+  /// Payments out of the owner's funds, like cancellation penalties, must not eat
+  /// into what bookers are owed. Checked before anything is cancelled, a cancelled
+  /// booking no longer counts as a liability while its refund is still in the balance.
+  pub(crate) fn assert_withdrawable_covers(&self, amount: u128, what: &str) {
+    let withdrawable = self.get_withdrawable().0;
+    assert!(withdrawable >= amount, "{}: {}, withdrawable: {}", what, amount, withdrawable);
+  }
+
+  pub(crate) fn storage_cost() -> u128 {
+    env::storage_usage() as u128 * env::storage_byte_cost()
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Balance left after storage staking and worst case refund liabilities.
+  pub fn get_withdrawable(&self) -> U128 {
+    let refund_liabilities = self.refund_liabilities();
+    U128::from(
+      env::account_balance()
+        .saturating_sub(Self::storage_cost())
+        .saturating_sub(refund_liabilities)
+    )
+  }
+
+  /// Self diagnostics for monitoring.
+  pub fn health(&self) -> Health {
+    let balance = env::account_balance();
+    let storage_cost = Self::storage_cost();
+    let liquid_balance = balance.saturating_sub(storage_cost);
+    let refund_liabilities = self.refund_liabilities();
+    let now = Self::now_ms();
+    let open_bookings = (self.blocking_bookings(now, u64::MAX).len() + self.unblocked_requests.len() as usize) as u64;
+    let mut warnings = Vec::new();
+    if balance < storage_cost {
+      warnings.push("balance below storage cost".to_string());
+    }
+    if liquid_balance < refund_liabilities {
+      warnings.push("liquid balance below refund liabilities".to_string());
+    }
+    Health {
+      version: env!("CARGO_PKG_VERSION").to_string(),
+      storage_usage: env::storage_usage(),
+      storage_cost: U128::from(storage_cost),
+      balance: U128::from(balance),
+      liquid_balance: U128::from(liquid_balance),
+      refund_liabilities: U128::from(refund_liabilities),
+      bookings: U128::from(self.next_booking_id),
+      open_bookings,
+      warnings,
+    }
+  }
+}
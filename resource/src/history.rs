@@ -0,0 +1,79 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{AdminAction, Pricing, PricingParams, Resource, ResourceExt, SensitiveChange};
+
+const MAX_HISTORY_PAGE_SIZE: u64 = 50;
+
+/// A pricing/policy configuration and the moment it came into force.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PricingVersion {
+  effective_from: u64,
+  pricing: Pricing,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PricingVersionView {
+  pub version: u64,
+  pub effective_from: u64,
+  pub pricing: PricingParams,
+}
+
+impl Resource {
+  /// Must be called whenever `self.pricing` changes, so old bookings can be judged
+  /// by the policy that applied when they were made.
+  pub(crate) fn record_pricing_version(&mut self) {
+    self.pricing_history.push(&PricingVersion {
+      effective_from: env::block_timestamp() / 1_000_000,
+      pricing: self.pricing.clone(),
+    });
+  }
+
+  /// Called by `apply_change` once a pricing change passed the timelock.
+  pub(crate) fn apply_pricing(&mut self, pricing: PricingParams) {
+    self.pricing = Pricing::new(pricing.clone());
+    self.record_pricing_version();
+    let version = self.pricing_history.len() - 1;
+    self.record_admin_action(AdminAction::PricingUpdate { version });
+    self.emit("PricingUpdate", serde_json::json!({
+      "version": version,
+      "pricing": pricing,
+    }));
+    self.report_update();
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Schedules new pricing for new bookings, it applies after the timelock like
+  /// any other `SensitiveChange`. Existing bookings keep the price they paid and
+  /// the refund policy they were made under, so refunds and payouts of old
+  /// bookings don't change. Returns the id of the scheduled change.
+  pub fn update_pricing(&mut self, pricing: PricingParams) -> u64 {
+    self.schedule_change(SensitiveChange::Pricing(Box::new(pricing)))
+  }
+
+  pub fn get_pricing_history(&self, from_index: u64, limit: u64) -> Vec<PricingVersionView> {
+    (from_index..std::cmp::min(from_index + limit.min(MAX_HISTORY_PAGE_SIZE), self.pricing_history.len()))
+      .filter_map(|version| self.pricing_history.get(version).map(|entry| PricingVersionView {
+        version,
+        effective_from: entry.effective_from,
+        pricing: PricingParams::from(&entry.pricing),
+      }))
+      .collect()
+  }
+
+  /// The pricing that was in force at `timestamp_ms`, e.g. the creation time of a booking.
+  pub fn get_pricing_at(&self, timestamp_ms: u64) -> Option<PricingVersionView> {
+    (0..self.pricing_history.len())
+      .rev()
+      .filter_map(|version| self.pricing_history.get(version).map(|entry| (version, entry)))
+      .find(|(_, entry)| entry.effective_from <= timestamp_ms)
+      .map(|(version, entry)| PricingVersionView {
+        version,
+        effective_from: entry.effective_from,
+        pricing: PricingParams::from(&entry.pricing),
+      })
+  }
+}
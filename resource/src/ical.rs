@@ -0,0 +1,99 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::calendar::civil_from_days;
+use crate::privacy::to_hex;
+use crate::{AdminAction, Resource, ResourceExt, MS_PER_DAY};
+
+/// Longest window a single feed request may cover.
+const MAX_FEED_WINDOW_MS: u64 = 400 * MS_PER_DAY;
+
+/// Feed tokens are created off chain (everything sent to or returned from the chain
+/// is public). The owner registers `sha256(token)`, which is stored salted again.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct FeedToken {
+  salt: Vec<u8>,
+  hash: Vec<u8>,
+}
+
+fn salted_hash(salt: &[u8], token_hash: &str) -> Vec<u8> {
+  env::sha256(&[salt, token_hash.as_bytes()].concat())
+}
+
+/// `20240131T235959Z`
+fn ical_timestamp(ms: u64) -> String {
+  let (year, month, day) = civil_from_days((ms / MS_PER_DAY) as i64);
+  let seconds_of_day = ms % MS_PER_DAY / 1000;
+  format!(
+    "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+    year,
+    month,
+    day,
+    seconds_of_day / 3600,
+    seconds_of_day / 60 % 60,
+    seconds_of_day % 60
+  )
+}
+
+impl Resource {
+  fn ical(&self, from: u64, to: u64, include_bookers: bool) -> String {
+    assert!(to > from && to - from <= MAX_FEED_WINDOW_MS, "invalid feed window");
+    let mut lines = vec![
+      "BEGIN:VCALENDAR".to_string(),
+      "VERSION:2.0".to_string(),
+      "PRODID:-//chershare//resource//EN".to_string(),
+      format!("X-WR-CALNAME:{}", self.title),
+    ];
+    for (booking_id, booking) in self.blocking_bookings(from, to) {
+      lines.push("BEGIN:VEVENT".to_string());
+      lines.push(format!("UID:{}@{}", booking_id, env::current_account_id()));
+      lines.push(format!("DTSTAMP:{}", ical_timestamp(booking.created_at)));
+      lines.push(format!("DTSTART:{}", ical_timestamp(booking.start)));
+      lines.push(format!("DTEND:{}", ical_timestamp(booking.end)));
+      if include_bookers {
+        lines.push(format!("SUMMARY:Booked by {}", booking.consumer_account_id));
+      } else {
+        lines.push("SUMMARY:Booked".to_string());
+      }
+      lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.join("\r\n")
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Occupancy between `from` and `to` as an iCalendar document, without booker identities.
+  pub fn get_ical(&self, from: u64, to: u64) -> String {
+    self.ical(from, to, false)
+  }
+
+  /// Like `get_ical` but with the booker of every event, requires the owner's feed token.
+  pub fn get_private_ical(&self, token: String, from: u64, to: u64) -> String {
+    let feed_token = self.feed_token.as_ref().expect("no feed token");
+    let token_hash = to_hex(&env::sha256(token.as_bytes()));
+    assert!(
+      salted_hash(&feed_token.salt, &token_hash) == feed_token.hash,
+      "invalid feed token"
+    );
+    self.ical(from, to, true)
+  }
+
+  /// Registers a new feed token by its hex encoded sha256, replacing any previous one.
+  pub fn create_feed_token(&mut self, token_hash: String) {
+    self.assert_owner();
+    let salt = env::random_seed();
+    self.feed_token = Some(FeedToken {
+      hash: salted_hash(&salt, &token_hash.to_lowercase()),
+      salt,
+    });
+    self.record_admin_action(AdminAction::FeedTokenCreated);
+  }
+
+  pub fn revoke_feed_token(&mut self) {
+    self.assert_owner();
+    assert!(self.feed_token.take().is_some(), "no feed token");
+    self.record_admin_action(AdminAction::FeedTokenRevoked);
+  }
+}
@@ -0,0 +1,84 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::near_bindgen;
+
+use crate::{AdminAction, Booking, BookingStatus, Resource, ResourceExt};
+
+/// Keeps an import batch within one transaction's gas.
+const MAX_IMPORT_BATCH: usize = 50;
+
+/// A reservation taken over from another booking system.
+#[derive(Deserialize, Serialize)]
+pub struct ImportedBooking {
+  pub account_id: String,
+  pub start: u64,
+  pub end: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct BookingImportLog {
+  id: U128,
+  booker_account_id: String,
+  start: u64,
+  end: u64,
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Honors reservations made elsewhere before the resource moved here. They are
+  /// confirmed without payment and only possible until the first booking is made.
+  pub fn import_bookings(&mut self, bookings: Vec<ImportedBooking>) -> Vec<U128> {
+    self.assert_owner();
+    assert!(!self.imports_closed, "imports are closed after the first booking");
+    assert!(bookings.len() <= MAX_IMPORT_BATCH, "too many bookings");
+    let booking_ids: Vec<U128> = bookings.into_iter().map(|imported| {
+      assert!(imported.end > imported.start, "end before start");
+      self.assert_no_booking_collision(imported.start, imported.end);
+      let booking_id = self.next_booking_id;
+      self.next_booking_id += 1;
+      let mut booking = Booking {
+        consumer_account_id: imported.account_id,
+        start: imported.start,
+        end: imported.end,
+        price: 0,
+        commitment_deposit: 0,
+        status: BookingStatus::Confirmed,
+        created_at: Self::now_ms(),
+        cancellation: None,
+        policy: self.current_booking_policy(),
+        refunded: 0,
+        package_id: None,
+        unit: 0,
+        mirror_of: None,
+        imported: true,
+        campaign_id: None,
+        metered: false,
+        checked_in_at: None,
+        checked_out_at: None,
+        deposit_held: false,
+        terms_version: None,
+        coupon_code: None,
+        paid_out: false,
+        exchange_rate: None,
+      };
+      self.add_blockers(booking_id, &mut booking);
+      self.store_booking(booking_id, &booking);
+      let mut account_booking_ids = self.account_bookings.get(&booking.consumer_account_id).unwrap_or_default();
+      account_booking_ids.push(booking_id);
+      self.account_bookings.insert(&booking.consumer_account_id, &account_booking_ids);
+      self.emit("BookingImport", BookingImportLog {
+        id: U128::from(booking_id),
+        booker_account_id: self.booker_label(&booking.consumer_account_id),
+        start: booking.start,
+        end: booking.end,
+      });
+      U128::from(booking_id)
+    }).collect();
+    self.record_admin_action(AdminAction::BookingImport { bookings: booking_ids.len() as u32 });
+    booking_ids
+  }
+
+  pub fn get_imports_closed(&self) -> bool {
+    self.imports_closed
+  }
+}
@@ -0,0 +1,364 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+
+use crate::{AdminAction, BookingStatus, Resource, ResourceExt, SensitiveChange, MS_PER_DAY};
+
+/// Invoices are due this long after the booking was made.
+const INVOICE_TERM_MS: u64 = 30 * MS_PER_DAY;
+/// An overdue invoice is reminded about at most this often.
+const DUNNING_INTERVAL_MS: u64 = 7 * MS_PER_DAY;
+pub(crate) const MAX_LATE_INTEREST_BPS_PER_DAY: u16 = 100;
+const MAX_PROCESS_OVERDUE_LIMIT: u64 = 50;
+
+/// An account the owner trusts to book paying only for storage and pay the price later.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct InvoiceCustomer {
+  pub suspended: bool,
+  /// booking ids with an unpaid invoice
+  pub open_invoices: Vec<u128>,
+  pub outstanding: U128,
+}
+
+impl Default for InvoiceCustomer {
+  fn default() -> Self {
+    Self {
+      suspended: false,
+      open_invoices: Vec::new(),
+      outstanding: U128::from(0),
+    }
+  }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Invoice {
+  pub account_id: String,
+  /// includes accrued late interest
+  pub amount: U128,
+  /// the part of `amount` late interest is charged on, i.e. without interest
+  pub principal: U128,
+  pub due_at: u64,
+  /// late interest is accrued in whole days up to here
+  pub interest_accrued_until: u64,
+  /// the rate in effect when the invoice was opened, later changes don't apply
+  pub late_interest_bps_per_day: u16,
+  pub last_dunning_at: Option<u64>,
+}
+
+/// One booking as a line on an invoice, also used by the factory to consolidate
+/// invoices over several resources.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct InvoiceLine {
+  pub booking_id: U128,
+  pub booker: String,
+  pub start: u64,
+  pub end: u64,
+  pub price: U128,
+  pub refunded: U128,
+  pub commitment_deposit: U128,
+  /// still owed on an open invoice, including late interest
+  pub amount_due: U128,
+  pub status: BookingStatus,
+}
+
+#[derive(Deserialize, Serialize)]
+struct InvoiceLog {
+  booking_id: U128,
+  invoice: Invoice,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DunningLog {
+  booking_id: U128,
+  account_id: String,
+  amount: U128,
+  interest: U128,
+  days_overdue: u64,
+}
+
+impl Resource {
+  /// Unpaid invoices lower what a booking holds for others, see `track_liability`.
+  fn store_invoice(&mut self, booking_id: u128, invoice: Option<&Invoice>) {
+    self.track_liability(booking_id, |resource| match invoice {
+      Some(invoice) => {
+        resource.invoices.insert(&booking_id, invoice);
+      },
+      None => {
+        resource.invoices.remove(&booking_id);
+      },
+    });
+  }
+
+  fn has_overdue_invoice(&self, customer: &InvoiceCustomer) -> bool {
+    let now = Self::now_ms();
+    customer.open_invoices.iter()
+      .filter_map(|booking_id| self.invoices.get(booking_id))
+      .any(|invoice| invoice.due_at <= now)
+  }
+
+  /// Whether `account_id` can currently book paying only for storage, with an invoice for the price.
+  pub(crate) fn may_book_on_invoice(&self, account_id: &String) -> bool {
+    self.invoice_customers.get(account_id)
+      .is_some_and(|customer| !customer.suspended && !self.has_overdue_invoice(&customer))
+  }
+
+  pub(crate) fn open_invoice(&mut self, booking_id: u128, account_id: &String, amount: u128) {
+    let mut customer = self.invoice_customers.get(account_id).unwrap();
+    customer.open_invoices.push(booking_id);
+    customer.outstanding = U128::from(customer.outstanding.0 + amount);
+    self.invoice_customers.insert(account_id, &customer);
+    let due_at = Self::now_ms() + INVOICE_TERM_MS;
+    let invoice = Invoice {
+      account_id: account_id.clone(),
+      amount: U128::from(amount),
+      principal: U128::from(amount),
+      due_at,
+      interest_accrued_until: due_at,
+      late_interest_bps_per_day: self.late_interest_bps_per_day,
+      last_dunning_at: None,
+    };
+    self.store_invoice(booking_id, Some(&invoice));
+    self.emit("InvoiceIssued", &InvoiceLog {
+      booking_id: U128::from(booking_id),
+      invoice,
+    });
+  }
+
+  /// Lowers the invoice of a booking by `amount`, accrued interest first, closing
+  /// it when nothing is left. Returns the part of `amount` that exceeded the invoice.
+  fn credit_invoice(&mut self, booking_id: u128, amount: u128) -> u128 {
+    let Some(mut invoice) = self.invoices.get(&booking_id) else {
+      return amount;
+    };
+    let credited = amount.min(invoice.amount.0);
+    let interest = invoice.amount.0 - invoice.principal.0;
+    invoice.principal = U128::from(invoice.principal.0 - credited.saturating_sub(interest));
+    invoice.amount = U128::from(invoice.amount.0 - credited);
+    let mut customer = self.invoice_customers.get(&invoice.account_id).unwrap_or_default();
+    customer.outstanding = U128::from(customer.outstanding.0 - credited);
+    if invoice.amount.0 == 0 {
+      self.store_invoice(booking_id, None);
+      customer.open_invoices.retain(|open_booking_id| *open_booking_id != booking_id);
+    } else {
+      self.store_invoice(booking_id, Some(&invoice));
+    }
+    self.invoice_customers.insert(&invoice.account_id, &customer);
+    amount - credited
+  }
+
+  /// Adds late interest for the whole days since the last accrual. It is simple
+  /// interest on the principal, so the result doesn't depend on how often this
+  /// runs. Returns the interest added.
+  fn accrue_late_interest(invoice: &mut Invoice, now: u64) -> u128 {
+    let days = now.saturating_sub(invoice.interest_accrued_until) / MS_PER_DAY;
+    if days == 0 {
+      return 0;
+    }
+    invoice.interest_accrued_until += days * MS_PER_DAY;
+    let interest = invoice.principal.0 * invoice.late_interest_bps_per_day as u128 * days as u128 / 10_000;
+    invoice.amount = U128::from(invoice.amount.0 + interest);
+    interest
+  }
+
+  /// Brings the stored invoice of `booking_id` and its customer's balance up to
+  /// date with late interest. Returns the invoice and the interest added.
+  fn update_invoice(&mut self, booking_id: u128, now: u64) -> (Invoice, u128) {
+    let mut invoice = self.invoices.get(&booking_id).expect("no open invoice");
+    let interest = Self::accrue_late_interest(&mut invoice, now);
+    if interest > 0 {
+      self.store_invoice(booking_id, Some(&invoice));
+      let mut customer = self.invoice_customers.get(&invoice.account_id).unwrap();
+      customer.outstanding = U128::from(customer.outstanding.0 + interest);
+      self.invoice_customers.insert(&invoice.account_id, &customer);
+    }
+    (invoice, interest)
+  }
+
+  /// Raises the open invoice of a booking by `amount`. Returns false if the
+  /// booking has no open invoice.
+  pub(crate) fn add_to_invoice(&mut self, booking_id: u128, amount: u128) -> bool {
+    let Some(mut invoice) = self.invoices.get(&booking_id) else {
+      return false;
+    };
+    invoice.amount = U128::from(invoice.amount.0 + amount);
+    invoice.principal = U128::from(invoice.principal.0 + amount);
+    self.store_invoice(booking_id, Some(&invoice));
+    let mut customer = self.invoice_customers.get(&invoice.account_id).unwrap();
+    customer.outstanding = U128::from(customer.outstanding.0 + amount);
+    self.invoice_customers.insert(&invoice.account_id, &customer);
+    true
+  }
+
+  /// Pays a refund of booking `booking_id`, an unpaid invoice is reduced
+  /// instead of sending money that was never received.
+  pub(crate) fn refund(&mut self, booking_id: u128, account_id: &str, amount: u128) {
+    let remaining = self.credit_invoice(booking_id, amount);
+    if remaining > 0 {
+      Promise::new(AccountId::try_from(account_id.to_string()).unwrap()).transfer(remaining);
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Lets `account_id` book on invoice, or lifts a suspension.
+  pub fn add_invoice_customer(&mut self, account_id: String) {
+    self.assert_owner();
+    let customer = self.invoice_customers.get(&account_id).unwrap_or_default();
+    self.invoice_customers.insert(&account_id, &InvoiceCustomer { suspended: false, ..customer });
+    self.record_admin_action(AdminAction::InvoiceCustomerChange { account_id, suspended: false });
+  }
+
+  /// Revokes booking on invoice. The owner can always do this, anyone else only
+  /// once the customer has an overdue invoice. Open invoices stay payable.
+  pub fn suspend_invoice_customer(&mut self, account_id: String) {
+    let mut customer = self.invoice_customers.get(&account_id).expect("not an invoice customer");
+    if env::predecessor_account_id().to_string() != self.owner {
+      assert!(self.has_overdue_invoice(&customer), "no overdue invoice");
+    }
+    customer.suspended = true;
+    self.invoice_customers.insert(&account_id, &customer);
+    self.record_admin_action(AdminAction::InvoiceCustomerChange { account_id, suspended: true });
+  }
+
+  /// Schedules a new late interest rate for invoices opened after the timelock,
+  /// open invoices keep theirs.
+  pub fn set_late_interest(&mut self, bps_per_day: u16) -> u64 {
+    self.schedule_change(SensitiveChange::LateInterest(bps_per_day))
+  }
+
+  pub(crate) fn apply_late_interest(&mut self, bps_per_day: u16) {
+    self.late_interest_bps_per_day = bps_per_day;
+    self.record_admin_action(AdminAction::LateInterestChange { bps_per_day });
+  }
+
+  pub fn get_late_interest(&self) -> u16 {
+    self.late_interest_bps_per_day
+  }
+
+  /// Keeper entry point: accrues late interest on up to `limit` overdue invoices
+  /// not reminded about within `DUNNING_INTERVAL_MS` and emits an `InvoiceOverdue`
+  /// event for each. Returns how many were processed, call again until it is 0.
+  pub fn process_overdue(&mut self, limit: u64) -> u64 {
+    let now = Self::now_ms();
+    let limit = limit.min(MAX_PROCESS_OVERDUE_LIMIT) as usize;
+    let due: Vec<u128> = self.invoice_customers.values()
+      .flat_map(|customer| customer.open_invoices)
+      .filter(|booking_id| self.invoices.get(booking_id).is_some_and(|invoice| {
+        invoice.due_at <= now
+          && invoice.last_dunning_at.is_none_or(|dunned_at| now >= dunned_at + DUNNING_INTERVAL_MS)
+      }))
+      .take(limit)
+      .collect();
+    for booking_id in due.iter().copied() {
+      let (mut invoice, interest) = self.update_invoice(booking_id, now);
+      invoice.last_dunning_at = Some(now);
+      self.store_invoice(booking_id, Some(&invoice));
+      self.emit("InvoiceOverdue", &DunningLog {
+        booking_id: U128::from(booking_id),
+        account_id: invoice.account_id,
+        amount: invoice.amount,
+        interest: U128::from(interest),
+        days_overdue: (now - invoice.due_at) / MS_PER_DAY,
+      });
+    }
+    due.len() as u64
+  }
+
+  /// Pays the open invoice of a booking, anything above the amount due is returned.
+  #[payable]
+  pub fn pay_invoice(&mut self, booking_id: u128) {
+    let (invoice, _) = self.update_invoice(booking_id, Self::now_ms());
+    assert!(env::attached_deposit() >= invoice.amount.0, "due: {}, sent: {}", invoice.amount.0, env::attached_deposit());
+    let change = self.credit_invoice(booking_id, env::attached_deposit());
+    if change > 0 {
+      Promise::new(env::predecessor_account_id()).transfer(change);
+    }
+    self.emit("InvoicePaid", &InvoiceLog {
+      booking_id: U128::from(booking_id),
+      invoice,
+    });
+  }
+
+  /// The invoice of a booking with late interest accrued up to now.
+  pub fn get_invoice(&self, booking_id: U128) -> Option<Invoice> {
+    let mut invoice = self.invoices.get(&booking_id.0)?;
+    Self::accrue_late_interest(&mut invoice, Self::now_ms());
+    Some(invoice)
+  }
+
+  pub fn get_invoice_line(&self, booking_id: U128) -> Option<InvoiceLine> {
+    let booking = self.bookings.get(&booking_id.0)?;
+    Some(InvoiceLine {
+      booking_id,
+      booker: self.booker_label(&booking.consumer_account_id),
+      start: booking.start,
+      end: booking.end,
+      price: U128::from(booking.price),
+      refunded: U128::from(booking.refunded),
+      commitment_deposit: U128::from(booking.commitment_deposit),
+      amount_due: self.get_invoice(booking_id).map_or(U128::from(0), |invoice| invoice.amount),
+      status: booking.status,
+    })
+  }
+
+  pub fn get_invoice_customer(&self, account_id: String) -> Option<InvoiceCustomer> {
+    self.invoice_customers.get(&account_id)
+  }
+
+  /// Accounts receivable, `(account_id, outstanding)` of every customer owing something.
+  pub fn get_receivables(&self) -> Vec<(String, U128)> {
+    self.invoice_customers.iter()
+      .filter(|(_, customer)| customer.outstanding.0 > 0)
+      .map(|(account_id, customer)| (account_id, customer.outstanding))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::json_types::U128;
+  use near_sdk::test_utils::VMContextBuilder;
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::{booking_context, resource};
+  use crate::Pricing;
+
+  /// A resource charging 1 per ms with alice as invoice customer.
+  fn invoicing_resource() -> Resource {
+    let mut resource = resource(1);
+    resource.pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": null,
+      "price_per_unit": null,
+      "nightly": null,
+    })).unwrap());
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .build());
+    resource.add_invoice_customer("alice.near".to_string());
+    resource
+  }
+
+  #[test]
+  fn invoice_customer_pays_only_for_storage() {
+    let mut resource = invoicing_resource();
+    testing_env!(booking_context(0).build());
+    let booking_id = resource.book(100, 200, None, None, None);
+    assert_eq!(resource.get_invoice(booking_id).unwrap().amount, U128::from(100));
+    assert_eq!(resource.get_invoice_customer("alice.near".to_string()).unwrap().outstanding, U128::from(100));
+  }
+
+  #[test]
+  #[should_panic(expected = "required: ")]
+  fn invoiced_booking_pays_for_its_storage() {
+    let mut resource = invoicing_resource();
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("alice.near".parse().unwrap())
+      .build());
+    resource.book(100, 200, None, None, None);
+  }
+}
@@ -0,0 +1,25 @@
+// The account of a resource is created with a full access key of its creator.
+// Owners who rely on the contract's own permissions alone can have the factory
+// rotate or remove it.
+
+use near_sdk::{env, near_bindgen, Promise, PublicKey};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+#[near_bindgen]
+impl Resource {
+  /// Deletes `old_key` from this account and adds `new_key` as full access key
+  /// if given. Only the factory calls this, on request of the owner.
+  pub fn replace_access_key(&mut self, old_key: PublicKey, new_key: Option<PublicKey>) -> Promise {
+    self.assert_factory();
+    self.record_admin_action(AdminAction::AccessKeyChange {
+      removed: String::from(&old_key),
+      added: new_key.as_ref().map(String::from),
+    });
+    let promise = Promise::new(env::current_account_id()).delete_key(old_key);
+    match new_key {
+      Some(new_key) => promise.add_full_access_key(new_key),
+      None => promise,
+    }
+  }
+}
@@ -1,10 +1,14 @@
 use near_sdk::json_types::U128;
-use near_sdk::{env, PanicOnDefault};
+use near_sdk::{env, AccountId, BorshStorageKey, PanicOnDefault, Promise};
 
 use near_sdk::collections::{
+  LazyOption,
   LookupSet, 
   TreeMap, 
-  LookupMap 
+  LookupMap,
+  UnorderedMap,
+  UnorderedSet,
+  Vector,
 };
 use near_sdk::serde::{
     Deserialize,
@@ -14,60 +18,622 @@ use near_sdk::serde::{
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::near_bindgen;
 
+mod access;
+mod allocation;
+mod amendments;
+mod audit;
+mod blackouts;
+mod bookings;
+mod calendar;
+mod campaigns;
+mod closures;
+mod conditions;
+mod config;
+mod coupons;
+mod discounts;
+mod draft;
+mod events;
+mod force_majeure;
+mod health;
+mod history;
+mod lottery;
+mod metadata;
+mod metering;
+mod ical;
+mod imports;
+mod keys;
+mod invoicing;
+mod metrics;
+mod oracle;
+mod migration;
+mod mirror;
+mod multiview;
+mod notices;
+mod overlaps;
+mod packages;
+mod payouts;
+mod phases;
+mod privacy;
+mod rates;
+mod refunds;
+mod reporting;
+mod reschedule;
+mod seasonal;
+mod terms;
+mod season;
+mod timelock;
+mod units;
+mod usage;
+mod weekdays;
+
+pub use access::Visibility;
+pub use amendments::PriceAmendment;
+pub use audit::{AdminAction, AuditEntry};
+pub use blackouts::Blackout;
+pub use bookings::BookingView;
+pub use calendar::{AvailabilityKind, AvailabilityRange, DayView};
+pub use campaigns::Campaign;
+pub use conditions::{ConditionEvidence, ConditionPhase, ConditionReport};
+pub use config::ResourceConfig;
+pub use coupons::{Coupon, CouponDiscount};
+pub use discounts::{DurationDiscount, LastMinuteRule};
+pub use draft::DraftUpdate;
+pub use events::{ChangeKind, ChangeRecord};
+pub use force_majeure::ForceMajeure;
+pub use health::Health;
+pub use history::{PricingVersion, PricingVersionView};
+pub use ical::FeedToken;
+pub use imports::ImportedBooking;
+pub use invoicing::{Invoice, InvoiceCustomer, InvoiceLine};
+pub use lottery::{Lottery, LotteryEntry};
+pub use metadata::MetadataUpdate;
+pub use metrics::{Reliability, ReliabilityBucket, ResponseStats};
+pub use notices::{Notice, OwnerDashboard};
+pub use packages::{Package, PackageOffer};
+pub use chershare_common::pagination::Page;
+pub use phases::BookingPhase;
+pub use rates::{ExchangeRate, PriceCurrency};
+pub use refunds::RefundQuote;
+pub use reporting::{ResourceStatus, ResourceSummary};
+pub use reschedule::ModificationPolicy;
+pub use season::ActiveWindow;
+pub use seasonal::{PriceSegment, Quote, SeasonalRate, SeasonalRateKind};
+pub use terms::TermsVersion;
+pub use timelock::{ScheduledChange, SensitiveChange};
+
+pub use units::{near_string_to_yocto, yocto_to_near_string};
+pub use usage::{UsagePricing, UsageReport};
+pub use weekdays::WeekdayRates;
+
+use rates::to_yocto;
+
+/// Prefixes of all persistent collections. New collections get a new variant at
+/// the end, never reorder existing ones.
+#[derive(BorshSerialize, BorshStorageKey)]
+pub(crate) enum StorageKey {
+  // replaced by `Images`, a `LookupSet` that couldn't be enumerated
+  #[allow(dead_code)]
+  ImageUrls,
+  Tags,
+  // replaced by `Blockers`, kept so the prefixes after them don't move
+  #[allow(dead_code)]
+  BlockerStarts,
+  #[allow(dead_code)]
+  BlockerEnds,
+  // never deployed, bookings went straight to `BookingRecords`
+  #[allow(dead_code)]
+  Bookings,
+  FreeBookingCounts,
+  AuditLog,
+  PricingHistory,
+  ScheduledChanges,
+  Description,
+  Notices,
+  AccountBookings,
+  // replaced by `AllowedAccounts`, an `UnorderedSet` whose pages shifted on removals
+  #[allow(dead_code)]
+  Allowlist,
+  MemberTiers,
+  Lotteries,
+  InvoiceCustomers,
+  Invoices,
+  PriceAmendments,
+  RecentChanges,
+  UnblockedRequests,
+  AllowedAccounts,
+  Packages,
+  Blockers,
+  MirroredBookings,
+  // replaced by `PayoutsDueById`, an `UnorderedSet` that couldn't be paged by id
+  #[allow(dead_code)]
+  PayoutsDue,
+  PayoutsDueById,
+  BookingRecords,
+  FirstBookingDiscountsUsed,
+  Blackouts,
+  BlackoutStarts,
+  Images,
+  UsageReports,
+  HeldDeposits,
+  ConditionReports,
+  FinalizedRevenue,
+  FinalizedPayments,
+  Terms,
+  // replaced by `CouponsByCode`, a `LookupMap` that couldn't be exported
+  #[allow(dead_code)]
+  Coupons,
+  CouponsByCode,
+}
+
 #[derive(Deserialize, Serialize)]
 struct BookingCreationLog {
   id: U128,
   booker_account_id: String, 
   start: u64, 
   end: u64, 
-  price: U128
+  price: U128,
+  price_near: String,
+  exchange_rate: Option<ExchangeRate>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct BookingApprovalLog {
+  id: U128,
+}
+
+#[derive(Deserialize, Serialize)]
+struct BookingRejectionLog {
+  id: U128,
+  refund_amount: U128,
+  reason_text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct BookingRequestExpiryLog {
+  id: U128,
+  refund_amount: U128,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CheckInLog {
+  id: U128,
+  returned_deposit: U128,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DepositForfeitLog {
+  id: U128,
+  forfeited_deposit: U128,
+}
+
+#[derive(Deserialize, Serialize)]
+struct OwnerCancellationLog {
+  id: U128,
+  refund_amount: U128,
+  penalty: U128,
+  reason: Option<CancellationReason>,
+  reason_text: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
 struct BookingCancellationLog {
   id: U128, 
-  refund_amount: U128
+  refund_amount: U128,
+  refund_amount_near: String,
+  by_owner: bool,
+  reason: Option<CancellationReason>,
+  reason_text: Option<String>,
+  force_majeure: bool,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+pub(crate) const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+pub(crate) const MAX_REASON_TEXT_LENGTH: usize = 280;
+/// How long after a booking ended (or was cancelled) it can still be disputed.
+pub(crate) const DISPUTE_WINDOW_MS: u64 = 30 * MS_PER_DAY;
+const DEFAULT_APPROVAL_TIMEOUT_MS: u64 = 2 * MS_PER_DAY;
+const DEFAULT_TIMELOCK_MS: u64 = MS_PER_DAY;
+const DEFAULT_GRACE_PERIOD_MS: u64 = 60 * 60 * 1000;
+const MAX_REFUND_TIERS: usize = 10;
+/// Upper bound of the storage a booking adds, see `get_required_deposit`.
+const MAX_BOOKING_STORAGE_BYTES: u64 = 1_000;
+
+fn default_instant_book() -> bool {
+  true
+}
+
+fn default_capacity() -> u32 {
+  1
+}
+
+/// Time unit the duration based part of the price is charged in.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PricingUnit {
+  #[default]
+  Millisecond,
+  Hour,
+  Day,
+  /// nights between check-in and check-out, see `NightlyConfig`
+  Night,
+}
+
+impl PricingUnit {
+  fn ms(&self) -> u64 {
+    match self {
+      PricingUnit::Millisecond => 1,
+      PricingUnit::Hour => 60 * 60 * 1000,
+      PricingUnit::Day | PricingUnit::Night => MS_PER_DAY,
+    }
+  }
+}
+
+/// Day boundaries for nightly pricing, accommodation style. Bookings start at the
+/// check-in hour and end at the check-out hour, local time.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy)]
+pub struct NightlyConfig {
+  pub utc_offset_minutes: i16,
+  pub check_in_hour: u8,
+  pub check_out_hour: u8,
+}
+
+impl NightlyConfig {
+  fn local_ms(&self, timestamp_ms: u64) -> i64 {
+    timestamp_ms as i64 + self.utc_offset_minutes as i64 * 60 * 1000
+  }
+
+  fn local_day(&self, timestamp_ms: u64) -> i64 {
+    self.local_ms(timestamp_ms).div_euclid(MS_PER_DAY as i64)
+  }
+
+  fn is_at_hour(&self, timestamp_ms: u64, hour: u8) -> bool {
+    self.local_ms(timestamp_ms).rem_euclid(MS_PER_DAY as i64) == hour as i64 * 60 * 60 * 1000
+  }
+
+  pub fn nights(&self, from: u64, until: u64) -> u64 {
+    (self.local_day(until) - self.local_day(from)).max(0) as u64
+  }
+
+  pub fn assert_aligned(&self, from: u64, until: u64) {
+    assert!(self.is_at_hour(from, self.check_in_hour), "check-in is at {}:00", self.check_in_hour);
+    assert!(self.is_at_hour(until, self.check_out_hour), "check-out is at {}:00", self.check_out_hour);
+    assert!(self.nights(from, until) > 0, "stay at least one night");
+  }
+}
+
+/// How partial units and proportional refunds are rounded.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+  #[default]
+  Up,
+  Down,
+  /// half up
+  Nearest,
+}
+
+impl Rounding {
+  pub fn div(&self, numerator: u128, denominator: u128) -> u128 {
+    match self {
+      Rounding::Up => numerator.div_ceil(denominator),
+      Rounding::Down => numerator / denominator,
+      Rounding::Nearest => (numerator + denominator / 2) / denominator,
+    }
+  }
+}
+
+/// Share of the price refunded when a booking is cancelled at least
+/// `min_notice_ms` before its start, e.g. 50% if more than 48 hours ahead.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy)]
+pub struct RefundTier {
+  pub min_notice_ms: u64,
+  pub refund_bps: u16,
+}
+
+/// A fixed price for bookings of exactly `duration_ms`, e.g. "4 hours".
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct DurationPreset {
+  pub label: String,
+  pub duration_ms: u64,
+  pub price: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
 pub struct PricingParams {
   price_per_ms: U128,
   price_per_booking: U128,
+  /// without `refund_tiers` the refund falls linearly from the full price this
+  /// long before the start to nothing at the start
+  #[serde(default)]
   full_refund_period_ms: u64,
+  /// replace the linear refund, the tier with the longest notice that still
+  /// applies counts, cancellations with less notice than every tier get nothing
+  #[serde(default)]
+  refund_tiers: Vec<RefundTier>,
+  // a resource without any price has to opt in explicitly, free bookings cost nothing to spam
+  #[serde(default)]
+  allow_free_bookings: bool,
+  #[serde(default)]
+  max_free_bookings_per_day: u32,
+  // refundable on check-in, forfeited to the owner on no-show
+  commitment_deposit_per_ms: Option<U128>,
+  /// charge per hour or day instead of per ms, started units are rounded by `rounding`
+  #[serde(default)]
+  unit: PricingUnit,
+  /// required for units other than `Millisecond`, which use `price_per_ms`
+  price_per_unit: Option<U128>,
+  #[serde(default)]
+  rounding: Rounding,
+  /// required for the `Night` unit
+  nightly: Option<NightlyConfig>,
+  /// bookings matching a preset's duration pay its price instead
+  #[serde(default)]
+  duration_presets: Vec<DurationPreset>,
+  /// reject bookings that match no preset
+  #[serde(default)]
+  presets_only: bool,
+  /// longest booking accepted
+  #[serde(default)]
+  max_duration_ms: Option<u64>,
+  /// how far ahead of its start a booking can be made
+  #[serde(default)]
+  max_advance_ms: Option<u64>,
+  /// how far ahead of its start a booking has to be made at least
+  #[serde(default)]
+  min_notice_ms: u64,
+  /// bookings pay for their whole window up front and get the time they didn't
+  /// use back on `check_out`
+  #[serde(default)]
+  metered: bool,
+  /// date ranges charged at a different rate, must not overlap
+  #[serde(default)]
+  seasonal_rates: Vec<SeasonalRate>,
+  /// per ms prices by day of the week instead of `price_per_ms`
+  #[serde(default)]
+  weekday_rates: Option<WeekdayRates>,
+  /// long stay discounts, the one with the longest threshold below the
+  /// duration applies
+  #[serde(default)]
+  duration_discounts: Vec<DurationDiscount>,
 }
 
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
 pub struct Pricing {
   price_fixed_base: u128,
   price_per_ms: u128,
   refund_buffer: u64,
+  refund_tiers: Vec<RefundTier>,
+  allow_free_bookings: bool,
+  max_free_bookings_per_day: u32,
+  commitment_deposit_per_ms: u128,
+  unit: PricingUnit,
+  price_per_unit: u128,
+  rounding: Rounding,
+  nightly: Option<NightlyConfig>,
+  duration_presets: Vec<DurationPreset>,
+  presets_only: bool,
+  max_duration_ms: Option<u64>,
+  max_advance_ms: Option<u64>,
+  min_notice_ms: u64,
+  metered: bool,
+  seasonal_rates: Vec<SeasonalRate>,
+  weekday_rates: Option<WeekdayRates>,
+  duration_discounts: Vec<DurationDiscount>,
+}
+
+impl From<&Pricing> for PricingParams {
+  fn from(pricing: &Pricing) -> Self {
+    Self {
+      price_per_ms: U128::from(pricing.price_per_ms),
+      price_per_booking: U128::from(pricing.price_fixed_base),
+      full_refund_period_ms: pricing.refund_buffer,
+      refund_tiers: pricing.refund_tiers.clone(),
+      allow_free_bookings: pricing.allow_free_bookings,
+      max_free_bookings_per_day: pricing.max_free_bookings_per_day,
+      commitment_deposit_per_ms: Some(U128::from(pricing.commitment_deposit_per_ms)),
+      unit: pricing.unit,
+      price_per_unit: Some(U128::from(pricing.price_per_unit)),
+      rounding: pricing.rounding,
+      nightly: pricing.nightly,
+      duration_presets: pricing.duration_presets.clone(),
+      presets_only: pricing.presets_only,
+      max_duration_ms: pricing.max_duration_ms,
+      max_advance_ms: pricing.max_advance_ms,
+      min_notice_ms: pricing.min_notice_ms,
+      metered: pricing.metered,
+      seasonal_rates: pricing.seasonal_rates.clone(),
+      weekday_rates: pricing.weekday_rates.clone(),
+      duration_discounts: pricing.duration_discounts.clone(),
+    }
+  }
 }
 
 impl Pricing {
   pub fn new(init_params: PricingParams) -> Self {
-    return Self {
+    let price_per_unit = match init_params.unit {
+      PricingUnit::Millisecond => init_params.price_per_ms.0,
+      _ => init_params.price_per_unit.expect("price_per_unit is required for this unit").0,
+    };
+    if init_params.unit == PricingUnit::Night {
+      let nightly = init_params.nightly.expect("nightly config is required for nightly pricing");
+      assert!(nightly.check_in_hour < 24 && nightly.check_out_hour < 24, "invalid check-in/check-out hour");
+    }
+    if init_params.price_per_booking.0 == 0 && price_per_unit == 0 {
+      assert!(
+        init_params.allow_free_bookings,
+        "free bookings must be enabled with allow_free_bookings"
+      );
+      assert!(
+        init_params.max_free_bookings_per_day > 0,
+        "max_free_bookings_per_day must be set for free bookings"
+      );
+    }
+    assert!(
+      init_params.weekday_rates.is_none() || init_params.unit == PricingUnit::Millisecond,
+      "weekday rates are per ms"
+    );
+    if let Some(weekday_rates) = &init_params.weekday_rates {
+      weekday_rates.assert_valid();
+    }
+    let mut refund_tiers = init_params.refund_tiers;
+    assert!(refund_tiers.len() <= MAX_REFUND_TIERS, "too many refund tiers");
+    assert!(refund_tiers.iter().all(|tier| tier.refund_bps <= 10_000), "refund above 100%");
+    refund_tiers.sort_by_key(|tier| std::cmp::Reverse(tier.min_notice_ms));
+    Self {
       price_fixed_base: init_params.price_per_booking.0, 
       price_per_ms: init_params.price_per_ms.0, 
-      refund_buffer: init_params.full_refund_period_ms
+      refund_buffer: init_params.full_refund_period_ms,
+      refund_tiers,
+      allow_free_bookings: init_params.allow_free_bookings,
+      max_free_bookings_per_day: init_params.max_free_bookings_per_day,
+      commitment_deposit_per_ms: init_params.commitment_deposit_per_ms.map_or(0, |deposit| deposit.0),
+      unit: init_params.unit,
+      price_per_unit,
+      rounding: init_params.rounding,
+      nightly: init_params.nightly,
+      duration_presets: init_params.duration_presets,
+      presets_only: init_params.presets_only,
+      max_duration_ms: init_params.max_duration_ms,
+      max_advance_ms: init_params.max_advance_ms,
+      min_notice_ms: init_params.min_notice_ms,
+      metered: init_params.metered,
+      seasonal_rates: seasonal::sorted_seasonal_rates(init_params.seasonal_rates),
+      weekday_rates: init_params.weekday_rates,
+      duration_discounts: discounts::sorted_duration_discounts(init_params.duration_discounts),
     }
   }
 
+  fn preset(&self, from: u64, until: u64) -> Option<&DurationPreset> {
+    self.duration_presets.iter().find(|preset| preset.duration_ms == until - from)
+  }
+
+  pub fn get_duration_presets(&self) -> Vec<DurationPreset> {
+    self.duration_presets.clone()
+  }
+
+  /// Rejects intervals the pricing can't charge for, i.e. stays not aligned to
+  /// check-in and check-out in nightly mode, and bookings that are too long.
+  pub fn assert_valid_interval(&self, from: u64, until: u64) {
+    if let Some(max_duration_ms) = self.max_duration_ms {
+      assert!(until - from <= max_duration_ms, "booking longer than {} ms", max_duration_ms);
+    }
+    if self.presets_only {
+      assert!(self.preset(from, until).is_some(), "duration has to match a preset");
+    }
+    if let (PricingUnit::Night, Some(nightly)) = (self.unit, self.nightly) {
+      nightly.assert_aligned(from, until);
+    }
+  }
+
+  /// Rejects bookings made later than `min_notice_ms` or earlier than
+  /// `max_advance_ms` before their start.
+  pub fn assert_within_advance_window(&self, from: u64, now: u64) {
+    assert!(from >= now.saturating_add(self.min_notice_ms), "booking starts too soon");
+    if let Some(max_advance_ms) = self.max_advance_ms {
+      assert!(from <= now.saturating_add(max_advance_ms), "booking starts too far in the future");
+    }
+  }
+
+  pub fn get_commitment_deposit(&self, from: u64, until: u64) -> u128 {
+    ((until - from) as u128) * self.commitment_deposit_per_ms
+  }
+
+  /// Price of a one hour booking (one night in nightly mode), what the factory
+  /// indexes resources by.
+  pub fn get_base_price(&self) -> u128 {
+    if self.unit == PricingUnit::Night {
+      return self.price_fixed_base + self.price_per_unit;
+    }
+    self.get_price(0, 60 * 60 * 1000)
+  }
+
+  /// Price after long stay discounts.
   pub fn get_price(&self, from: u64, until:u64) -> u128 {
-    return self.price_fixed_base + ((until - from) as u128) * self.price_per_ms; 
-  }
-  pub fn get_refund_amount(&self, from: u64, until:u64, now: u64) -> u128 {
-    let price_payed = self.get_price(from, until);
-    if now < from {
-      let distance = from - now; 
-      if distance < self.refund_buffer { 
-        price_payed * distance as u128 / self.refund_buffer as u128
-      } else {
-        price_payed
-      }
+    let gross = self.get_gross_price(from, until);
+    gross - self.get_duration_discount(from, until, gross)
+  }
+
+  /// Price before long stay discounts.
+  pub fn get_gross_price(&self, from: u64, until: u64) -> u128 {
+    let segments: u128 = self.get_price_segments(from, until).iter().map(|segment| segment.price.0).sum();
+    match self.preset(from, until) {
+      Some(_) => segments,
+      None => self.price_fixed_base + segments,
+    }
+  }
+
+  /// The per booking price, unless a duration preset replaces the whole price.
+  pub fn get_price_per_booking(&self, from: u64, until: u64) -> u128 {
+    if self.preset(from, until).is_some() { 0 } else { self.price_fixed_base }
+  }
+
+  /// Price of the duration at the regular rate, without seasonal rates.
+  fn get_duration_price(&self, from: u64, until: u64) -> u128 {
+    if let Some(weekday_rates) = &self.weekday_rates {
+      return weekday_rates.price(from, until);
+    }
+    let units = match (self.unit, self.nightly) {
+      (PricingUnit::Night, Some(nightly)) => nightly.nights(from, until) as u128,
+      _ => self.rounding.div((until - from) as u128, self.unit.ms() as u128),
+    };
+    units * self.price_per_unit
+  }
+}
+
+/// The terms a booking was made under. Copied onto every booking at creation so
+/// later changes by the owner never alter what an existing booker is owed.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct BookingPolicy {
+  refund_buffer: u64,
+  #[serde(default)]
+  refund_tiers: Vec<RefundTier>,
+  rounding: Rounding,
+  /// paid on top of the full refund when the owner cancels
+  owner_cancellation_penalty_bps: u16,
+  approval_timeout_ms: u64,
+  grace_period_ms: u64,
+  modification: ModificationPolicy,
+}
+
+impl BookingPolicy {
+  pub fn get_refund_amount(&self, price_payed: u128, from: u64, booked_at: u64, now: u64) -> u128 {
+    if self.is_fully_refundable(from, booked_at, now) {
+      price_payed
+    } else if let Some(refund_bps) = self.tier_refund_bps(from, now) {
+      self.rounding.div(price_payed * refund_bps as u128, 10_000)
+    } else if now < from {
+      self.rounding.div(price_payed * (from - now) as u128, self.refund_buffer as u128)
     } else {
       0 
     }
   } // fees will not be payed back due to technical reasons
+
+  pub fn is_fully_refundable(&self, from: u64, booked_at: u64, now: u64) -> bool {
+    // cooling off right after booking, no matter how close the start is
+    now < from && (
+      now < booked_at + self.grace_period_ms
+        || match self.tier_refund_bps(from, now) {
+          Some(refund_bps) => refund_bps == 10_000,
+          None => from - now >= self.refund_buffer,
+        }
+    )
+  }
+
+  /// `None` without tiers, tiers are sorted by descending notice.
+  fn tier_refund_bps(&self, from: u64, now: u64) -> Option<u16> {
+    if self.refund_tiers.is_empty() {
+      return None;
+    }
+    let notice = from.saturating_sub(now);
+    Some(self.refund_tiers.iter().find(|tier| notice >= tier.min_notice_ms).map_or(0, |tier| tier.refund_bps))
+  }
+}
+
+/// Whether pending booking requests block the calendar.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverbookingPolicy {
+  /// a request holds its slot until it is approved, rejected or expired
+  #[default]
+  ExclusiveHold,
+  /// overlapping requests are accepted, the first one approved gets the slot
+  FirstApprovedWins,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -77,9 +643,97 @@ pub struct ResourceInitParams {
   pub image_urls: Vec<String>, 
   pub contact: String, 
   pub tags: Vec<String>,
+  pub category: Option<String>,
   pub pricing: PricingParams,  
   pub coordinates: [f32; 2], 
   pub min_duration_ms: u64,
+  // when false, bookings are requests the owner has to approve
+  #[serde(default = "default_instant_book")]
+  pub instant_book: bool,
+  pub approval_timeout_ms: Option<u64>,
+  #[serde(default)]
+  pub overbooking_policy: OverbookingPolicy,
+  /// delay before pricing/policy/beneficiary changes take effect
+  pub timelock_ms: Option<u64>,
+  /// cancellations within this period after booking are always fully refunded
+  pub grace_period_ms: Option<u64>,
+  /// hide booker account ids in events and public views
+  #[serde(default)]
+  pub privacy_mode: bool,
+  #[serde(default)]
+  pub visibility: Visibility,
+  #[serde(default)]
+  pub modification_policy: Option<ModificationPolicy>,
+  /// start out as a `Draft` that is finished with `update_draft` and `publish`
+  #[serde(default)]
+  pub draft: bool,
+  /// number of identical units, e.g. bikes, that can be booked at the same time
+  #[serde(default = "default_capacity")]
+  pub capacity: u32,
+}
+
+/// What a new booking pays, in yocto NEAR.
+pub(crate) struct Charge {
+  pub price: u128,
+  pub commitment_deposit: u128,
+  pub exchange_rate: Option<ExchangeRate>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct FreeBookingCount {
+  day: u64,
+  count: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy)]
+pub enum CancellationReason {
+  ChangeOfPlans,
+  Illness,
+  Weather,
+  ResourceUnavailable,
+  DoubleBooking,
+  Other,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Cancellation {
+  by_owner: bool,
+  reason: Option<CancellationReason>,
+  reason_text: Option<String>,
+  cancelled_at: u64,
+}
+
+/// A blocked slot of one unit, keyed by `(unit, start)` in `Resource::blockers`.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Interval {
+  end: u64,
+  booking_id: u128,
+}
+
+/// Where a booking is in its lifecycle, see `can_become` for the legal transitions.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BookingStatus {
+  /// a request waiting for the owner's approval
+  Pending,
+  Confirmed,
+  CheckedIn,
+  /// ended and settled with the owner
+  Completed,
+  Cancelled,
+  Disputed,
+}
+
+impl BookingStatus {
+  pub fn can_become(self, next: BookingStatus) -> bool {
+    use BookingStatus::*;
+    matches!(
+      (self, next),
+      (Pending, Confirmed | Cancelled)
+        | (Confirmed, CheckedIn | Completed | Cancelled | Disputed)
+        | (CheckedIn, Completed | Cancelled | Disputed)
+        | (Disputed, Completed | Cancelled)
+    )
+  }
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -88,24 +742,201 @@ pub struct Booking {
   start: u64, 
   end: u64, 
   price: u128, 
+  commitment_deposit: u128,
+  status: BookingStatus,
+  created_at: u64,
+  /// details of the cancellation once `status` is `Cancelled`
+  cancellation: Option<Cancellation>,
+  policy: BookingPolicy,
+  /// refunded by the owner through `issue_refund`
+  refunded: u128,
+  /// paid with a credit of this package instead of the price
+  package_id: Option<u64>,
+  /// unit the booking occupies, assigned when it starts blocking
+  unit: u32,
+  /// blocks the slot of this booking on the twin, see `mirror_block`
+  mirror_of: Option<u128>,
+  /// taken over from another system by `import_bookings`
+  imported: bool,
+  /// discount campaign the price was reduced by
+  campaign_id: Option<u64>,
+  /// charged for the time between check-in and check-out only, see `check_out`
+  metered: bool,
+  checked_in_at: Option<u64>,
+  checked_out_at: Option<u64>,
+  /// commitment deposit kept at check-in as security for usage surcharges
+  deposit_held: bool,
+  /// version of the terms in effect when the booking was made
+  terms_version: Option<u64>,
+  /// promo code redeemed on the booking
+  coupon_code: Option<String>,
+  /// the price left escrow, see `complete_booking`
+  paid_out: bool,
+  /// rate price and deposit were converted at, for resources priced in another currency
+  exchange_rate: Option<ExchangeRate>,
+}
+
+impl Booking {
+  pub(crate) fn set_status(&mut self, status: BookingStatus) {
+    assert!(
+      self.status.can_become(status),
+      "booking can't go from {:?} to {:?}",
+      self.status,
+      status
+    );
+    self.status = status;
+  }
+
+  /// Paid on top of the refund when the owner cancels, out of the owner's funds.
+  pub(crate) fn owner_cancellation_penalty(&self) -> u128 {
+    self.price * self.policy.owner_cancellation_penalty_bps as u128 / 10_000
+  }
+
+  pub(crate) fn is_pending(&self) -> bool {
+    self.status == BookingStatus::Pending
+  }
+
+  pub(crate) fn is_checked_in(&self) -> bool {
+    self.status == BookingStatus::CheckedIn
+  }
+
+  /// Amounts of a settled booking can't change, its price is no longer in escrow.
+  pub(crate) fn assert_in_escrow(&self) {
+    assert!(!self.paid_out && self.status != BookingStatus::Completed, "booking is already paid out");
+  }
+
+  pub(crate) fn cancel(&mut self, cancellation: Cancellation) {
+    self.set_status(BookingStatus::Cancelled);
+    self.cancellation = Some(cancellation);
+  }
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Resource {
   owner: String, 
+  /// the factory that deployed this resource, it receives metadata updates
+  factory: String,
   title: String, 
-  description: String, 
+  // only read by views, kept out of the main state so bookings don't pay for loading it
+  description: LazyOption<String>, 
   pricing: Pricing, 
   min_duration_ms: u64, 
   contact: String, 
-  image_urls: LookupSet<String>, 
-  tags: LookupSet<String>, 
+  image_urls: UnorderedSet<String>, 
+  tags: UnorderedSet<String>, 
+  category: Option<String>,
+  status: ResourceStatus,
   next_booking_id: u128,
-  blocker_starts: TreeMap<u64, u128>, 
-  blocker_ends: TreeMap<u64, u128>, 
-  bookings: LookupMap<u128, Booking>, 
+  /// blocking bookings by `(unit, start)`, the intervals of a unit never overlap
+  blockers: TreeMap<(u32, u64), Interval>, 
+  bookings: UnorderedMap<u128, Booking>, 
   coordinates: [f32; 2], 
+  free_booking_counts: LookupMap<String, FreeBookingCount>, 
+  instant_book: bool,
+  approval_timeout_ms: u64,
+  overbooking_policy: OverbookingPolicy,
+  audit_log: Vector<AuditEntry>,
+  pricing_history: Vector<PricingVersion>,
+  /// receives forfeited deposits and payouts, the owner unless changed
+  beneficiary: String,
+  timelock_ms: u64,
+  grace_period_ms: u64,
+  modification_policy: ModificationPolicy,
+  scheduled_changes: UnorderedMap<u64, ScheduledChange>,
+  next_change_id: u64,
+  notices: Vector<Notice>,
+  notices_read: u64,
+  response_stats: ResponseStats,
+  reliability_buckets: Vec<ReliabilityBucket>,
+  feed_token: Option<FeedToken>,
+  privacy_mode: bool,
+  /// salts `booker_label`, readable in state like everything else
+  privacy_salt: Vec<u8>,
+  /// booking ids of every consumer
+  account_bookings: LookupMap<String, Vec<u128>>,
+  visibility: Visibility,
+  /// accounts allowed to book a private resource
+  allowlist: TreeMap<String, ()>,
+  active_window: ActiveWindow,
+  /// early access for tiers, see `set_booking_phases`
+  booking_phases: Vec<BookingPhase>,
+  member_tiers: LookupMap<String, String>,
+  /// contested slots allocated by drawing, see `create_lottery`
+  lotteries: UnorderedMap<u64, Lottery>,
+  next_lottery_id: u64,
+  invoice_customers: UnorderedMap<String, InvoiceCustomer>,
+  /// unpaid invoices by booking id
+  invoices: LookupMap<u128, Invoice>,
+  late_interest_bps_per_day: u16,
+  /// owner proposals awaiting the consumer's consent, by booking id
+  price_amendments: LookupMap<u128, PriceAmendment>,
+  /// sequence number of the next emitted event
+  event_seq: u64,
+  /// ring buffer of the latest booking changes
+  recent_changes: Vector<ChangeRecord>,
+  changes_recorded: u64,
+  /// lowest `event_seq` `get_changes_since` can still serve
+  changes_lost_before: u64,
+  /// pending requests kept out of the blockers under `FirstApprovedWins`
+  unblocked_requests: UnorderedSet<u128>,
+  /// on-chain aggregator that is told about creation and every summary change
+  indexer_account: Option<AccountId>,
+  /// copied onto new bookings, see `owner_cancel_booking`
+  owner_cancellation_penalty_bps: u16,
+  package_offers: Vec<PackageOffer>,
+  campaigns: Vec<Campaign>,
+  next_campaign_id: u64,
+  first_booking_discount_bps: u16,
+  last_minute_rule: Option<LastMinuteRule>,
+  /// owner blocked periods by start, they never overlap
+  blackouts: TreeMap<u64, Blackout>,
+  blackout_starts: LookupMap<u64, u64>,
+  next_blackout_id: u64,
+  force_majeure: Option<ForceMajeure>,
+  /// turnover time kept free between consecutive bookings of a unit
+  buffer_ms: u64,
+  usage_pricing: Option<UsagePricing>,
+  usage_reports: LookupMap<u128, UsageReport>,
+  held_deposits: UnorderedSet<u128>,
+  condition_reports: LookupMap<u128, Vec<ConditionReport>>,
+  /// account allowed to check bookings in and out, e.g. a smart lock gateway
+  oracle: Option<String>,
+  /// how long after check-out a held deposit waits for a usage report
+  deposit_release_ms: u64,
+  /// paid out booking revenue per month, see `get_finalized_revenue`
+  finalized_revenue: LookupMap<u32, u128>,
+  terms: Vector<TermsVersion>,
+  coupons: UnorderedMap<String, Coupon>,
+  first_booking_discounts_used: LookupSet<String>,
+  /// bought packages by account, see `buy_package`
+  packages: LookupMap<String, Vec<Package>>,
+  next_package_id: u64,
+  capacity: u32,
+  /// see `resolve_overlap`
+  overlap_compensation_bps: u16,
+  /// another listing of the same asset, see `set_twin`
+  twin: Option<String>,
+  /// local mirror booking by the twin's booking id
+  mirrored_bookings: LookupMap<u128, u128>,
+  /// set by the first booking, see `import_bookings`
+  imports_closed: bool,
+  /// bookings whose price is held in escrow until `claim_payout`
+  payouts_due: TreeMap<u128, ()>,
+  /// claimed payouts waiting for the factory's fee split
+  payouts_in_flight: u128,
+  /// sum of what bookings hold for others, kept by `store_booking`
+  liabilities: u128,
+  /// price and deposit paid by open lottery entries
+  lottery_liabilities: u128,
+  /// next booking of the first version to move, see `migrate_bookings`
+  legacy_bookings_from: Option<u128>,
+  /// currency prices are set in, `None` for NEAR
+  price_currency: Option<PriceCurrency>,
+  /// latest rate pushed by the feed of `price_currency`
+  exchange_rate: Option<ExchangeRate>,
+  /// bookings in escrow whose dispute window closed, see `finalize_payments`
+  finalized_payments: LookupSet<u128>,
 }
 
 #[near_bindgen]
@@ -113,109 +944,818 @@ impl Resource {
   #[init]
   pub fn init(
     owner: String, 
-    init_params: ResourceInitParams
+    init_params: ResourceInitParams,
+    indexer_account: Option<AccountId>,
+  ) -> Self {
+    let mut resource = Self::new(owner, init_params, indexer_account, env::predecessor_account_id().to_string());
+    resource.announce();
+    resource
+  }
+
+  pub(crate) fn new(
+    owner: String,
+    init_params: ResourceInitParams,
+    indexer_account: Option<AccountId>,
+    factory: String,
   ) -> Self {
+    assert!(init_params.capacity > 0, "capacity has to be at least 1");
     let pricing = Pricing::new(init_params.pricing);
     let mut resource = Self {
+      beneficiary: owner.clone(),
+      factory,
       owner, 
       title: init_params.title, 
-      description: init_params.description, 
+      description: LazyOption::new(StorageKey::Description, Some(&init_params.description)), 
       pricing, 
       contact: init_params.contact, 
-      image_urls: LookupSet::new(b"i"), 
-      tags: LookupSet::new(b"t"), 
-      blocker_starts: TreeMap::new(b"b"), 
-      blocker_ends: TreeMap::new(b"e"), 
-      bookings: LookupMap::new(b"k"),
+      image_urls: UnorderedSet::new(StorageKey::Images), 
+      tags: UnorderedSet::new(StorageKey::Tags), 
+      category: init_params.category,
+      status: if init_params.draft { ResourceStatus::Draft } else { ResourceStatus::Active },
+      blockers: TreeMap::new(StorageKey::Blockers), 
+      bookings: UnorderedMap::new(StorageKey::BookingRecords),
       coordinates: init_params.coordinates, 
       min_duration_ms: init_params.min_duration_ms, 
-      next_booking_id: 0
+      next_booking_id: 0,
+      free_booking_counts: LookupMap::new(StorageKey::FreeBookingCounts),
+      instant_book: init_params.instant_book,
+      approval_timeout_ms: init_params.approval_timeout_ms.unwrap_or(DEFAULT_APPROVAL_TIMEOUT_MS),
+      overbooking_policy: init_params.overbooking_policy,
+      audit_log: Vector::new(StorageKey::AuditLog),
+      pricing_history: Vector::new(StorageKey::PricingHistory),
+      timelock_ms: init_params.timelock_ms.unwrap_or(DEFAULT_TIMELOCK_MS),
+      grace_period_ms: init_params.grace_period_ms.unwrap_or(DEFAULT_GRACE_PERIOD_MS),
+      modification_policy: init_params.modification_policy.unwrap_or_default(),
+      scheduled_changes: UnorderedMap::new(StorageKey::ScheduledChanges),
+      next_change_id: 0,
+      notices: Vector::new(StorageKey::Notices),
+      notices_read: 0,
+      response_stats: ResponseStats::default(),
+      reliability_buckets: Vec::new(),
+      feed_token: None,
+      privacy_mode: init_params.privacy_mode,
+      privacy_salt: env::random_seed(),
+      account_bookings: LookupMap::new(StorageKey::AccountBookings),
+      visibility: init_params.visibility,
+      allowlist: TreeMap::new(StorageKey::AllowedAccounts),
+      active_window: ActiveWindow::default(),
+      booking_phases: Vec::new(),
+      member_tiers: LookupMap::new(StorageKey::MemberTiers),
+      lotteries: UnorderedMap::new(StorageKey::Lotteries),
+      next_lottery_id: 0,
+      invoice_customers: UnorderedMap::new(StorageKey::InvoiceCustomers),
+      invoices: LookupMap::new(StorageKey::Invoices),
+      late_interest_bps_per_day: 0,
+      price_amendments: LookupMap::new(StorageKey::PriceAmendments),
+      event_seq: 0,
+      recent_changes: Vector::new(StorageKey::RecentChanges),
+      changes_recorded: 0,
+      changes_lost_before: 0,
+      unblocked_requests: UnorderedSet::new(StorageKey::UnblockedRequests),
+      indexer_account,
+      owner_cancellation_penalty_bps: 0,
+      package_offers: Vec::new(),
+      campaigns: Vec::new(),
+      next_campaign_id: 0,
+      first_booking_discount_bps: 0,
+      last_minute_rule: None,
+      blackouts: TreeMap::new(StorageKey::Blackouts),
+      blackout_starts: LookupMap::new(StorageKey::BlackoutStarts),
+      next_blackout_id: 0,
+      force_majeure: None,
+      buffer_ms: 0,
+      usage_pricing: None,
+      usage_reports: LookupMap::new(StorageKey::UsageReports),
+      held_deposits: UnorderedSet::new(StorageKey::HeldDeposits),
+      condition_reports: LookupMap::new(StorageKey::ConditionReports),
+      oracle: None,
+      deposit_release_ms: usage::DEFAULT_DEPOSIT_RELEASE_MS,
+      finalized_revenue: LookupMap::new(StorageKey::FinalizedRevenue),
+      terms: Vector::new(StorageKey::Terms),
+      coupons: UnorderedMap::new(StorageKey::CouponsByCode),
+      first_booking_discounts_used: LookupSet::new(StorageKey::FirstBookingDiscountsUsed),
+      packages: LookupMap::new(StorageKey::Packages),
+      next_package_id: 0,
+      capacity: init_params.capacity,
+      overlap_compensation_bps: 0,
+      twin: None,
+      mirrored_bookings: LookupMap::new(StorageKey::MirroredBookings),
+      imports_closed: false,
+      payouts_due: TreeMap::new(StorageKey::PayoutsDueById),
+      payouts_in_flight: 0,
+      liabilities: 0,
+      lottery_liabilities: 0,
+      legacy_bookings_from: None,
+      price_currency: None,
+      exchange_rate: None,
+      finalized_payments: LookupSet::new(StorageKey::FinalizedPayments),
     };
+    resource.record_pricing_version();
     resource.image_urls.extend(init_params.image_urls);
     resource.tags.extend(init_params.tags); 
     resource
   }
 
-  pub fn test() -> String {
-    return "hi, cool!".into(); 
+  pub(crate) fn assert_owner(&self) {
+    assert_eq!(
+      env::predecessor_account_id().to_string(),
+      self.owner,
+      "only the owner can do this"
+    );
   }
 
-  pub fn assert_no_booking_collision(&self, start: u64, end: u64) {
-    if let Some(booking_right_start) = self.blocker_ends.higher(&start) { // find out booking with the next end marker right of from
-      if let Some(booking_right) = self.blocker_ends.get(&booking_right_start) {
-        if let Some(booking) = self.bookings.get(&booking_right) {
-          assert!( // check that that one's start is after this ones end
-            booking.start > end, 
-            "booking collision"
-          );
-        }
-      }
+  pub(crate) fn assert_factory(&self) {
+    assert_eq!(
+      env::predecessor_account_id().to_string(),
+      self.factory,
+      "only the factory can do this"
+    );
+  }
+
+  pub(crate) fn now_ms() -> u64 {
+    env::block_timestamp() / 1_000_000
+  }
+
+  fn current_booking_policy(&self) -> BookingPolicy {
+    BookingPolicy {
+      refund_buffer: self.pricing.refund_buffer,
+      refund_tiers: self.pricing.refund_tiers.clone(),
+      rounding: self.pricing.rounding,
+      owner_cancellation_penalty_bps: self.owner_cancellation_penalty_bps,
+      approval_timeout_ms: self.approval_timeout_ms,
+      grace_period_ms: self.grace_period_ms,
+      modification: self.modification_policy.clone(),
     }
-    if let Some(booking_left_start) = self.blocker_starts.lower(&end) {
-      if let Some(booking_left) = self.blocker_starts.get(&booking_left_start) {
-        if let Some(booking) = self.bookings.get(&booking_left) {
-          assert!(
-            booking.end < start,
-            "booking collision"
-          );
-        }
-      }
+  }
+
+  /// Puts the booking on a free unit, moving others if needed, the caller stores
+  /// the booking afterwards.
+  pub(crate) fn add_blockers(&mut self, booking_id: u128, booking: &mut Booking) {
+    let (unit, moves) = self.plan_unit(booking.start, booking.end).expect("booking collision");
+    self.move_units(moves);
+    booking.unit = unit;
+    self.blockers.insert(&(booking.unit, booking.start), &Interval { end: booking.end, booking_id });
+    self.unblocked_requests.remove(&booking_id);
+    self.mirror_to_twin(booking_id, booking);
+  }
+
+  pub(crate) fn is_blocking(&self, booking_id: u128, booking: &Booking) -> bool {
+    self.blockers
+      .get(&(booking.unit, booking.start))
+      .is_some_and(|interval| interval.booking_id == booking_id)
+  }
+
+  pub(crate) fn remove_blockers(&mut self, booking_id: u128, booking: &Booking) {
+    // requests under FirstApprovedWins never made it into the blockers
+    if self.is_blocking(booking_id, booking) {
+      self.blockers.remove(&(booking.unit, booking.start));
+      self.release_on_twin(booking_id, booking);
+    } else {
+      self.unblocked_requests.remove(&booking_id);
     }
   }
 
-  #[payable]
-  pub fn book(&mut self, start: u64, end: u64) {
-    assert!(end > start, "end before start"); 
-    let duration = end - start;
-    assert!(duration >= self.min_duration_ms);
-    self.assert_no_booking_collision(start, end); 
-    let price = self.pricing.get_price(start, end);
+  fn remove_booking(&mut self, booking_id: u128) -> Booking {
+    let mut removed = None;
+    self.track_liability(booking_id, |resource| removed = resource.bookings.remove(&booking_id));
+    let booking = removed.expect("booking not found");
+    self.remove_blockers(booking_id, &booking);
+    booking
+  }
+
+  /// Checks in an approved booking during its window, for the consumer or the
+  /// resource's oracle. Returns the commitment deposit unless it is held.
+  pub(crate) fn check_in_booking(&mut self, booking_id: u128, mut booking: Booking) {
+    assert!(!booking.is_pending(), "booking is not approved");
+    assert!(!booking.is_checked_in(), "already checked in");
+    let ms = Self::now_ms(); 
     assert!(
-        env::attached_deposit() >= price,
-        "price: {}, sent: {}",
-        price,
-        env::attached_deposit()
+      ms >= booking.start && ms < booking.end,
+      "check-in is only possible during the booking"
     );
+    booking.set_status(BookingStatus::CheckedIn);
+    booking.checked_in_at = Some(ms);
+    booking.deposit_held = booking.commitment_deposit > 0 && self.holds_deposits();
+    self.store_booking(booking_id, &booking);
+    if booking.deposit_held {
+      self.held_deposits.insert(&booking_id);
+    }
+    self.emit("CheckIn", &CheckInLog {
+      id: U128::from(booking_id),
+      returned_deposit: U128::from(if booking.deposit_held { 0 } else { booking.commitment_deposit }),
+    });
+    if booking.commitment_deposit > 0 && !booking.deposit_held {
+      near_sdk::Promise::new(booking.consumer_account_id.parse().unwrap()).transfer(booking.commitment_deposit);
+    }
+  }
+
+  /// Cancels a live booking that hasn't ended, refunding everything plus the owner
+  /// cancellation penalty it was made under. The caller checks that the owner's
+  /// withdrawable funds cover the penalty, see `assert_withdrawable_covers`. The
+  /// reliability score changes, the caller reports the update once it is done
+  /// cancelling.
+  pub(crate) fn cancel_by_owner(
+    &mut self,
+    booking_id: u128,
+    mut booking: Booking,
+    reason: Option<CancellationReason>,
+    reason_text: Option<String>
+  ) {
+    self.remove_blockers(booking_id, &booking);
+    // a held deposit goes back in full, the owner cancelled before usage was settled
+    let returns_deposit = !booking.is_checked_in() || booking.deposit_held;
+    booking.deposit_held = false;
+    self.held_deposits.remove(&booking_id);
+    booking.cancel(Cancellation {
+      by_owner: true,
+      reason,
+      reason_text: reason_text.clone(),
+      cancelled_at: Self::now_ms(),
+    });
+    self.store_booking(booking_id, &booking);
+    self.record_cancellation(true);
+    if let Some(package_id) = booking.package_id {
+      self.return_package_credit(&booking.consumer_account_id, package_id);
+    }
+    let penalty = booking.owner_cancellation_penalty();
+    let mut refund_amount = booking.price - booking.refunded;
+    if returns_deposit {
+      refund_amount += booking.commitment_deposit;
+    }
+    self.emit("OwnerBookingCancellation", &OwnerCancellationLog {
+      id: U128::from(booking_id),
+      refund_amount: U128::from(refund_amount),
+      penalty: U128::from(penalty),
+      reason,
+      reason_text,
+    });
+    self.refund(booking_id, &booking.consumer_account_id, refund_amount + penalty);
+  }
+
+  /// Cancelled bookings stay around for their cancellation record, this only returns live ones.
+  pub(crate) fn get_active_booking(&self, booking_id: u128) -> Booking {
+    let booking = self.bookings.get(&booking_id).expect("booking not found");
+    assert!(booking.status != BookingStatus::Cancelled, "booking is cancelled");
+    booking
+  }
+
+  #[allow(clippy::needless_return)]
+  pub fn test() -> String {
+    return "hi, cool!".into(); 
+  }
+
+  pub fn assert_no_booking_collision(&self, start: u64, end: u64) {
+    if self.plan_unit(start, end).is_none() {
+      // open lotteries hold a unit each until they are drawn
+      let lottery = self.lotteries.values().any(|lottery| lottery.start < end && start < lottery.end);
+      assert!(!lottery, "slot is allocated by lottery");
+      panic!("booking collision");
+    }
+  }
+
+  /// Stores a paid for booking and blocks its slot, callers check everything beforehand.
+  pub(crate) fn create_booking(
+    &mut self,
+    consumer_account_id: String,
+    start: u64,
+    end: u64,
+    charge: Charge,
+    pending: bool,
+  ) -> u128 {
+    let Charge { price, commitment_deposit, exchange_rate } = charge;
     let booking_id = self.next_booking_id; 
     self.next_booking_id += 1; 
-    let booking = Booking {
-      consumer_account_id: env::signer_account_id().to_string(), 
+    self.imports_closed = true;
+    let mut booking = Booking {
+      consumer_account_id, 
       start, 
       end, 
-      price
+      price,
+      commitment_deposit,
+      status: if pending { BookingStatus::Pending } else { BookingStatus::Confirmed },
+      created_at: Self::now_ms(),
+      cancellation: None,
+      policy: self.current_booking_policy(),
+      refunded: 0,
+      package_id: None,
+      unit: 0,
+      mirror_of: None,
+      imported: false,
+      campaign_id: None,
+      metered: self.pricing.metered,
+      checked_in_at: None,
+      checked_out_at: None,
+      deposit_held: false,
+      terms_version: self.current_terms_version(),
+      coupon_code: None,
+      paid_out: false,
+      exchange_rate: exchange_rate.clone(),
     }; 
-    self.bookings.insert(&booking_id, &booking);
-    self.blocker_starts.insert(&start, &booking_id);
-    self.blocker_ends.insert(&end, &booking_id); 
+    if !booking.is_pending() || self.overbooking_policy == OverbookingPolicy::ExclusiveHold {
+      self.add_blockers(booking_id, &mut booking);
+    } else {
+      self.unblocked_requests.insert(&booking_id);
+    }
+    self.store_booking(booking_id, &booking);
+    if price > 0 {
+      self.payouts_due.insert(&booking_id, &());
+    }
+    let mut account_booking_ids = self.account_bookings.get(&booking.consumer_account_id).unwrap_or_default();
+    account_booking_ids.push(booking_id);
+    self.account_bookings.insert(&booking.consumer_account_id, &account_booking_ids);
+    self.record_booking_made();
 
-    env::log_str(&*format!("BookingCreation: {}", serde_json::ser::to_string(&BookingCreationLog {
+    let event = if booking.is_pending() { "BookingRequest" } else { "BookingCreation" };
+    self.emit(event, &BookingCreationLog {
       id: U128::from(booking_id),
-      booker_account_id: booking.consumer_account_id, 
+      booker_account_id: self.booker_label(&booking.consumer_account_id), 
       start: booking.start, 
       end: booking.end, 
       price: U128::from(price), 
-    }).unwrap())); 
-    // from the start, find the next end
+      price_near: yocto_to_near_string(price),
+      exchange_rate,
+    }); 
+    booking_id
+  }
+
+  fn count_free_booking(&mut self, account_id: &String) {
+    assert!(self.pricing.allow_free_bookings, "free bookings are not allowed");
+    let today = env::block_timestamp() / 1_000_000 / MS_PER_DAY;
+    let mut counter = match self.free_booking_counts.get(account_id) {
+      Some(counter) if counter.day == today => counter,
+      _ => FreeBookingCount { day: today, count: 0 },
+    };
+    assert!(
+      counter.count < self.pricing.max_free_bookings_per_day,
+      "daily free booking limit of {} reached",
+      self.pricing.max_free_bookings_per_day
+    );
+    counter.count += 1;
+    self.free_booking_counts.insert(account_id, &counter);
+  }
+
+  /// Keeps `amount` plus the storage added since `initial_storage`, sends the rest back.
+  fn refund_overpayment(&mut self, booking_id: u128, amount: u128, initial_storage: u64) {
+    let storage_cost = env::storage_usage().saturating_sub(initial_storage) as u128 * env::storage_byte_cost();
+    let required = amount + storage_cost;
+    assert!(
+      env::attached_deposit() >= required,
+      "required: {} (storage: {}), sent: {}",
+      required,
+      storage_cost,
+      env::attached_deposit()
+    );
+    let surplus = env::attached_deposit() - required;
+    if surplus > 0 {
+      self.emit("Overpayment", serde_json::json!({
+        "booking_id": U128::from(booking_id),
+        "storage_cost": U128::from(storage_cost),
+        "refunded": U128::from(surplus),
+      }));
+      Promise::new(env::predecessor_account_id()).transfer(surplus);
+    }
   }
 
-  pub fn cancel_booking(&mut self, booking_id: u128) {
-    let booking = self.bookings.get(&booking_id).unwrap(); 
+  /// Every booking covers the storage it takes up, anything attached beyond the
+  /// price, the commitment deposit and the storage is sent back.
+  #[payable]
+  pub fn book(
+    &mut self,
+    start: u64,
+    end: u64,
+    use_package: Option<bool>,
+    terms_version: Option<u64>,
+    coupon_code: Option<String>,
+  ) -> U128 {
+    let initial_storage = env::storage_usage();
+    assert!(self.status == ResourceStatus::Active, "resource is not accepting bookings");
+    self.assert_current_terms(terms_version);
+    let account_id = env::predecessor_account_id().to_string();
+    self.assert_may_book(&account_id);
+    assert!(end > start, "end before start"); 
+    assert!(self.active_window.contains(start, end), "booking is outside the season");
+    self.assert_no_force_majeure(start, end);
+    self.pricing.assert_valid_interval(start, end);
+    self.pricing.assert_within_advance_window(start, Self::now_ms());
+    self.assert_booking_phase_open(&account_id, start);
+    let duration = end - start;
+    assert!(duration >= self.min_duration_ms);
+    self.assert_no_booking_collision(start, end); 
+    let package_id = if use_package.unwrap_or(false) {
+      Some(self.use_package_credit(&account_id, start, end))
+    } else {
+      None
+    };
+    let exchange_rate = self.current_exchange_rate();
+    let list_price = if package_id.is_some() {
+      0
+    } else {
+      to_yocto(self.pricing.get_price(start, end), exchange_rate.as_ref())
+    };
+    let (campaign_price, campaign_id) = self.campaign_price(start, list_price);
+    let last_minute_discount = self.last_minute_discount(start, campaign_price);
+    let coupon_discount = coupon_code
+      .as_ref()
+      .map_or(0, |code| self.use_coupon(code, campaign_price - last_minute_discount));
+    let discounted_price = campaign_price - last_minute_discount - coupon_discount;
+    let first_booking_discount = self.use_first_booking_discount(&account_id, discounted_price);
+    let price = discounted_price - first_booking_discount;
+    let commitment_deposit = to_yocto(self.pricing.get_commitment_deposit(start, end), exchange_rate.as_ref());
+    // trusted customers attaching less than the required deposit only pay for
+    // storage, they get an invoice and no commitment deposit
+    let required_deposit = price + commitment_deposit + MAX_BOOKING_STORAGE_BYTES as u128 * env::storage_byte_cost();
+    let invoiced = price > 0 && env::attached_deposit() < required_deposit && self.may_book_on_invoice(&account_id);
+    let commitment_deposit = if invoiced { 0 } else { commitment_deposit };
+    if !invoiced {
+      assert!(
+          env::attached_deposit() >= price + commitment_deposit,
+          "price: {}, deposit: {}, sent: {}",
+          price,
+          commitment_deposit,
+          env::attached_deposit()
+      );
+    }
+    if list_price == 0 && package_id.is_none() {
+      self.count_free_booking(&account_id);
+    }
+    let booking_id = self.create_booking(
+      account_id.clone(),
+      start,
+      end,
+      Charge { price, commitment_deposit, exchange_rate },
+      !self.instant_book,
+    );
+    if invoiced {
+      self.open_invoice(booking_id, &account_id, price);
+    }
+    if package_id.is_some() || campaign_id.is_some() || coupon_code.is_some() {
+      let mut booking = self.bookings.get(&booking_id).unwrap();
+      booking.package_id = package_id;
+      booking.campaign_id = campaign_id;
+      booking.coupon_code = coupon_code.clone();
+      self.store_booking(booking_id, &booking);
+    }
+    if let Some(campaign_id) = campaign_id {
+      self.emit("CampaignDiscount", serde_json::json!({
+        "booking_id": U128::from(booking_id),
+        "campaign_id": campaign_id,
+        "discount": U128::from(list_price - campaign_price),
+      }));
+    }
+    if last_minute_discount > 0 {
+      self.emit("LastMinuteDiscount", serde_json::json!({
+        "booking_id": U128::from(booking_id),
+        "discount": U128::from(last_minute_discount),
+      }));
+    }
+    if let Some(code) = coupon_code {
+      self.emit("CouponRedemption", serde_json::json!({
+        "booking_id": U128::from(booking_id),
+        "code": code,
+        "discount": U128::from(coupon_discount),
+      }));
+    }
+    if first_booking_discount > 0 {
+      self.emit("FirstBookingDiscount", serde_json::json!({
+        "booking_id": U128::from(booking_id),
+        "discount": U128::from(first_booking_discount),
+      }));
+    }
+    let amount = if invoiced { 0 } else { price + commitment_deposit };
+    self.refund_overpayment(booking_id, amount, initial_storage);
+    U128::from(booking_id)
+  }
+
+  pub fn cancel_booking(
+    &mut self,
+    booking_id: u128,
+    reason: Option<CancellationReason>,
+    reason_text: Option<String>
+  ) {
+    let mut booking = self.get_active_booking(booking_id); 
     assert!(
       booking.consumer_account_id.eq(&env::signer_account_id().to_string()), 
       "not your booking"
     ); 
-    self.bookings.remove(&booking_id).unwrap(); 
-    let ms = env::block_timestamp() / 1_000_000; 
-    let refund_amount = self.pricing.get_refund_amount(booking.start, booking.end, ms);  
-    env::log_str(&*format!("BookingCancellation: {}", serde_json::ser::to_string(&BookingCancellationLog {
+    if let Some(text) = &reason_text {
+      assert!(text.len() <= MAX_REASON_TEXT_LENGTH, "reason text too long");
+    }
+    let ms = Self::now_ms(); 
+    // a no-show forfeits the commitment deposit
+    assert!(ms < booking.start || booking.is_checked_in(), "booking has started without a check-in");
+    assert!(ms < booking.end, "booking is over");
+    // the held deposit is settled through `settle_usage` or the dispute
+    assert!(
+      booking.status != BookingStatus::Disputed && !booking.deposit_held,
+      "booking holds a deposit for usage, it can't be cancelled"
+    );
+    self.remove_blockers(booking_id, &booking);
+    let was_pending = booking.is_pending();
+    let price_refund = self.cancellation_refund(&booking, ms);
+    let was_checked_in = booking.is_checked_in();
+    let force_majeure = self.is_force_majeure(booking.start, booking.end);
+    booking.cancel(Cancellation {
+      by_owner: false,
+      reason,
+      reason_text: reason_text.clone(),
+      cancelled_at: ms,
+    });
+    self.store_booking(booking_id, &booking);
+    self.record_cancellation(false);
+    let fully_refundable = was_pending || force_majeure;
+    let mut refund_amount = price_refund;
+    if let Some(package_id) = booking.package_id {
+      if fully_refundable || booking.policy.is_fully_refundable(booking.start, booking.created_at, ms) {
+        self.return_package_credit(&booking.consumer_account_id, package_id);
+      }
+    }
+    if !was_checked_in {
+      refund_amount += booking.commitment_deposit;
+    }
+    self.emit("BookingCancellation", &BookingCancellationLog {
       id: U128::from(booking_id), 
-      refund_amount: U128::from(refund_amount) 
-    }).unwrap())); 
-    near_sdk::Promise::new(booking.consumer_account_id.to_string().parse().unwrap()).transfer(refund_amount);
+      refund_amount: U128::from(refund_amount),
+      refund_amount_near: yocto_to_near_string(refund_amount),
+      by_owner: false,
+      reason,
+      reason_text,
+      force_majeure,
+    }); 
+    self.refund(booking_id, &booking.consumer_account_id, refund_amount);
+  }
+
+  /// Cancels a booking on the owner's side, e.g. because the space became
+  /// unavailable. The booker gets everything back plus the penalty the booking
+  /// was made under.
+  pub fn owner_cancel_booking(
+    &mut self,
+    booking_id: u128,
+    reason: Option<CancellationReason>,
+    reason_text: Option<String>
+  ) {
+    self.assert_owner();
+    let booking = self.get_active_booking(booking_id);
+    if let Some(text) = &reason_text {
+      assert!(text.len() <= MAX_REASON_TEXT_LENGTH, "reason text too long");
+    }
+    assert!(Self::now_ms() < booking.end, "booking is over");
+    self.assert_withdrawable_covers(booking.owner_cancellation_penalty(), "penalty");
+    self.cancel_by_owner(booking_id, booking, reason, reason_text);
+    self.report_update();
+  }
+
+  /// Penalty in basis points of the price for owner cancellations of bookings made from now on.
+  pub fn set_owner_cancellation_penalty(&mut self, bps: u16) {
+    self.assert_owner();
+    assert!(bps <= 10_000, "penalty above 100%");
+    self.owner_cancellation_penalty_bps = bps;
+    self.record_admin_action(AdminAction::OwnerCancellationPenaltyChange { bps });
+  }
+
+  pub fn get_owner_cancellation_penalty(&self) -> u16 {
+    self.owner_cancellation_penalty_bps
+  }
+
+  pub fn approve_booking(&mut self, booking_id: u128) {
+    self.assert_owner();
+    let mut booking = self.get_active_booking(booking_id); 
+    assert!(booking.is_pending(), "booking is not pending");
+    assert!(
+      Self::now_ms() < booking.created_at + booking.policy.approval_timeout_ms,
+      "booking request expired"
+    );
+    // requests made under FirstApprovedWins don't hold their slot, whatever the policy is now
+    if !self.is_blocking(booking_id, &booking) {
+      // another request for the same slot may have been approved in the meantime
+      self.assert_no_booking_collision(booking.start, booking.end);
+      self.add_blockers(booking_id, &mut booking);
+    }
+    booking.set_status(BookingStatus::Confirmed);
+    self.store_booking(booking_id, &booking);
+    self.record_response(booking.created_at);
+    self.record_admin_action(AdminAction::BookingApproval { booking_id: U128::from(booking_id) });
+    self.emit("BookingApproval", &BookingApprovalLog {
+      id: U128::from(booking_id),
+    });
+  }
+
+  /// Declines a booking request, the requester gets everything back.
+  pub fn reject_booking(&mut self, booking_id: u128, reason_text: Option<String>) {
+    self.assert_owner();
+    let mut booking = self.get_active_booking(booking_id);
+    assert!(booking.is_pending(), "booking is not pending");
+    if let Some(text) = &reason_text {
+      assert!(text.len() <= MAX_REASON_TEXT_LENGTH, "reason text too long");
+    }
+    self.remove_blockers(booking_id, &booking);
+    booking.cancel(Cancellation {
+      by_owner: true,
+      reason: None,
+      reason_text: reason_text.clone(),
+      cancelled_at: Self::now_ms(),
+    });
+    self.store_booking(booking_id, &booking);
+    self.record_response(booking.created_at);
+    self.record_admin_action(AdminAction::BookingRejection { booking_id: U128::from(booking_id) });
+    if let Some(package_id) = booking.package_id {
+      self.return_package_credit(&booking.consumer_account_id, package_id);
+    }
+    let refund_amount = booking.price - booking.refunded + booking.commitment_deposit;
+    self.emit("BookingRejection", BookingRejectionLog {
+      id: U128::from(booking_id),
+      refund_amount: U128::from(refund_amount),
+      reason_text,
+    });
+    self.refund(booking_id, &booking.consumer_account_id, refund_amount);
   }
 
-  pub fn get_quote(&self, start: u64, end: u64) -> U128 {
-    U128::from(self.pricing.get_price(start, end))
+  /// Anyone can release a request the owner did not answer in time, refunding the requester.
+  pub fn expire_request(&mut self, booking_id: u128) {
+    let booking = self.get_active_booking(booking_id); 
+    assert!(booking.is_pending(), "booking is not pending");
+    assert!(
+      Self::now_ms() >= booking.created_at + booking.policy.approval_timeout_ms,
+      "approval timeout not reached"
+    );
+    self.remove_booking(booking_id);
+    self.record_unanswered_request();
+    if let Some(package_id) = booking.package_id {
+      self.return_package_credit(&booking.consumer_account_id, package_id);
+    }
+    let refund_amount = booking.price - booking.refunded + booking.commitment_deposit;
+    self.emit("BookingRequestExpiry", &BookingRequestExpiryLog {
+      id: U128::from(booking_id),
+      refund_amount: U128::from(refund_amount),
+    });
+    self.refund(booking_id, &booking.consumer_account_id, refund_amount);
+  }
+
+  /// The consumer confirms they showed up, which returns the commitment deposit.
+  pub fn check_in(&mut self, booking_id: u128) {
+    let booking = self.get_active_booking(booking_id); 
+    assert!(
+      booking.consumer_account_id.eq(&env::signer_account_id().to_string()), 
+      "not your booking"
+    ); 
+    self.check_in_booking(booking_id, booking);
+  }
+
+  /// After a booking ended without check-in the owner keeps the commitment deposit.
+  pub fn forfeit_deposit(&mut self, booking_id: u128) {
+    self.assert_owner();
+    let mut booking = self.get_active_booking(booking_id); 
+    assert!(!booking.is_pending(), "booking is not approved");
+    assert!(!booking.is_checked_in(), "consumer checked in");
+    assert!(booking.commitment_deposit > 0, "no deposit to forfeit");
+    assert!(
+      Self::now_ms() >= booking.end,
+      "booking has not ended yet"
+    );
+    let forfeited_deposit = booking.commitment_deposit;
+    booking.commitment_deposit = 0;
+    self.store_booking(booking_id, &booking);
+    self.record_admin_action(AdminAction::DepositForfeit {
+      booking_id: U128::from(booking_id),
+      amount: U128::from(forfeited_deposit),
+    });
+    self.emit("DepositForfeit", &DepositForfeitLog {
+      id: U128::from(booking_id),
+      forfeited_deposit: U128::from(forfeited_deposit),
+    });
+    near_sdk::Promise::new(self.beneficiary.parse().unwrap()).transfer(forfeited_deposit);
+  }
+
+  /// Pays out earnings to the beneficiary, never touching what is owed to bookers.
+  pub fn withdraw(&mut self, amount: U128) {
+    self.assert_owner();
+    let withdrawable = self.get_withdrawable().0;
+    assert!(
+      amount.0 <= withdrawable,
+      "withdrawal would leave refund liabilities uncovered, withdrawable: {}",
+      withdrawable
+    );
+    self.record_admin_action(AdminAction::Withdrawal { amount });
+    self.emit("Withdrawal", serde_json::json!({
+      "amount": amount,
+      "beneficiary": self.beneficiary,
+    }));
+    near_sdk::Promise::new(self.beneficiary.parse().unwrap()).transfer(amount.0);
+  }
+
+  /// Price plus refundable commitment deposit plus the storage the booking takes up
+  /// at most, i.e. what `book` expects to be attached. `book` sends back what the
+  /// storage didn't use.
+  pub fn get_required_deposit(&self, start: u64, end: u64) -> U128 {
+    let commitment_deposit = to_yocto(
+      self.pricing.get_commitment_deposit(start, end),
+      self.current_exchange_rate().as_ref(),
+    );
+    let amount = self.quote_price(start, end) + commitment_deposit;
+    U128::from(amount + MAX_BOOKING_STORAGE_BYTES as u128 * env::storage_byte_cost())
+  }
+
+  pub fn get_booking_status(&self, booking_id: U128) -> Option<BookingStatus> {
+    self.bookings.get(&booking_id.0).map(|booking| booking.status)
+  }
+
+  pub fn get_owner(&self) -> String {
+    self.owner.clone()
+  }
+
+  pub fn get_description(&self) -> String {
+    self.description.get().unwrap_or_default()
+  }
+
+  /// Parameters to set up a copy of this resource.
+  pub fn get_init_params(&self) -> ResourceInitParams {
+    ResourceInitParams {
+      title: self.title.clone(),
+      description: self.get_description(),
+      image_urls: self.image_urls.to_vec(),
+      contact: self.contact.clone(),
+      tags: self.tags.to_vec(),
+      category: self.category.clone(),
+      pricing: PricingParams::from(&self.pricing),
+      coordinates: self.coordinates,
+      min_duration_ms: self.min_duration_ms,
+      instant_book: self.instant_book,
+      approval_timeout_ms: Some(self.approval_timeout_ms),
+      overbooking_policy: self.overbooking_policy,
+      timelock_ms: Some(self.timelock_ms),
+      grace_period_ms: Some(self.grace_period_ms),
+      privacy_mode: self.privacy_mode,
+      visibility: self.visibility,
+      modification_policy: Some(self.modification_policy.clone()),
+      draft: false,
+      capacity: self.capacity,
+    }
+  }
+
+  pub fn get_duration_presets(&self) -> Vec<DurationPreset> {
+    self.pricing.get_duration_presets()
+  }
+
+  pub fn get_capacity(&self) -> u32 {
+    self.capacity
+  }
+
+  /// Time kept free before and after every booking, e.g. for cleaning. Only
+  /// bookings made from now on are checked against it.
+  pub fn set_buffer(&mut self, buffer_ms: u64) {
+    self.assert_owner();
+    self.buffer_ms = buffer_ms;
+    self.record_admin_action(AdminAction::BufferChange { buffer_ms });
+  }
+
+  pub fn get_buffer(&self) -> u64 {
+    self.buffer_ms
+  }
+
+  /// Number of units that could still be booked for `[start, end)`.
+  pub fn get_available_units(&self, start: u64, end: u64) -> u32 {
+    assert!(end > start, "end before start");
+    if self.is_blacked_out(start, end) {
+      return 0;
+    }
+    self.capacity.saturating_sub(self.max_overlap(start, end))
+  }
+
+  /// Itemized price of `[start, end)` booked now, running campaigns and last
+  /// minute discounts included.
+  pub fn get_quote(&self, start: u64, end: u64) -> Quote {
+    assert!(end > start, "end before start");
+    let exchange_rate = self.current_exchange_rate();
+    let convert = |amount| to_yocto(amount, exchange_rate.as_ref());
+    let gross = convert(self.pricing.get_gross_price(start, end));
+    let list_price = convert(self.pricing.get_price(start, end));
+    let total = self.quote_price(start, end);
+    let segments = self.pricing
+      .get_price_segments(start, end)
+      .into_iter()
+      .map(|segment| PriceSegment { price: U128::from(convert(segment.price.0)), ..segment })
+      .collect();
+    Quote {
+      price_per_booking: U128::from(convert(self.pricing.get_price_per_booking(start, end))),
+      segments,
+      gross: U128::from(gross),
+      duration_discount: U128::from(gross - list_price),
+      discount: U128::from(list_price - total),
+      total: U128::from(total),
+      exchange_rate,
+    }
+  }
+
+  /// Same as `get_quote`, formatted in NEAR with fixed decimals.
+  pub fn get_quote_near(&self, start: u64, end: u64) -> String {
+    yocto_to_near_string(self.quote_price(start, end))
+  }
+
+  pub fn yocto_to_near(&self, amount: U128) -> String {
+    yocto_to_near_string(amount.0)
+  }
+
+  pub fn near_to_yocto(&self, amount: String) -> U128 {
+    U128::from(near_string_to_yocto(&amount))
   }
 }
 
@@ -225,27 +1765,505 @@ impl Resource {
  */
 #[cfg(test)]
 mod tests {
-  // Example tests
-
-  // use super::*;
-
-  // #[test]
-  // fn get_default_greeting() {
-  //     let contract = Contract::default();
-  //     // this test did not call set_greeting so should return the default "Hello" greeting
-  //     assert_eq!(
-  //         contract.get_greeting(),
-  //         "Hello".to_string()
-  //     );
-  // }
-
-  // #[test]
-  // fn set_then_get_greeting() {
-  //     let mut contract = Contract::default();
-  //     contract.set_greeting("howdy".to_string());
-  //     assert_eq!(
-  //         contract.get_greeting(),
-  //         "howdy".to_string()
-  //     );
-  // }
+  use near_sdk::test_utils::VMContextBuilder;
+  use near_sdk::testing_env;
+
+  use super::*;
+
+  pub(crate) fn resource(capacity: u32) -> Resource {
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("factory.near".parse().unwrap())
+      .signer_account_id("alice.near".parse().unwrap())
+      .build());
+    let init_params: ResourceInitParams = serde_json::from_value(serde_json::json!({
+      "title": "bike",
+      "description": "",
+      "image_urls": [],
+      "contact": "",
+      "tags": [],
+      "category": null,
+      "pricing": {
+        "price_per_ms": "0",
+        "price_per_booking": "0",
+        "full_refund_period_ms": 0,
+        "allow_free_bookings": true,
+        "max_free_bookings_per_day": 1000,
+        "commitment_deposit_per_ms": null,
+        "price_per_unit": null,
+        "nightly": null,
+      },
+      "coordinates": [0.0, 0.0],
+      "min_duration_ms": 1,
+      "approval_timeout_ms": null,
+      "timelock_ms": null,
+      "grace_period_ms": null,
+      "capacity": capacity,
+    })).unwrap();
+    let resource = Resource::init("owner.near".to_string(), init_params, None);
+    testing_env!(booking_context(0).build());
+    resource
+  }
+
+  /// Alice attaching `price` and the most a booking can take up in storage.
+  pub(crate) fn booking_context(price: u128) -> VMContextBuilder {
+    let mut context = VMContextBuilder::new();
+    context
+      .signer_account_id("alice.near".parse().unwrap())
+      .predecessor_account_id("alice.near".parse().unwrap())
+      .attached_deposit(price + MAX_BOOKING_STORAGE_BYTES as u128 * env::storage_byte_cost());
+    context
+  }
+
+  /// Collision check by comparing against every booking, the reference for the index.
+  fn collides_naively(booked: &[(u64, u64)], start: u64, end: u64) -> bool {
+    booked.iter().any(|(booked_start, booked_end)| *booked_start < end && start < *booked_end)
+  }
+
+  #[test]
+  fn adjacent_bookings_dont_collide() {
+    let mut resource = resource(1);
+    resource.book(100, 200, None, None, None);
+    resource.book(200, 300, None, None, None);
+    resource.book(0, 100, None, None, None);
+    assert_eq!(resource.get_available_units(0, 300), 0);
+    assert_eq!(resource.get_available_units(300, 301), 1);
+  }
+
+  #[test]
+  fn overlapping_ranges_collide() {
+    let mut resource = resource(1);
+    resource.book(100, 200, None, None, None);
+    for (start, end) in [(50, 150), (150, 250), (120, 180), (50, 250), (100, 200), (199, 200), (100, 101)] {
+      assert_eq!(resource.get_available_units(start, end), 0, "[{}, {})", start, end);
+    }
+  }
+
+  #[test]
+  fn range_spanning_several_bookings_collides() {
+    let mut resource = resource(1);
+    resource.book(100, 200, None, None, None);
+    resource.book(300, 400, None, None, None);
+    resource.book(500, 600, None, None, None);
+    assert_eq!(resource.get_available_units(150, 550), 0);
+    assert_eq!(resource.get_available_units(0, 700), 0);
+    assert_eq!(resource.get_available_units(250, 450), 0);
+    assert_eq!(resource.get_available_units(200, 300), 1);
+    assert_eq!(resource.get_available_units(400, 500), 1);
+  }
+
+  #[test]
+  #[should_panic(expected = "booking collision")]
+  fn containing_range_is_rejected() {
+    let mut resource = resource(1);
+    resource.book(100, 200, None, None, None);
+    resource.book(50, 250, None, None, None);
+  }
+
+  #[test]
+  fn index_matches_naive_check_on_every_range() {
+    let booked = [(2, 4), (5, 6), (8, 11)];
+    let mut resource = resource(1);
+    for (start, end) in booked {
+      resource.book(start, end, None, None, None);
+    }
+    for start in 0..13 {
+      for end in start + 1..14 {
+        assert_eq!(
+          resource.plan_unit(start, end).is_none(),
+          collides_naively(&booked, start, end),
+          "[{}, {})",
+          start,
+          end
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn cancelled_booking_frees_its_slot() {
+    let mut resource = resource(1);
+    let booking_id = resource.book(100, 200, None, None, None);
+    resource.cancel_booking(booking_id.0, None, None);
+    assert_eq!(resource.get_available_units(100, 200), 1);
+    resource.book(50, 250, None, None, None);
+  }
+
+  #[test]
+  fn booking_through_a_contract_belongs_to_the_contract() {
+    let mut resource = resource(1);
+    testing_env!(booking_context(0)
+      .predecessor_account_id("marketplace.near".parse().unwrap())
+      .build());
+    let booking_id = resource.book(100, 200, None, None, None);
+    assert_eq!(resource.get_booking(booking_id).unwrap().booker, "marketplace.near");
+  }
+
+  #[test]
+  #[should_panic(expected = "required: ")]
+  fn free_booking_pays_for_its_storage() {
+    let mut resource = resource(1);
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("alice.near".parse().unwrap())
+      .build());
+    resource.book(100, 200, None, None, None);
+  }
+
+  #[test]
+  fn overlapping_bookings_fill_units() {
+    let mut resource = resource(2);
+    resource.book(100, 200, None, None, None);
+    assert_eq!(resource.get_available_units(150, 250), 1);
+    resource.book(150, 250, None, None, None);
+    assert_eq!(resource.get_available_units(180, 190), 0);
+    assert_eq!(resource.get_available_units(200, 250), 1);
+    resource.book(200, 300, None, None, None);
+    assert_eq!(resource.get_available_units(250, 300), 1);
+  }
+
+  #[test]
+  #[should_panic(expected = "booking has started without a check-in")]
+  fn no_show_cant_cancel_to_get_the_deposit_back() {
+    let mut resource = resource(1);
+    resource.pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": "1",
+      "price_per_unit": null,
+      "nightly": null,
+    })).unwrap());
+    testing_env!(booking_context(200).build());
+    let booking_id = resource.book(100, 200, None, None, None);
+    testing_env!(VMContextBuilder::new()
+      .signer_account_id("alice.near".parse().unwrap())
+      .block_timestamp(300 * 1_000_000)
+      .build());
+    resource.cancel_booking(booking_id.0, None, None);
+  }
+
+  #[test]
+  #[should_panic(expected = "booking holds a deposit for usage")]
+  fn booking_holding_a_deposit_cant_be_cancelled() {
+    let mut resource = resource(1);
+    resource.pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": "1",
+      "price_per_unit": null,
+      "nightly": null,
+    })).unwrap());
+    resource.usage_pricing = Some(UsagePricing { unit_price: U128::from(1), reporter: None });
+    testing_env!(booking_context(200).build());
+    let booking_id = resource.book(100, 200, None, None, None);
+    testing_env!(VMContextBuilder::new()
+      .signer_account_id("alice.near".parse().unwrap())
+      .block_timestamp(150 * 1_000_000)
+      .build());
+    resource.check_in(booking_id.0);
+    assert!(resource.held_deposits.contains(&booking_id.0));
+    resource.cancel_booking(booking_id.0, None, None);
+  }
+
+  #[test]
+  fn payout_waits_for_the_dispute_window() {
+    let mut resource = resource(1);
+    resource.pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": null,
+      "price_per_unit": null,
+      "nightly": null,
+    })).unwrap());
+    testing_env!(booking_context(100).build());
+    let booking_id = resource.book(100, 200, None, None, None);
+    assert_eq!(resource.get_payouts_due(None, 10).items, vec![booking_id]);
+    assert!(resource.payout_amount(booking_id.0, 200).is_none());
+    assert!(resource.payout_amount(booking_id.0, 200 + DISPUTE_WINDOW_MS - 1).is_none());
+    assert_eq!(resource.payout_amount(booking_id.0, 200 + DISPUTE_WINDOW_MS), Some(100));
+  }
+
+  #[test]
+  fn payments_are_finalized_when_the_dispute_window_closes() {
+    let mut resource = resource(1);
+    resource.pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": null,
+      "price_per_unit": null,
+      "nightly": null,
+    })).unwrap());
+    testing_env!(booking_context(100).build());
+    let booking_id = resource.book(100, 200, None, None, None);
+    assert_eq!(resource.finalize_payments(), 0);
+
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .block_timestamp((200 + DISPUTE_WINDOW_MS) * 1_000_000)
+      .build());
+    assert_eq!(resource.finalize_payments(), 1);
+    assert_eq!(resource.get_finalized_revenue("1970-01".to_string()).0, 100);
+    assert_eq!(resource.finalize_payments(), 0);
+    // claiming doesn't count the payment again
+    resource.claim_payout(booking_id);
+    assert_eq!(resource.get_finalized_revenue("1970-01".to_string()).0, 100);
+  }
+
+  #[test]
+  fn bookings_move_between_units_to_make_room() {
+    let mut resource = resource(2);
+    resource.book(0, 10, None, None, None);
+    resource.book(20, 30, None, None, None);
+    resource.book(15, 25, None, None, None);
+    // no unit is free for all of it, but at most two bookings overlap
+    let booking_id = resource.book(5, 18, None, None, None);
+    assert_eq!(resource.get_available_units(5, 18), 0);
+    assert_eq!(resource.get_available_units(0, 5), 1);
+    let mut units: Vec<(u64, u32)> = (0..4)
+      .map(|booking_id| resource.bookings.get(&booking_id).unwrap())
+      .map(|booking| (booking.start, booking.unit))
+      .collect();
+    units.sort();
+    assert_eq!(units, vec![(0, 0), (5, 1), (15, 0), (20, 1)]);
+    assert!(resource.is_blocking(booking_id.0, &resource.bookings.get(&booking_id.0).unwrap()));
+  }
+
+  #[test]
+  #[should_panic(expected = "slot is allocated by lottery")]
+  fn lotteries_hold_one_unit() {
+    let mut resource = resource(2);
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .build());
+    resource.create_lottery(100, 200, 50);
+    testing_env!(booking_context(0).build());
+    resource.book(100, 200, None, None, None);
+    resource.book(150, 250, None, None, None);
+  }
+
+  #[test]
+  fn buffer_extends_bookings_on_both_sides() {
+    let mut resource = resource(1);
+    resource.buffer_ms = 20;
+    resource.book(100, 200, None, None, None);
+    assert_eq!(resource.get_available_units(200, 300), 0);
+    assert_eq!(resource.get_available_units(0, 90), 0);
+    resource.book(0, 80, None, None, None);
+    resource.book(220, 300, None, None, None);
+  }
+
+  #[test]
+  fn migrate_rewrites_first_version_state() {
+    use migration::{OldBooking, OldPricing, OldResource};
+    let account_id: AccountId = "bike.factory.near".parse().unwrap();
+    testing_env!(VMContextBuilder::new()
+      .current_account_id(account_id.clone())
+      .predecessor_account_id(account_id)
+      .build());
+    let mut old = OldResource {
+      owner: "owner.near".to_string(),
+      title: "bike".to_string(),
+      description: "red".to_string(),
+      pricing: OldPricing { price_fixed_base: 10, price_per_ms: 1, refund_buffer: 50 },
+      min_duration_ms: 1,
+      contact: "".to_string(),
+      image_urls: LookupSet::new(b"i".to_vec()),
+      tags: LookupSet::new(b"t".to_vec()),
+      next_booking_id: 3,
+      blocker_starts: TreeMap::new(b"b".to_vec()),
+      blocker_ends: TreeMap::new(b"e".to_vec()),
+      bookings: LookupMap::new(b"k".to_vec()),
+      coordinates: [1.0, 2.0],
+    };
+    old.image_urls.insert(&"bike.png".to_string());
+    old.tags.insert(&"red".to_string());
+    // booking 1 was cancelled, the old index kept its blockers
+    for (booking_id, start, end) in [(0, 100, 200), (1, 200, 300), (2, 400, 500)] {
+      old.blocker_starts.insert(&start, &booking_id);
+      old.blocker_ends.insert(&end, &booking_id);
+      if booking_id != 1 {
+        old.bookings.insert(&booking_id, &OldBooking {
+          consumer_account_id: "alice.near".to_string(),
+          start,
+          end,
+          price: 110,
+        });
+      }
+    }
+    env::state_write(&old);
+
+    let resource = Resource::migrate(vec!["bike.png".to_string()], vec!["red".to_string()]);
+    assert_eq!(resource.factory, "factory.near");
+    assert!(resource.status == ResourceStatus::Active);
+    assert_eq!(resource.get_images(), vec!["bike.png".to_string()]);
+    assert_eq!(resource.get_tags(), vec!["red".to_string()]);
+    assert!(resource.get_booking(U128::from(1)).is_none());
+    let booking = resource.get_booking(U128::from(2)).unwrap();
+    assert_eq!((booking.start.0, booking.end.0, booking.price.0), (400, 500, 110));
+    assert!(booking.status == BookingStatus::Confirmed);
+    assert_eq!(resource.payouts_due.len(), 2);
+    assert!(!env::storage_has_key(&[b"k".as_slice(), &borsh::to_vec(&0u128).unwrap()].concat()));
+    assert_eq!(resource.get_available_units(100, 200), 0);
+    assert_eq!(resource.get_available_units(200, 300), 1);
+  }
+
+  #[test]
+  fn erasure_leaves_no_trace_of_the_account() {
+    let mut resource = resource(1);
+    let alice = "alice.near".to_string();
+    resource.book(100, 200, None, None, None);
+    resource.packages.insert(&alice, &vec![Package { id: 0, credits: 0, expires_at: 0, max_session_ms: 100 }]);
+    resource.invoice_customers.insert(&alice, &InvoiceCustomer::default());
+    resource.member_tiers.insert(&alice, &"gold".to_string());
+    resource.allowlist.insert(&alice, &());
+    resource.first_booking_discounts_used.insert(&alice);
+    resource.lotteries.insert(&0, &Lottery {
+      start: 300,
+      end: 400,
+      signup_closes_at: u64::MAX,
+      price: U128::from(0),
+      commitment_deposit: U128::from(0),
+      entries: vec![LotteryEntry { account_id: alice.clone() }],
+      exchange_rate: None,
+    });
+
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id(alice.parse().unwrap())
+      .block_timestamp((200 + DISPUTE_WINDOW_MS) * 1_000_000)
+      .build());
+    assert_eq!(resource.erase_my_data(), 1);
+    assert!(resource.account_bookings.get(&alice).is_none());
+    assert_ne!(resource.bookings.get(&0).unwrap().consumer_account_id, alice);
+    assert!(resource.packages.get(&alice).is_none());
+    assert!(resource.invoice_customers.get(&alice).is_none());
+    assert!(resource.member_tiers.get(&alice).is_none());
+    assert!(!resource.allowlist.contains_key(&alice));
+    assert!(!resource.first_booking_discounts_used.contains(&alice));
+    assert!(resource.lotteries.get(&0).unwrap().entries.is_empty());
+    assert!(resource.free_booking_counts.get(&alice).is_none());
+  }
+
+  #[test]
+  fn late_interest_does_not_compound_across_accruals() {
+    let mut resource = resource(1);
+    let alice = "alice.near".to_string();
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .build());
+    resource.add_invoice_customer(alice.clone());
+    resource.apply_late_interest(100);
+    resource.open_invoice(0, &alice, 1_000_000);
+    // open invoices keep the rate they were opened with
+    resource.apply_late_interest(0);
+    let due_at = resource.invoices.get(&0).unwrap().due_at;
+    testing_env!(VMContextBuilder::new()
+      .block_timestamp((due_at + MS_PER_DAY) * 1_000_000)
+      .build());
+    assert_eq!(resource.process_overdue(10), 1);
+    testing_env!(VMContextBuilder::new()
+      .block_timestamp((due_at + 2 * MS_PER_DAY) * 1_000_000)
+      .build());
+    // 1% a day on the principal, the stored first day's interest earns none
+    assert_eq!(resource.get_invoice(U128::from(0)).unwrap().amount.0, 1_020_000);
+  }
+
+  #[test]
+  fn imported_config_applies_metadata_coupons_and_terms_to_a_published_resource() {
+    let mut resource = resource(1);
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .block_timestamp(1_000_000)
+      .build());
+    resource.create_coupon("SUMMER".to_string(), CouponDiscount::Percentage { bps: 1_000 }, 5, 100);
+    resource.create_coupon("WINTER".to_string(), CouponDiscount::Percentage { bps: 1_000 }, 5, 100);
+    resource.publish_terms("hash".to_string(), 50);
+    let mut config = serde_json::to_value(resource.export_config()).unwrap();
+    config["metadata"]["title"] = "scooter".into();
+    config["coupons"].as_object_mut().unwrap().remove("WINTER");
+    config["coupons"]["SUMMER"]["remaining_uses"] = 2.into();
+    config["terms"]["content_hash"] = "new hash".into();
+    config["deposit_release_ms"] = 10.into();
+
+    assert_eq!(resource.import_config(serde_json::from_value(config.clone()).unwrap()).len(), 1);
+    assert_eq!(resource.title, "scooter");
+    assert_eq!(resource.get_coupon("SUMMER".to_string()).unwrap().remaining_uses, 2);
+    assert!(resource.get_coupon("WINTER".to_string()).is_none());
+    assert_eq!(resource.get_terms(1).unwrap().content_hash, "new hash");
+    // timelocked
+    let (_, scheduled) = resource.get_scheduled_changes().pop().unwrap();
+    assert!(matches!(scheduled.change, SensitiveChange::DepositReleasePeriod(10)));
+
+    config["buffer"] = 5.into();
+    assert!(serde_json::from_value::<ResourceConfig>(config).is_err());
+  }
+
+  #[test]
+  fn prices_in_another_currency_are_charged_at_the_pushed_rate() {
+    let mut resource = resource(1);
+    resource.pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": null,
+      "price_per_unit": null,
+      "nightly": null,
+    })).unwrap());
+    resource.apply_price_currency(Some(PriceCurrency {
+      currency: "USD".to_string(),
+      feed: "feed.near".to_string(),
+      max_rate_age_ms: 1_000,
+    }));
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("feed.near".parse().unwrap())
+      .build());
+    resource.push_exchange_rate(U128::from(1_000), 7.into());
+
+    testing_env!(booking_context(100_000).build());
+    let booking_id = resource.book(100, 200, None, None, None);
+    let booking = resource.get_booking(booking_id).unwrap();
+    assert_eq!(booking.price.0, 100_000);
+    assert_eq!(booking.exchange_rate.unwrap().round_id.0, 7);
+  }
+
+  #[test]
+  fn refund_tiers_use_longest_notice_that_applies() {
+    let mut policy = resource(1).get_booking_policy();
+    policy.grace_period_ms = 0;
+    policy.refund_tiers = vec![
+      RefundTier { min_notice_ms: 100, refund_bps: 10_000 },
+      RefundTier { min_notice_ms: 50, refund_bps: 5_000 },
+    ];
+    assert_eq!(policy.get_refund_amount(1000, 200, 0, 100), 1000);
+    assert_eq!(policy.get_refund_amount(1000, 200, 0, 120), 500);
+    assert_eq!(policy.get_refund_amount(1000, 200, 0, 190), 0);
+  }
+
+  #[test]
+  fn seasonal_rates_split_the_price() {
+    let pricing = Pricing::new(serde_json::from_value(serde_json::json!({
+      "price_per_ms": "1",
+      "price_per_booking": "0",
+      "commitment_deposit_per_ms": null,
+      "price_per_unit": null,
+      "nightly": null,
+      "seasonal_rates": [
+        { "label": "summer", "start": 100, "end": 200, "rate": { "Multiplier": { "bps": 20_000 } } },
+        { "label": "holidays", "start": 220, "end": 230, "rate": { "PricePerMs": { "price_per_ms": "5" } } },
+      ],
+    })).unwrap());
+    let prices: Vec<u128> = pricing.get_price_segments(50, 250).iter().map(|segment| segment.price.0).collect();
+    assert_eq!(prices, vec![50, 200, 20, 50, 20]);
+    assert_eq!(pricing.get_price(50, 250), 340);
+    assert_eq!(pricing.get_price(0, 100), 100);
+  }
+
+  #[test]
+  fn weekday_rates_charge_each_day_at_its_rate() {
+    let rates = WeekdayRates {
+      utc_offset_minutes: 0,
+      weekday_price_per_ms: U128::from(1),
+      weekend_price_per_ms: U128::from(3),
+      per_day_price_per_ms: None,
+    };
+    // 1970-01-02 was a Friday
+    let half_day = MS_PER_DAY / 2;
+    assert_eq!(rates.price(MS_PER_DAY + half_day, 2 * MS_PER_DAY + half_day), 4 * half_day as u128);
+  }
 }
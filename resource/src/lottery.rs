@@ -0,0 +1,174 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+
+use crate::rates::to_yocto;
+use crate::{AdminAction, Charge, ExchangeRate, Resource, ResourceExt, ResourceStatus};
+
+/// Keeps a draw and its refunds within one transaction's gas.
+const MAX_LOTTERY_ENTRIES: usize = 50;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct LotteryEntry {
+  pub account_id: String,
+}
+
+/// A slot that is given to one of the accounts signing up before `signup_closes_at`
+/// instead of the first one to book it.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Lottery {
+  pub start: u64,
+  pub end: u64,
+  pub signup_closes_at: u64,
+  /// fixed when the lottery is created, every entry pays exactly this much
+  pub price: U128,
+  pub commitment_deposit: U128,
+  pub entries: Vec<LotteryEntry>,
+  /// rate price and deposit were converted at when the lottery was created
+  pub exchange_rate: Option<ExchangeRate>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct LotteryDrawLog {
+  lottery_id: u64,
+  booking_id: Option<U128>,
+  winner: Option<String>,
+  entries: u64,
+  /// false if the slot was taken, blacked out or paused since, then everyone is refunded
+  slot_available: bool,
+}
+
+impl Resource {
+  /// Takes `account_id` out of every open lottery and refunds its entries.
+  pub(crate) fn leave_lotteries(&mut self, account_id: &String) {
+    for (lottery_id, mut lottery) in self.lotteries.to_vec() {
+      let entries = lottery.entries.len();
+      lottery.entries.retain(|entry| entry.account_id != *account_id);
+      if lottery.entries.len() == entries {
+        continue;
+      }
+      self.lotteries.insert(&lottery_id, &lottery);
+      let cost = lottery.price.0 + lottery.commitment_deposit.0;
+      self.lottery_liabilities -= cost;
+      if cost > 0 {
+        Promise::new(AccountId::try_from(account_id.clone()).unwrap()).transfer(cost);
+      }
+    }
+  }
+
+  /// Whether `book` would still take the lottery's slot. The lottery itself has to
+  /// be removed already, it holds a unit of its own.
+  fn lottery_slot_available(&self, lottery: &Lottery) -> bool {
+    self.status == ResourceStatus::Active
+      && self.active_window.contains(lottery.start, lottery.end)
+      && !self.is_force_majeure(lottery.start, lottery.end)
+      && self.plan_unit(lottery.start, lottery.end).is_some()
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn create_lottery(&mut self, start: u64, end: u64, signup_closes_at: u64) -> u64 {
+    self.assert_owner();
+    assert!(end > start, "end before start");
+    self.pricing.assert_valid_interval(start, end);
+    assert!(
+      signup_closes_at > Self::now_ms() && signup_closes_at <= start,
+      "sign-up has to close before the slot starts"
+    );
+    self.assert_no_booking_collision(start, end);
+    let exchange_rate = self.current_exchange_rate();
+    let id = self.next_lottery_id;
+    self.next_lottery_id += 1;
+    self.lotteries.insert(&id, &Lottery {
+      start,
+      end,
+      signup_closes_at,
+      price: U128::from(to_yocto(self.pricing.get_price(start, end), exchange_rate.as_ref())),
+      commitment_deposit: U128::from(to_yocto(
+        self.pricing.get_commitment_deposit(start, end),
+        exchange_rate.as_ref(),
+      )),
+      entries: Vec::new(),
+      exchange_rate,
+    });
+    self.record_admin_action(AdminAction::LotteryCreated { id });
+    id
+  }
+
+  /// Signs up for a lottery, the attached deposit has to cover price and
+  /// commitment deposit and is returned in full if the caller doesn't win.
+  #[payable]
+  pub fn enter_lottery(&mut self, lottery_id: u64) {
+    let mut lottery = self.lotteries.get(&lottery_id).expect("lottery not found");
+    // refunds go back to whoever paid
+    let account_id = env::predecessor_account_id().to_string();
+    assert!(self.status == ResourceStatus::Active, "resource is not accepting bookings");
+    self.assert_may_book(&account_id);
+    assert!(Self::now_ms() < lottery.signup_closes_at, "sign-up is closed");
+    assert!(lottery.entries.len() < MAX_LOTTERY_ENTRIES, "lottery is full");
+    assert!(
+      lottery.entries.iter().all(|entry| entry.account_id != account_id),
+      "already signed up"
+    );
+    let cost = lottery.price.0 + lottery.commitment_deposit.0;
+    assert!(env::attached_deposit() >= cost, "price: {}, sent: {}", cost, env::attached_deposit());
+    if env::attached_deposit() > cost {
+      Promise::new(env::predecessor_account_id()).transfer(env::attached_deposit() - cost);
+    }
+    lottery.entries.push(LotteryEntry { account_id });
+    self.lottery_liabilities += cost;
+    self.lotteries.insert(&lottery_id, &lottery);
+  }
+
+  /// Anyone can draw once sign-up closed. The winner gets a confirmed booking,
+  /// everyone else is refunded. If the slot can't be booked anymore, e.g. it was
+  /// blacked out meanwhile, there is no winner and everyone is refunded.
+  pub fn draw_lottery(&mut self, lottery_id: u64) -> Option<U128> {
+    let lottery = self.lotteries.get(&lottery_id).expect("lottery not found");
+    assert!(Self::now_ms() >= lottery.signup_closes_at, "sign-up is still open");
+    self.lotteries.remove(&lottery_id);
+    let slot_available = self.lottery_slot_available(&lottery);
+    let winner_index = if lottery.entries.is_empty() || !slot_available {
+      None
+    } else {
+      let seed = env::random_seed();
+      let random = u64::from_le_bytes(seed[..8].try_into().unwrap());
+      Some((random % lottery.entries.len() as u64) as usize)
+    };
+    let cost = lottery.price.0 + lottery.commitment_deposit.0;
+    // the winner's booking holds its share from now on
+    self.lottery_liabilities -= cost * lottery.entries.len() as u128;
+    let mut booking_id = None;
+    for (index, entry) in lottery.entries.iter().enumerate() {
+      if Some(index) == winner_index {
+        booking_id = Some(U128::from(self.create_booking(
+          entry.account_id.clone(),
+          lottery.start,
+          lottery.end,
+          Charge {
+            price: lottery.price.0,
+            commitment_deposit: lottery.commitment_deposit.0,
+            exchange_rate: lottery.exchange_rate.clone(),
+          },
+          false,
+        )));
+      } else if cost > 0 {
+        Promise::new(AccountId::try_from(entry.account_id.clone()).unwrap()).transfer(cost);
+      }
+    }
+    self.emit("LotteryDraw", &LotteryDrawLog {
+      lottery_id,
+      booking_id,
+      winner: winner_index.map(|index| self.booker_label(&lottery.entries[index].account_id)),
+      entries: lottery.entries.len() as u64,
+      slot_available,
+    });
+    booking_id
+  }
+
+  pub fn get_lotteries(&self) -> Vec<(u64, Lottery)> {
+    self.lotteries.to_vec()
+  }
+}
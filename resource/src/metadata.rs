@@ -0,0 +1,82 @@
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+/// Listing details an owner can change at any time, fields left out stay as they are.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct MetadataUpdate {
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub contact: Option<String>,
+  pub coordinates: Option<[f32; 2]>,
+  /// added to the existing images
+  pub image_urls: Option<Vec<String>>,
+  /// replaces the existing tags
+  pub tags: Option<Vec<String>>,
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Updates the listing and reports it to the factory. Pricing and other
+  /// settings bookers rely on have their own, timelocked setters.
+  pub fn update_metadata(&mut self, update: MetadataUpdate) {
+    self.assert_owner();
+    if let Some(title) = update.title {
+      assert!(!title.is_empty(), "title is missing");
+      self.title = title;
+    }
+    if let Some(description) = update.description {
+      self.description.set(&description);
+    }
+    if let Some(contact) = update.contact {
+      self.contact = contact;
+    }
+    if let Some(coordinates) = update.coordinates {
+      self.coordinates = coordinates;
+    }
+    if let Some(image_urls) = update.image_urls {
+      self.image_urls.extend(image_urls);
+    }
+    if let Some(tags) = update.tags {
+      self.tags.clear();
+      self.tags.extend(tags);
+    }
+    self.record_admin_action(AdminAction::MetadataChange);
+    self.report_update();
+  }
+
+  pub fn add_image(&mut self, image_url: String) {
+    self.assert_owner();
+    assert!(self.image_urls.insert(&image_url), "image already added");
+    self.record_admin_action(AdminAction::MetadataChange);
+  }
+
+  pub fn remove_image(&mut self, image_url: String) {
+    self.assert_owner();
+    assert!(self.image_urls.remove(&image_url), "image not found");
+    self.record_admin_action(AdminAction::MetadataChange);
+  }
+
+  pub fn add_tag(&mut self, tag: String) {
+    self.assert_owner();
+    assert!(self.tags.insert(&tag), "tag already added");
+    self.record_admin_action(AdminAction::MetadataChange);
+    self.report_update();
+  }
+
+  pub fn remove_tag(&mut self, tag: String) {
+    self.assert_owner();
+    assert!(self.tags.remove(&tag), "tag not found");
+    self.record_admin_action(AdminAction::MetadataChange);
+    self.report_update();
+  }
+
+  pub fn get_images(&self) -> Vec<String> {
+    self.image_urls.to_vec()
+  }
+
+  pub fn get_tags(&self) -> Vec<String> {
+    self.tags.to_vec()
+  }
+}
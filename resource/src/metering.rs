@@ -0,0 +1,60 @@
+// Metered bookings reserve a window and pay for all of it, but are only charged
+// for the time between check-in and check-out, e.g. for tool or vehicle sharing.
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{Booking, Resource, ResourceExt};
+
+#[derive(Deserialize, Serialize)]
+struct CheckOutLog {
+  id: U128,
+  used_ms: u64,
+  charge: U128,
+  refund_amount: U128,
+}
+
+impl Resource {
+  /// Records the check-out of a checked in booking. Metered bookings are charged
+  /// pro rata for the time since check-in and get the rest refunded, others keep
+  /// paying the whole window.
+  pub(crate) fn check_out_booking(&mut self, booking_id: u128, mut booking: Booking) {
+    assert!(booking.checked_out_at.is_none(), "already checked out");
+    let checked_in_at = booking.checked_in_at.filter(|_| booking.is_checked_in()).expect("not checked in");
+    let now = Self::now_ms();
+    assert!(now < booking.end, "booking is over");
+    let used_ms = now - checked_in_at;
+    let charge = if booking.metered {
+      booking.price * used_ms as u128 / (booking.end - booking.start) as u128
+    } else {
+      booking.price
+    };
+    let refund_amount = (booking.price - booking.refunded).saturating_sub(charge);
+    booking.refunded += refund_amount;
+    booking.checked_out_at = Some(now);
+    self.store_booking(booking_id, &booking);
+    self.emit("CheckOut", CheckOutLog {
+      id: U128::from(booking_id),
+      used_ms,
+      charge: U128::from(charge),
+      refund_amount: U128::from(refund_amount),
+    });
+    self.refund(booking_id, &booking.consumer_account_id, refund_amount);
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Ends the use of a checked in metered booking. Without check-out the whole
+  /// window is charged.
+  pub fn check_out(&mut self, booking_id: u128) {
+    let booking = self.get_active_booking(booking_id);
+    assert!(
+      booking.consumer_account_id.eq(&env::signer_account_id().to_string()),
+      "not your booking"
+    );
+    assert!(booking.metered, "booking is not metered");
+    self.check_out_booking(booking_id, booking);
+  }
+}
@@ -0,0 +1,117 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{Resource, ResourceExt, MS_PER_DAY};
+
+/// The reliability score looks at the last `RELIABILITY_BUCKETS` periods of this length.
+const RELIABILITY_BUCKET_MS: u64 = 30 * MS_PER_DAY;
+const RELIABILITY_BUCKETS: usize = 6;
+
+/// How quickly the owner answers booking requests.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct ResponseStats {
+  responses: u64,
+  total_response_ms: u64,
+  /// requests that expired without an answer
+  unanswered: u64,
+}
+
+impl ResponseStats {
+  pub fn average_response_ms(&self) -> Option<u64> {
+    self.total_response_ms.checked_div(self.responses)
+  }
+
+  pub fn unanswered(&self) -> u64 {
+    self.unanswered
+  }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct ReliabilityBucket {
+  pub period: u64,
+  pub bookings: u32,
+  pub consumer_cancellations: u32,
+  pub owner_cancellations: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Reliability {
+  pub bookings: u32,
+  pub consumer_cancellations: u32,
+  pub owner_cancellations: u32,
+  /// 10000 minus the share of bookings cancelled by the owner, in basis points.
+  /// `None` without any bookings in the window.
+  pub score_bps: Option<u16>,
+}
+
+impl Resource {
+  fn current_reliability_bucket(&mut self) -> &mut ReliabilityBucket {
+    let period = Self::now_ms() / RELIABILITY_BUCKET_MS;
+    if self.reliability_buckets.last().map(|bucket| bucket.period) != Some(period) {
+      self.reliability_buckets.push(ReliabilityBucket {
+        period,
+        bookings: 0,
+        consumer_cancellations: 0,
+        owner_cancellations: 0,
+      });
+    }
+    let keep_from = self.reliability_buckets.len().saturating_sub(RELIABILITY_BUCKETS);
+    self.reliability_buckets.drain(..keep_from);
+    self.reliability_buckets.last_mut().unwrap()
+  }
+
+  pub(crate) fn record_booking_made(&mut self) {
+    self.current_reliability_bucket().bookings += 1;
+  }
+
+  /// Owner cancellations change the score, so they are reported to the factory right away.
+  pub(crate) fn record_cancellation(&mut self, by_owner: bool) {
+    let bucket = self.current_reliability_bucket();
+    if by_owner {
+      bucket.owner_cancellations += 1;
+    } else {
+      bucket.consumer_cancellations += 1;
+    }
+  }
+
+  pub(crate) fn reliability(&self) -> Reliability {
+    let oldest_period = (Self::now_ms() / RELIABILITY_BUCKET_MS).saturating_sub(RELIABILITY_BUCKETS as u64 - 1);
+    let mut reliability = Reliability {
+      bookings: 0,
+      consumer_cancellations: 0,
+      owner_cancellations: 0,
+      score_bps: None,
+    };
+    for bucket in self.reliability_buckets.iter().filter(|bucket| bucket.period >= oldest_period) {
+      reliability.bookings += bucket.bookings;
+      reliability.consumer_cancellations += bucket.consumer_cancellations;
+      reliability.owner_cancellations += bucket.owner_cancellations;
+    }
+    if reliability.bookings > 0 {
+      let cancelled_bps = (reliability.owner_cancellations as u64 * 10_000 / reliability.bookings as u64).min(10_000);
+      reliability.score_bps = Some(10_000 - cancelled_bps as u16);
+    }
+    reliability
+  }
+
+  /// Called when the owner approves or rejects a request created at `requested_at`.
+  pub(crate) fn record_response(&mut self, requested_at: u64) {
+    self.response_stats.responses += 1;
+    self.response_stats.total_response_ms += Self::now_ms().saturating_sub(requested_at);
+    self.report_update();
+  }
+
+  pub(crate) fn record_unanswered_request(&mut self) {
+    self.response_stats.unanswered += 1;
+    self.report_update();
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Booking and cancellation counts of roughly the last half year.
+  pub fn get_reliability(&self) -> Reliability {
+    self.reliability()
+  }
+}
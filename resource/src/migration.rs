@@ -0,0 +1,185 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, LookupSet, TreeMap};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, Gas, Promise};
+
+use crate::{
+  Booking, BookingStatus, Resource, ResourceExt, ResourceInitParams, ResourceStatus,
+};
+
+const MAX_MIGRATED_BOOKINGS: u128 = 200;
+const MIGRATE_GAS: Gas = Gas(60 * 10u64.pow(12));
+
+/// Pricing of the first deployed version.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct OldPricing {
+  pub price_fixed_base: u128,
+  pub price_per_ms: u128,
+  pub refund_buffer: u64,
+}
+
+/// Booking of the first deployed version, kept in a `LookupMap` under `b"k"`.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct OldBooking {
+  pub consumer_account_id: String,
+  pub start: u64,
+  pub end: u64,
+  pub price: u128,
+}
+
+/// State of the first deployed version. Its collections use one letter prefixes
+/// that don't clash with the `StorageKey` ones.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct OldResource {
+  pub owner: String,
+  pub title: String,
+  pub description: String,
+  pub pricing: OldPricing,
+  pub min_duration_ms: u64,
+  pub contact: String,
+  pub image_urls: LookupSet<String>,
+  pub tags: LookupSet<String>,
+  pub next_booking_id: u128,
+  pub blocker_starts: TreeMap<u64, u128>,
+  pub blocker_ends: TreeMap<u64, u128>,
+  pub bookings: LookupMap<u128, OldBooking>,
+  pub coordinates: [f32; 2],
+}
+
+impl Resource {
+  /// Moves up to 200 bookings of the first version into the current layout, the
+  /// price stays in escrow until it is paid out like any other. Returns whether
+  /// bookings are left, the resource is active again once all are moved.
+  fn move_legacy_bookings(&mut self) -> bool {
+    let from = self.legacy_bookings_from.expect("bookings are already migrated");
+    let mut legacy_bookings: LookupMap<u128, OldBooking> = LookupMap::new(b"k".to_vec());
+    let until = self.next_booking_id.min(from + MAX_MIGRATED_BOOKINGS);
+    for booking_id in from..until {
+      // cancelled bookings were removed
+      let Some(old_booking) = legacy_bookings.remove(&booking_id) else { continue };
+      let mut booking = Booking {
+        consumer_account_id: old_booking.consumer_account_id,
+        start: old_booking.start,
+        end: old_booking.end,
+        price: old_booking.price,
+        commitment_deposit: 0,
+        status: BookingStatus::Confirmed,
+        // unknown, old enough to be out of the grace period
+        created_at: 0,
+        cancellation: None,
+        policy: self.current_booking_policy(),
+        refunded: 0,
+        package_id: None,
+        unit: 0,
+        mirror_of: None,
+        imported: false,
+        campaign_id: None,
+        metered: false,
+        checked_in_at: None,
+        checked_out_at: None,
+        deposit_held: false,
+        terms_version: None,
+        coupon_code: None,
+        paid_out: false,
+        exchange_rate: None,
+      };
+      self.add_blockers(booking_id, &mut booking);
+      self.store_booking(booking_id, &booking);
+      if booking.price > 0 {
+        self.payouts_due.insert(&booking_id, &());
+      }
+      let mut account_booking_ids = self.account_bookings.get(&booking.consumer_account_id).unwrap_or_default();
+      account_booking_ids.push(booking_id);
+      self.account_bookings.insert(&booking.consumer_account_id, &account_booking_ids);
+    }
+    if until < self.next_booking_id {
+      self.legacy_bookings_from = Some(until);
+      return true;
+    }
+    self.legacy_bookings_from = None;
+    self.status = ResourceStatus::Active;
+    // the factory only knows the name of a first version resource
+    self.report_update();
+    false
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Rewrites the state of a resource deployed with the first version. Its image
+  /// urls and tags were kept in `LookupSet`s which cannot be enumerated, they are
+  /// passed in and checked against the old sets. The first 200 bookings are moved
+  /// right away, with more the resource stays paused until `migrate_bookings`
+  /// moved the rest. Called by the resource itself after deploying the new code,
+  /// or by its factory when it rehearses the upgrade on a shadow account.
+  #[init(ignore_state)]
+  pub fn migrate(image_urls: Vec<String>, tags: Vec<String>) -> Self {
+    // resources are always created as sub-accounts of their factory
+    let account_id = env::current_account_id();
+    let (_, factory) = account_id.as_str().split_once('.').expect("resource is no sub-account");
+    let caller = env::predecessor_account_id();
+    assert!(
+      caller == account_id || caller.as_str() == factory,
+      "only the resource or its factory can migrate it"
+    );
+    let mut old: OldResource = env::state_read().expect("no state to migrate");
+    for image_url in &image_urls {
+      assert!(old.image_urls.remove(image_url), "unknown image url {}", image_url);
+    }
+    for tag in &tags {
+      assert!(old.tags.remove(tag), "unknown tag {}", tag);
+    }
+    // the first version had no fees, refunds were rounded down and free resources unlimited
+    let free = old.pricing.price_fixed_base == 0 && old.pricing.price_per_ms == 0;
+    let init_params: ResourceInitParams = serde_json::from_value(serde_json::json!({
+      "title": old.title,
+      "description": old.description,
+      "image_urls": image_urls,
+      "contact": old.contact,
+      "tags": tags,
+      "category": null,
+      "pricing": {
+        "price_per_ms": U128::from(old.pricing.price_per_ms),
+        "price_per_booking": U128::from(old.pricing.price_fixed_base),
+        "full_refund_period_ms": old.pricing.refund_buffer,
+        "allow_free_bookings": free,
+        "max_free_bookings_per_day": if free { u32::MAX } else { 0 },
+        "rounding": "Down",
+      },
+      "coordinates": old.coordinates,
+      "min_duration_ms": old.min_duration_ms,
+      "approval_timeout_ms": null,
+      "timelock_ms": null,
+      "grace_period_ms": 0,
+    })).unwrap();
+    let mut resource = Self::new(old.owner, init_params, None, factory.to_string());
+
+    // the blockers are rebuilt from the bookings, the old index kept cancelled ones
+    old.blocker_starts.clear();
+    old.blocker_ends.clear();
+    resource.next_booking_id = old.next_booking_id;
+    resource.imports_closed = old.next_booking_id > 0;
+    resource.legacy_bookings_from = Some(0);
+    resource.status = ResourceStatus::Paused;
+    resource.move_legacy_bookings();
+    resource
+  }
+
+  /// Deploys `code` over this resource and migrates the state with
+  /// `migrate_args`, called by the factory when a rollout reaches the resource.
+  /// The arguments are borsh serialized to keep the wasm compact.
+  pub fn upgrade(&mut self, #[serializer(borsh)] code: Vec<u8>, #[serializer(borsh)] migrate_args: String) -> Promise {
+    self.assert_factory();
+    Promise::new(env::current_account_id())
+      .deploy_contract(code)
+      .function_call("migrate".to_string(), migrate_args.into_bytes(), 0, MIGRATE_GAS)
+  }
+
+  /// Continues a migration with more than 200 bookings. The resource stays paused
+  /// until the owner or the factory called this often enough, it returns false
+  /// once all bookings are moved.
+  pub fn migrate_bookings(&mut self) -> bool {
+    self.assert_owner_or_factory();
+    self.move_legacy_bookings()
+  }
+}
@@ -0,0 +1,178 @@
+// Keeps two listings of the same physical asset, e.g. in different markets, from
+// being double booked. Every booking blocking one of them blocks the twin too.
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, Gas, Promise, PromiseError};
+
+use crate::{AdminAction, Booking, BookingStatus, Cancellation, CancellationReason, Resource, ResourceExt};
+
+const MIRROR_GAS: Gas = Gas(10 * 10u64.pow(12));
+const MIRROR_CALLBACK_GAS: Gas = Gas(10 * 10u64.pow(12));
+
+#[derive(Deserialize, Serialize)]
+struct MirrorBlockLog {
+  id: U128,
+  origin_booking: U128,
+  start: u64,
+  end: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct MirrorConflictLog {
+  id: U128,
+  refund_amount: U128,
+}
+
+impl Resource {
+  /// Asks the twin to block the slot of a booking that just started blocking here.
+  pub(crate) fn mirror_to_twin(&self, booking_id: u128, booking: &Booking) {
+    let Some(twin) = &self.twin else { return };
+    if booking.mirror_of.is_some() {
+      return;
+    }
+    Promise::new(twin.parse().unwrap())
+      .function_call(
+        "mirror_block".to_string(),
+        serde_json::json!({
+          "start": booking.start,
+          "end": booking.end,
+          "origin_booking": U128::from(booking_id),
+        }).to_string().into_bytes(),
+        0,
+        MIRROR_GAS,
+      )
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(MIRROR_CALLBACK_GAS)
+          .on_mirror_block(booking_id)
+      );
+  }
+
+  /// Frees the twin's copy of a booking that stopped blocking here.
+  pub(crate) fn release_on_twin(&self, booking_id: u128, booking: &Booking) {
+    let Some(twin) = &self.twin else { return };
+    if booking.mirror_of.is_some() {
+      return;
+    }
+    Promise::new(twin.parse().unwrap()).function_call(
+      "mirror_release".to_string(),
+      serde_json::json!({ "origin_booking": U128::from(booking_id) }).to_string().into_bytes(),
+      0,
+      MIRROR_GAS,
+    );
+  }
+
+  fn assert_twin(&self) {
+    assert!(
+      self.twin.as_ref() == Some(&env::predecessor_account_id().to_string()),
+      "only the twin can do this"
+    );
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Both listings have to name each other, `None` stops mirroring. Blocks that
+  /// already exist are not copied over.
+  pub fn set_twin(&mut self, twin: Option<String>) {
+    self.assert_owner();
+    assert!(self.capacity == 1, "only single unit resources can be mirrored");
+    self.twin = twin.clone();
+    self.record_admin_action(AdminAction::TwinChange { twin });
+  }
+
+  pub fn get_twin(&self) -> Option<String> {
+    self.twin.clone()
+  }
+
+  /// Called by the twin for each of its bookings, the slot is blocked here by a
+  /// booking without price on behalf of the twin.
+  pub fn mirror_block(&mut self, start: u64, end: u64, origin_booking: U128) {
+    self.assert_twin();
+    assert!(self.mirrored_bookings.get(&origin_booking.0).is_none(), "already mirrored");
+    self.assert_no_booking_collision(start, end);
+    let booking_id = self.next_booking_id;
+    self.next_booking_id += 1;
+    let mut booking = Booking {
+      consumer_account_id: env::predecessor_account_id().to_string(),
+      start,
+      end,
+      price: 0,
+      commitment_deposit: 0,
+      status: BookingStatus::Confirmed,
+      created_at: Self::now_ms(),
+      cancellation: None,
+      policy: self.current_booking_policy(),
+      refunded: 0,
+      package_id: None,
+      unit: 0,
+      mirror_of: Some(origin_booking.0),
+      imported: false,
+      campaign_id: None,
+      metered: false,
+      checked_in_at: None,
+      checked_out_at: None,
+      deposit_held: false,
+      terms_version: None,
+      coupon_code: None,
+      paid_out: false,
+      exchange_rate: None,
+    };
+    self.add_blockers(booking_id, &mut booking);
+    self.store_booking(booking_id, &booking);
+    self.mirrored_bookings.insert(&origin_booking.0, &booking_id);
+    self.emit("MirrorBlock", MirrorBlockLog {
+      id: U128::from(booking_id),
+      origin_booking,
+      start,
+      end,
+    });
+  }
+
+  pub fn mirror_release(&mut self, origin_booking: U128) {
+    self.assert_twin();
+    let Some(booking_id) = self.mirrored_bookings.remove(&origin_booking.0) else { return };
+    let booking = self.remove_booking(booking_id);
+    self.emit("MirrorRelease", MirrorBlockLog {
+      id: U128::from(booking_id),
+      origin_booking,
+      start: booking.start,
+      end: booking.end,
+    });
+  }
+
+  /// The twin was booked for the same slot first, the booking here is cancelled
+  /// and refunded in full.
+  #[private]
+  pub fn on_mirror_block(&mut self, booking_id: u128, #[callback_result] call_result: Result<(), PromiseError>) {
+    if call_result.is_ok() {
+      return;
+    }
+    let Some(mut booking) = self.bookings.get(&booking_id) else { return };
+    if booking.status == BookingStatus::Cancelled {
+      return;
+    }
+    self.remove_blockers(booking_id, &booking);
+    let was_checked_in = booking.is_checked_in();
+    booking.cancel(Cancellation {
+      by_owner: true,
+      reason: Some(CancellationReason::DoubleBooking),
+      reason_text: None,
+      cancelled_at: Self::now_ms(),
+    });
+    self.store_booking(booking_id, &booking);
+    if let Some(package_id) = booking.package_id {
+      self.return_package_credit(&booking.consumer_account_id, package_id);
+    }
+    let mut refund_amount = booking.price - booking.refunded;
+    if !was_checked_in {
+      refund_amount += booking.commitment_deposit;
+    }
+    self.emit("MirrorConflict", MirrorConflictLog {
+      id: U128::from(booking_id),
+      refund_amount: U128::from(refund_amount),
+    });
+    self.refund(booking_id, &booking.consumer_account_id, refund_amount);
+  }
+}
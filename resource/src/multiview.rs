@@ -0,0 +1,85 @@
+use near_sdk::json_types::U128;
+use near_sdk::near_bindgen;
+use near_sdk::serde::Deserialize;
+use serde_json::Value;
+
+use crate::{Resource, ResourceExt};
+
+const MAX_MULTI_VIEW_CALLS: usize = 20;
+
+/// Calls view `$method` with its arguments taken from the JSON object `$args`.
+macro_rules! dispatch {
+  ($resource:ident, $args:ident, $method:ident($($arg:ident: $ty:ty),*)) => {{
+    #[derive(Deserialize)]
+    struct Args {
+      $($arg: $ty),*
+    }
+    let Args { $($arg),* } = serde_json::from_value($args).expect("invalid arguments");
+    serde_json::to_value($resource.$method($($arg),*)).unwrap()
+  }};
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Runs several views at once, e.g. everything a detail page needs, and returns
+  /// their results in order. Each call is `(method, args)`, only the views below
+  /// are available.
+  pub fn multi_view(&self, calls: Vec<(String, Value)>) -> Vec<Value> {
+    assert!(calls.len() <= MAX_MULTI_VIEW_CALLS, "too many calls");
+    calls.into_iter()
+      .map(|(method, args)| {
+        let args = if args.is_null() { Value::Object(Default::default()) } else { args };
+        match method.as_str() {
+          "get_summary" => dispatch!(self, args, get_summary()),
+          "get_description" => dispatch!(self, args, get_description()),
+          "get_images" => dispatch!(self, args, get_images()),
+          "get_tags" => dispatch!(self, args, get_tags()),
+          "get_quote" => dispatch!(self, args, get_quote(start: u64, end: u64)),
+          "get_quote_near" => dispatch!(self, args, get_quote_near(start: u64, end: u64)),
+          "get_required_deposit" => dispatch!(self, args, get_required_deposit(start: u64, end: u64)),
+          "get_month_view" => dispatch!(self, args, get_month_view(year: u32, month: u32)),
+          "get_availability" => dispatch!(self, args, get_availability(from: u64, to: u64)),
+          "get_blocked_periods" => dispatch!(self, args, get_blocked_periods(from: u64, to: u64)),
+          "get_force_majeure" => dispatch!(self, args, get_force_majeure()),
+          "get_capacity" => dispatch!(self, args, get_capacity()),
+          "get_buffer" => dispatch!(self, args, get_buffer()),
+          "get_oracle" => dispatch!(self, args, get_oracle()),
+          "get_price_currency" => dispatch!(self, args, get_price_currency()),
+          "get_exchange_rate" => dispatch!(self, args, get_exchange_rate()),
+          "get_current_terms" => dispatch!(self, args, get_current_terms()),
+          "get_coupon" => dispatch!(self, args, get_coupon(code: String)),
+          "get_terms" => dispatch!(self, args, get_terms(version: u64)),
+          "get_terms_versions" => dispatch!(self, args, get_terms_versions(cursor: Option<String>, limit: u64)),
+          "get_deposit_release_period" => dispatch!(self, args, get_deposit_release_period()),
+          "get_available_units" => dispatch!(self, args, get_available_units(start: u64, end: u64)),
+          "get_booking_status" => dispatch!(self, args, get_booking_status(booking_id: U128)),
+          "get_booking" => dispatch!(self, args, get_booking(booking_id: U128)),
+          "get_bookings" => dispatch!(self, args, get_bookings(cursor: Option<String>, limit: u64)),
+          "get_bookings_by_account" => dispatch!(self, args, get_bookings_by_account(account_id: String, cursor: Option<String>, limit: u64)),
+          "get_booking_count" => dispatch!(self, args, get_booking_count()),
+          "get_owner" => dispatch!(self, args, get_owner()),
+          "get_duration_presets" => dispatch!(self, args, get_duration_presets()),
+          "get_reliability" => dispatch!(self, args, get_reliability()),
+          "get_active_window" => dispatch!(self, args, get_active_window()),
+          "get_visibility" => dispatch!(self, args, get_visibility()),
+          "get_booking_phases" => dispatch!(self, args, get_booking_phases()),
+          "get_modification_policy" => dispatch!(self, args, get_modification_policy()),
+          "get_booking_policy" => dispatch!(self, args, get_booking_policy()),
+          "get_refund_quote" => dispatch!(self, args, get_refund_quote(booking_id: U128)),
+          "get_lotteries" => dispatch!(self, args, get_lotteries()),
+          "get_payouts_due" => dispatch!(self, args, get_payouts_due(cursor: Option<String>, limit: u64)),
+          "get_finalized_revenue" => dispatch!(self, args, get_finalized_revenue(period: String)),
+          "get_package_offers" => dispatch!(self, args, get_package_offers()),
+          "get_campaigns" => dispatch!(self, args, get_campaigns()),
+          "get_last_minute_rule" => dispatch!(self, args, get_last_minute_rule()),
+          "get_usage_pricing" => dispatch!(self, args, get_usage_pricing()),
+          "get_usage_report" => dispatch!(self, args, get_usage_report(booking_id: U128)),
+          "get_condition_reports" => dispatch!(self, args, get_condition_reports(booking_id: U128)),
+          "get_condition_evidence" => dispatch!(self, args, get_condition_evidence(booking_id: U128)),
+          "get_event_seq" => dispatch!(self, args, get_event_seq()),
+          _ => panic!("{} is not available in multi_view", method),
+        }
+      })
+      .collect()
+  }
+}
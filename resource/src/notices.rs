@@ -0,0 +1,52 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::near_bindgen;
+
+use crate::{Resource, ResourceExt, ResourceStatus};
+
+const MAX_UNREAD_NOTICES: u64 = 20;
+
+/// A message from the platform, e.g. about a policy violation. Only the hash is
+/// stored on chain, the content is delivered off chain.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize)]
+pub struct Notice {
+  pub message_hash: String,
+  pub received_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct OwnerDashboard {
+  pub status: ResourceStatus,
+  pub unread_notices: Vec<Notice>,
+  pub unread_notice_count: u64,
+  pub scheduled_changes: u64,
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn receive_notice(&mut self, message_hash: String) {
+    self.assert_factory();
+    self.notices.push(&Notice {
+      message_hash: message_hash.clone(),
+      received_at: Self::now_ms(),
+    });
+    self.emit("NoticeReceived", &message_hash);
+  }
+
+  pub fn mark_notices_read(&mut self) {
+    self.assert_owner();
+    self.notices_read = self.notices.len();
+  }
+
+  pub fn get_owner_dashboard(&self) -> OwnerDashboard {
+    let unread_from = self.notices_read.max(self.notices.len().saturating_sub(MAX_UNREAD_NOTICES));
+    OwnerDashboard {
+      status: self.status,
+      unread_notices: (unread_from..self.notices.len())
+        .filter_map(|index| self.notices.get(index))
+        .collect(),
+      unread_notice_count: self.notices.len() - self.notices_read,
+      scheduled_changes: self.scheduled_changes.len(),
+    }
+  }
+}
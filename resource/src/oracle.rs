@@ -0,0 +1,48 @@
+// A smart lock gateway or similar device reports physical access, so check-in
+// and check-out follow the door instead of the booker's attestation.
+
+use near_sdk::{env, near_bindgen};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+impl Resource {
+  fn assert_oracle(&self) {
+    assert!(
+      self.oracle.as_deref() == Some(env::predecessor_account_id().as_str()),
+      "only the oracle can call this method"
+    );
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Sets or removes the account allowed to call `oracle_check_in` and
+  /// `oracle_check_out`.
+  pub fn set_oracle(&mut self, oracle: Option<String>) {
+    self.assert_owner();
+    if let Some(oracle) = &oracle {
+      assert!(env::is_valid_account_id(oracle.as_bytes()), "invalid oracle account");
+    }
+    self.oracle = oracle.clone();
+    self.record_admin_action(AdminAction::OracleChange { oracle });
+  }
+
+  pub fn get_oracle(&self) -> Option<String> {
+    self.oracle.clone()
+  }
+
+  /// Same as the booker's `check_in`, called when the booker opened the lock.
+  pub fn oracle_check_in(&mut self, booking_id: u128) {
+    self.assert_oracle();
+    let booking = self.get_active_booking(booking_id);
+    self.check_in_booking(booking_id, booking);
+  }
+
+  /// Checks the booker out when they left, which ends the charged time of metered
+  /// bookings.
+  pub fn oracle_check_out(&mut self, booking_id: u128) {
+    self.assert_oracle();
+    let booking = self.get_active_booking(booking_id);
+    self.check_out_booking(booking_id, booking);
+  }
+}
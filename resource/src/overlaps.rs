@@ -0,0 +1,90 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::near_bindgen;
+
+use crate::{AdminAction, Cancellation, CancellationReason, Interval, Resource, ResourceExt};
+
+#[derive(Deserialize, Serialize)]
+struct OverlapResolutionLog {
+  /// the cancelled booking
+  id: U128,
+  kept_booking_id: U128,
+  unit: u32,
+  overlap_start: u64,
+  overlap_end: u64,
+  refund_amount: U128,
+  compensation: U128,
+  resolved_by: String,
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Bonus in basis points of the price paid on top of the refund when a booking
+  /// is cancelled by `resolve_overlap`.
+  pub fn set_overlap_compensation(&mut self, bps: u16) {
+    self.assert_owner();
+    assert!(bps <= 10_000, "compensation above 100%");
+    self.overlap_compensation_bps = bps;
+    self.record_admin_action(AdminAction::OverlapCompensationChange { bps });
+  }
+
+  pub fn get_overlap_compensation(&self) -> u16 {
+    self.overlap_compensation_bps
+  }
+
+  /// Repairs two confirmed bookings that ended up on the same unit at the same
+  /// time, which `book` never allows but a bug could. The booking created first
+  /// is kept, the other one is cancelled with a full refund plus the overlap
+  /// compensation. Callable by the owner and by the factory as arbiter.
+  pub fn resolve_overlap(&mut self, booking_a: u128, booking_b: u128) {
+    let caller = self.assert_owner_or_factory();
+    assert!(booking_a != booking_b, "same booking");
+    let a = self.get_active_booking(booking_a);
+    let b = self.get_active_booking(booking_b);
+    assert!(!a.is_pending() && !b.is_pending(), "only confirmed bookings can overlap");
+    // settled bookings would be refunded out of other bookings' escrow
+    a.assert_in_escrow();
+    b.assert_in_escrow();
+    self.assert_payment_open(booking_a);
+    self.assert_payment_open(booking_b);
+    assert!(a.unit == b.unit && a.start < b.end && b.start < a.end, "bookings don't overlap");
+
+    let ((kept_id, kept), (cancelled_id, mut cancelled)) = if (a.created_at, booking_a) < (b.created_at, booking_b) {
+      ((booking_a, a), (booking_b, b))
+    } else {
+      ((booking_b, b), (booking_a, a))
+    };
+    let compensation = cancelled.price * self.overlap_compensation_bps as u128 / 10_000;
+    self.assert_withdrawable_covers(compensation, "compensation");
+    // both may have been written under the same blocker key, only the kept one stays
+    self.remove_blockers(cancelled_id, &cancelled);
+    self.blockers.insert(&(kept.unit, kept.start), &Interval { end: kept.end, booking_id: kept_id });
+
+    let was_checked_in = cancelled.is_checked_in();
+    cancelled.cancel(Cancellation {
+      by_owner: true,
+      reason: Some(CancellationReason::DoubleBooking),
+      reason_text: None,
+      cancelled_at: Self::now_ms(),
+    });
+    self.store_booking(cancelled_id, &cancelled);
+    if let Some(package_id) = cancelled.package_id {
+      self.return_package_credit(&cancelled.consumer_account_id, package_id);
+    }
+    let mut refund_amount = cancelled.price - cancelled.refunded;
+    if !was_checked_in {
+      refund_amount += cancelled.commitment_deposit;
+    }
+    self.emit("OverlapResolution", OverlapResolutionLog {
+      id: U128::from(cancelled_id),
+      kept_booking_id: U128::from(kept_id),
+      unit: kept.unit,
+      overlap_start: kept.start.max(cancelled.start),
+      overlap_end: kept.end.min(cancelled.end),
+      refund_amount: U128::from(refund_amount),
+      compensation: U128::from(compensation),
+      resolved_by: caller,
+    });
+    self.refund(cancelled_id, &cancelled.consumer_account_id, refund_amount + compensation);
+  }
+}
@@ -0,0 +1,141 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{AdminAction, Resource, ResourceExt, ResourceStatus};
+
+/// A bundle of sessions sold up front, e.g. a 10 visit punch card.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct PackageOffer {
+  pub sessions: u32,
+  pub price: U128,
+  /// credits can be used for this long after the purchase
+  pub valid_ms: u64,
+  /// longest booking a single credit covers
+  pub max_session_ms: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct Package {
+  pub id: u64,
+  pub credits: u32,
+  pub expires_at: u64,
+  pub max_session_ms: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PackagePurchaseLog {
+  id: u64,
+  account_id: String,
+  credits: u32,
+  expires_at: u64,
+  price: U128,
+}
+
+impl Resource {
+  /// Takes one credit from the package of `account_id` expiring first that covers the booking.
+  pub(crate) fn use_package_credit(&mut self, account_id: &String, start: u64, end: u64) -> u64 {
+    let now = Self::now_ms();
+    let mut packages = self.packages.get(account_id).unwrap_or_default();
+    // used up packages stay until they expire, their bookings may still return a credit
+    packages.retain(|package| package.expires_at > now);
+    let package = packages
+      .iter_mut()
+      .filter(|package| package.credits > 0 && package.max_session_ms >= end - start)
+      .min_by_key(|package| package.expires_at)
+      .expect("no package credit covers this booking");
+    package.credits -= 1;
+    let package_id = package.id;
+    self.packages.insert(account_id, &packages);
+    package_id
+  }
+
+  /// Whether the package a booking was made with still covers a booking of `duration`.
+  pub(crate) fn package_covers(&self, account_id: &String, package_id: u64, duration: u64) -> bool {
+    let now = Self::now_ms();
+    self.packages
+      .get(account_id)
+      .unwrap_or_default()
+      .iter()
+      .any(|package| package.id == package_id && package.expires_at > now && package.max_session_ms >= duration)
+  }
+
+  /// Gives the credit of a cancelled booking back, unless its package expired meanwhile.
+  pub(crate) fn return_package_credit(&mut self, account_id: &String, package_id: u64) {
+    let now = Self::now_ms();
+    let mut packages = self.packages.get(account_id).unwrap_or_default();
+    if let Some(package) = packages.iter_mut().find(|package| package.id == package_id) {
+      if package.expires_at > now {
+        package.credits += 1;
+        self.packages.insert(account_id, &packages);
+      }
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn set_package_offers(&mut self, offers: Vec<PackageOffer>) {
+    self.assert_owner();
+    for offer in &offers {
+      assert!(offer.sessions > 0, "a package needs at least one session");
+      assert!(offer.valid_ms > 0, "a package has to be valid for some time");
+      assert!(offer.max_session_ms >= self.min_duration_ms, "sessions shorter than the minimum duration");
+    }
+    self.package_offers = offers;
+    self.record_admin_action(AdminAction::PackageOffersChange);
+  }
+
+  pub fn get_package_offers(&self) -> Vec<PackageOffer> {
+    self.package_offers.clone()
+  }
+
+  /// Buys the package at `offer_index` of `get_package_offers`, credits are spent
+  /// with `book(start, end, Some(true))`. The price is not refundable.
+  #[payable]
+  pub fn buy_package(&mut self, offer_index: u32) -> u64 {
+    let offer = self.package_offers.get(offer_index as usize).cloned().expect("offer not found");
+    let account_id = env::signer_account_id().to_string();
+    assert!(self.status == ResourceStatus::Active, "resource is not accepting bookings");
+    self.assert_may_book(&account_id);
+    assert!(
+      env::attached_deposit() >= offer.price.0,
+      "price: {}, sent: {}",
+      offer.price.0,
+      env::attached_deposit()
+    );
+    let id = self.next_package_id;
+    self.next_package_id += 1;
+    let package = Package {
+      id,
+      credits: offer.sessions,
+      expires_at: Self::now_ms() + offer.valid_ms,
+      max_session_ms: offer.max_session_ms,
+    };
+    let now = Self::now_ms();
+    let mut packages = self.packages.get(&account_id).unwrap_or_default();
+    packages.retain(|package| package.expires_at > now);
+    packages.push(package.clone());
+    self.packages.insert(&account_id, &packages);
+    self.emit("PackagePurchase", PackagePurchaseLog {
+      id,
+      account_id,
+      credits: package.credits,
+      expires_at: package.expires_at,
+      price: offer.price,
+    });
+    id
+  }
+
+  /// Packages of `account_id` with credits left that have not expired.
+  pub fn get_packages(&self, account_id: String) -> Vec<Package> {
+    let now = Self::now_ms();
+    self.packages
+      .get(&account_id)
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|package| package.credits > 0 && package.expires_at > now)
+      .collect()
+  }
+}
@@ -0,0 +1,244 @@
+// Booking payments stay in escrow until the dispute window after the booking
+// closed, then the owner claims them minus the platform fee configured in the
+// factory. A payment is final once the window closed, no refund or dispute
+// can reach it after that.
+
+use std::ops::Bound;
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, Gas, Promise, PromiseError};
+
+use chershare_common::pagination::{decode_cursor, paginate, Page};
+
+use crate::calendar::civil_from_days;
+use crate::{BookingStatus, Resource, ResourceExt, DISPUTE_WINDOW_MS, MS_PER_DAY};
+
+const FEE_QUERY_GAS: Gas = Gas(5 * 10u64.pow(12));
+const PAYOUT_CALLBACK_GAS: Gas = Gas(10 * 10u64.pow(12));
+/// Keeps a batch of payouts within one transaction's gas.
+const MAX_PAYOUT_BATCH: usize = 50;
+/// Bookings still in their dispute window are skipped, this bounds how many are looked at.
+const MAX_PAYOUT_SCAN: usize = 200;
+
+#[derive(Deserialize, Serialize)]
+struct BookingCompletionLog {
+  id: U128,
+  payout: U128,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PaymentFinalizedLog {
+  booking_id: U128,
+  amount: U128,
+  finalized_at: u64,
+  period: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PayoutLog {
+  bookings: u32,
+  amount: U128,
+  platform_fee: U128,
+  tenant_fee: U128,
+  beneficiary: String,
+}
+
+/// Revenue is bucketed by calendar month (UTC), keyed as `year * 100 + month`.
+fn revenue_period(timestamp_ms: u64) -> u32 {
+  let (year, month, _) = civil_from_days((timestamp_ms / MS_PER_DAY) as i64);
+  year as u32 * 100 + month
+}
+
+/// Parses a `YYYY-MM` period.
+fn parse_revenue_period(period: &str) -> u32 {
+  let (year, month) = period.split_once('-').expect("period must be YYYY-MM");
+  let year: u32 = year.parse().expect("invalid year");
+  let month: u32 = month.parse().expect("invalid month");
+  assert!((1..=12).contains(&month), "invalid month");
+  year * 100 + month
+}
+
+impl Resource {
+  /// Price left after refunds if the booking can be paid out now.
+  pub(crate) fn payout_amount(&self, booking_id: u128, now: u64) -> Option<u128> {
+    let booking = self.bookings.get(&booking_id)?;
+    let payable = matches!(booking.status, BookingStatus::Confirmed | BookingStatus::CheckedIn)
+      && booking.end + DISPUTE_WINDOW_MS <= now
+      && self.invoices.get(&booking_id).is_none();
+    payable.then_some(booking.price - booking.refunded)
+  }
+
+  /// Marks the booking completed and releases its payment from escrow.
+  pub(crate) fn complete_booking(&mut self, booking_id: u128, amount: u128) {
+    let mut booking = self.bookings.get(&booking_id).unwrap();
+    assert!(!booking.paid_out, "booking is already paid out");
+    booking.set_status(BookingStatus::Completed);
+    booking.paid_out = true;
+    self.store_booking(booking_id, &booking);
+    self.payouts_due.remove(&booking_id);
+    self.emit("BookingCompletion", BookingCompletionLog {
+      id: U128::from(booking_id),
+      payout: U128::from(amount),
+    });
+    self.finalize_payment(booking_id, amount);
+    self.finalized_payments.remove(&booking_id);
+  }
+
+  pub(crate) fn assert_payment_open(&self, booking_id: u128) {
+    assert!(!self.finalized_payments.contains(&booking_id), "payment is final");
+  }
+
+  /// Records `amount` as revenue of the current month, before platform fees,
+  /// for accounting that recognizes revenue once it can't be refunded anymore.
+  /// Payments finalized before are left alone.
+  fn finalize_payment(&mut self, booking_id: u128, amount: u128) {
+    if !self.finalized_payments.insert(&booking_id) {
+      return;
+    }
+    let now = Self::now_ms();
+    let period = revenue_period(now);
+    let revenue = self.finalized_revenue.get(&period).unwrap_or(0);
+    self.finalized_revenue.insert(&period, &(revenue + amount));
+    self.emit("PaymentFinalized", PaymentFinalizedLog {
+      booking_id: U128::from(booking_id),
+      amount: U128::from(amount),
+      finalized_at: now,
+      period: format!("{}-{:02}", period / 100, period % 100),
+    });
+  }
+
+  /// Name of this resource in the factory's registry.
+  fn registry_name(&self) -> String {
+    let account_id = env::current_account_id().to_string();
+    account_id
+      .strip_suffix(&format!(".{}", self.factory))
+      .unwrap_or(&account_id)
+      .to_string()
+  }
+
+  /// Looks up the current fee split and pays out `amount` in the callback.
+  pub(crate) fn pay_out(&mut self, bookings: u32, amount: u128) -> Promise {
+    self.payouts_in_flight += amount;
+    Promise::new(self.factory.parse().unwrap())
+      .function_call(
+        "get_fee_split".to_string(),
+        serde_json::json!({ "name": self.registry_name() }).to_string().into_bytes(),
+        0,
+        FEE_QUERY_GAS,
+      )
+      .then(
+        Self::ext(env::current_account_id())
+          .with_static_gas(PAYOUT_CALLBACK_GAS)
+          .on_fee_split(bookings, U128::from(amount))
+      )
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Pays out a booking whose dispute window closed without a dispute to the beneficiary.
+  pub fn claim_payout(&mut self, booking_id: U128) -> Promise {
+    self.assert_owner();
+    let amount = self.payout_amount(booking_id.0, Self::now_ms()).expect("booking can't be paid out");
+    self.complete_booking(booking_id.0, amount);
+    self.pay_out(1, amount)
+  }
+
+  /// Pays out up to 50 bookings that can be paid out, in one transfer. Only the
+  /// 200 bookings due with the lowest ids are looked at.
+  pub fn claim_all_payouts(&mut self) -> Promise {
+    self.assert_owner();
+    let now = Self::now_ms();
+    let mut payable = Vec::new();
+    let mut settled = Vec::new();
+    for (booking_id, _) in self.payouts_due.iter().take(MAX_PAYOUT_SCAN) {
+      if payable.len() == MAX_PAYOUT_BATCH {
+        break;
+      }
+      match self.bookings.get(&booking_id).map(|booking| booking.status) {
+        // refunded on cancellation, nothing left in escrow
+        Some(BookingStatus::Cancelled) | None => settled.push(booking_id),
+        _ => {
+          if let Some(amount) = self.payout_amount(booking_id, now) {
+            payable.push((booking_id, amount));
+          }
+        },
+      }
+    }
+    for booking_id in settled {
+      self.payouts_due.remove(&booking_id);
+    }
+    assert!(!payable.is_empty(), "nothing to pay out");
+    let mut total = 0;
+    for (booking_id, amount) in payable.iter() {
+      self.complete_booking(*booking_id, *amount);
+      total += amount;
+    }
+    self.pay_out(payable.len() as u32, total)
+  }
+
+  /// Finalizes the payments whose dispute window closed since the last call, among
+  /// the 200 bookings due with the lowest ids, and returns how many. Anyone can
+  /// call this, claiming a payout finalizes it too if nobody did before.
+  pub fn finalize_payments(&mut self) -> u32 {
+    let now = Self::now_ms();
+    let closed: Vec<(u128, u128)> = self.payouts_due
+      .iter()
+      .take(MAX_PAYOUT_SCAN)
+      .filter(|(booking_id, _)| !self.finalized_payments.contains(booking_id))
+      .filter_map(|(booking_id, _)| self.payout_amount(booking_id, now).map(|amount| (booking_id, amount)))
+      .collect();
+    for (booking_id, amount) in closed.iter() {
+      self.finalize_payment(*booking_id, *amount);
+    }
+    closed.len() as u32
+  }
+
+  /// Payments finalized in the `YYYY-MM` month (UTC), before platform fees.
+  pub fn get_finalized_revenue(&self, period: String) -> U128 {
+    U128::from(self.finalized_revenue.get(&parse_revenue_period(&period)).unwrap_or(0))
+  }
+
+  /// Bookings whose payment is still held in escrow by id, continuing after
+  /// `cursor` of the previous page.
+  pub fn get_payouts_due(&self, cursor: Option<String>, limit: u64) -> Page<U128> {
+    // `TreeMap::range` yields nothing without a lower bound
+    let lower = cursor.map_or(Bound::Included(0), |cursor| Bound::Excluded(decode_cursor::<u128>(&cursor)));
+    let entries = self.payouts_due
+      .range((lower, Bound::Unbounded))
+      .map(|(booking_id, _)| (booking_id, U128::from(booking_id)));
+    paginate(entries, limit.min(MAX_PAYOUT_BATCH as u64) as usize)
+  }
+
+  #[private]
+  pub fn on_fee_split(
+    &mut self,
+    bookings: u32,
+    amount: U128,
+    #[callback_result] fee_split: Result<(u16, Option<(String, u16)>), PromiseError>,
+  ) {
+    self.payouts_in_flight -= amount.0;
+    // an unreachable factory must not lock the owner's earnings
+    let (fee_bps, tenant_cut) = fee_split.unwrap_or((0, None));
+    let platform_fee = amount.0 * fee_bps as u128 / 10_000;
+    let tenant_fee = tenant_cut.as_ref().map_or(0, |(_, bps)| platform_fee * *bps as u128 / 10_000);
+    let payout = amount.0 - platform_fee;
+    self.emit("Payout", PayoutLog {
+      bookings,
+      amount: U128::from(payout),
+      platform_fee: U128::from(platform_fee - tenant_fee),
+      tenant_fee: U128::from(tenant_fee),
+      beneficiary: self.beneficiary.clone(),
+    });
+    if payout > 0 {
+      Promise::new(self.beneficiary.parse().unwrap()).transfer(payout);
+    }
+    if platform_fee > tenant_fee {
+      Promise::new(self.factory.parse().unwrap()).transfer(platform_fee - tenant_fee);
+    }
+    if let Some((admin, _)) = tenant_cut.filter(|_| tenant_fee > 0) {
+      Promise::new(admin.parse().unwrap()).transfer(tenant_fee);
+    }
+  }
+}
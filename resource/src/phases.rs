@@ -0,0 +1,77 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+/// How far ahead of a slot's start an account of `tier` may book it. A phase
+/// without tier applies to everyone. Without one, accounts outside the tiers get
+/// the shortest lead of the tier phases, so tiers only ever book earlier.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct BookingPhase {
+  pub tier: Option<String>,
+  pub lead_ms: u64,
+}
+
+impl Resource {
+  /// Longest lead of the phases open to `account_id`, `None` when there are no
+  /// phases and booking ahead is unrestricted. See `BookingPhase` for accounts
+  /// no phase is open to.
+  pub(crate) fn booking_lead_ms(&self, account_id: &String) -> Option<u64> {
+    if self.booking_phases.is_empty() {
+      return None;
+    }
+    let tier = self.member_tiers.get(account_id);
+    Some(
+      self.booking_phases.iter()
+        .filter(|phase| phase.tier.is_none() || phase.tier == tier)
+        .map(|phase| phase.lead_ms)
+        .max()
+        .unwrap_or_else(|| self.booking_phases.iter().map(|phase| phase.lead_ms).min().unwrap())
+    )
+  }
+
+  pub(crate) fn assert_booking_phase_open(&self, account_id: &String, start: u64) {
+    if let Some(lead_ms) = self.booking_lead_ms(account_id) {
+      assert!(
+        Self::now_ms() >= start.saturating_sub(lead_ms),
+        "booking window for this slot is not open yet"
+      );
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Replaces all phases, an empty list lifts the restriction.
+  pub fn set_booking_phases(&mut self, phases: Vec<BookingPhase>) {
+    self.assert_owner();
+    self.booking_phases = phases.clone();
+    self.record_admin_action(AdminAction::BookingPhasesChange { phases });
+  }
+
+  /// Puts accounts into `tier`, or takes them out of any tier with `None`.
+  pub fn set_member_tier(&mut self, account_ids: Vec<String>, tier: Option<String>) {
+    self.assert_owner();
+    for account_id in account_ids.iter() {
+      match &tier {
+        Some(tier) => self.member_tiers.insert(account_id, tier),
+        None => self.member_tiers.remove(account_id),
+      };
+    }
+    self.record_admin_action(AdminAction::MemberTierChange { account_ids, tier });
+  }
+
+  pub fn get_booking_phases(&self) -> Vec<BookingPhase> {
+    self.booking_phases.clone()
+  }
+
+  pub fn get_member_tier(&self, account_id: String) -> Option<String> {
+    self.member_tiers.get(&account_id)
+  }
+
+  /// How far ahead `account_id` may book, `None` if there is no limit.
+  pub fn get_booking_lead_ms(&self, account_id: String) -> Option<u64> {
+    self.booking_lead_ms(&account_id)
+  }
+}
@@ -0,0 +1,104 @@
+use near_sdk::{env, near_bindgen};
+
+use crate::{AdminAction, Resource, ResourceExt, DISPUTE_WINDOW_MS};
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl Resource {
+  /// How a booker shows up in events and public views. In privacy mode that is a
+  /// salted hash instead of the account id. This is not privacy: the account ids
+  /// and the salt are in contract state, which anyone can read over RPC and use to
+  /// recompute every label. It only keeps identities out of calendars and indexers
+  /// that go by events and views. Bookers know their own booking ids from the
+  /// `book` result, the owner sees identities through the private feed.
+  pub(crate) fn booker_label(&self, account_id: &str) -> String {
+    if self.privacy_mode {
+      to_hex(&env::sha256(&[self.privacy_salt.as_slice(), account_id.as_bytes()].concat()))
+    } else {
+      account_id.to_string()
+    }
+  }
+
+  /// What `erase_my_data` leaves in place of `account_id`. Like `booker_label`
+  /// anyone with the account id can recompute it from the salt in state.
+  pub(crate) fn tombstone(&self, account_id: &str) -> String {
+    format!(
+      "erased:{}",
+      to_hex(&env::sha256(&[self.privacy_salt.as_slice(), account_id.as_bytes()].concat()))
+    )
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Pseudonymises bookers in events and views, see `booker_label` for why this
+  /// doesn't hide anyone from a reader of the contract state.
+  pub fn set_privacy_mode(&mut self, enabled: bool) {
+    self.assert_owner();
+    self.privacy_mode = enabled;
+    self.record_admin_action(AdminAction::PrivacyModeChange { enabled });
+  }
+
+  pub fn get_privacy_mode(&self) -> bool {
+    self.privacy_mode
+  }
+
+  /// Replaces the caller's account id in all their bookings with a tombstone, a
+  /// salted hash that still matches the account for anyone who knows it, and
+  /// drops free text they wrote, their packages, invoice account, member tier,
+  /// allowlist entry and lottery entries, which are refunded. Only possible once
+  /// every booking of the caller is over (or cancelled) and out of the dispute
+  /// window, no package credits are left and every invoice is paid.
+  pub fn erase_my_data(&mut self) -> u64 {
+    let account_id = env::predecessor_account_id().to_string();
+    let booking_ids = self.account_bookings.remove(&account_id).unwrap_or_default();
+    let now = Self::now_ms();
+    let tombstone = self.tombstone(&account_id);
+    for booking_id in booking_ids.iter() {
+      // expired requests are deleted entirely
+      let Some(mut booking) = self.bookings.get(booking_id) else { continue };
+      // the deposit still has to go back to the account
+      assert!(!booking.deposit_held, "booking {} still holds a deposit", booking_id);
+      let settled_at = match &booking.cancellation {
+        Some(cancellation) => cancellation.cancelled_at,
+        None => {
+          assert!(!booking.is_pending(), "booking {} is still pending", booking_id);
+          booking.end
+        },
+      };
+      assert!(
+        settled_at + DISPUTE_WINDOW_MS <= now,
+        "booking {} is not settled yet",
+        booking_id
+      );
+      booking.consumer_account_id = tombstone.clone();
+      if let Some(cancellation) = booking.cancellation.as_mut() {
+        cancellation.reason_text = None;
+      }
+      self.store_booking(*booking_id, &booking);
+    }
+    self.free_booking_counts.remove(&account_id);
+    if let Some(packages) = self.packages.remove(&account_id) {
+      for package in packages {
+        assert!(package.credits == 0 || package.expires_at <= now, "package {} still has credits", package.id);
+      }
+    }
+    if let Some(customer) = self.invoice_customers.remove(&account_id) {
+      assert!(customer.open_invoices.is_empty(), "pay the open invoices first");
+    }
+    self.member_tiers.remove(&account_id);
+    self.allowlist.remove(&account_id);
+    // only the tombstone keeps the first booking discount from being used twice
+    if self.first_booking_discounts_used.remove(&account_id) {
+      self.first_booking_discounts_used.insert(&tombstone);
+    }
+    self.leave_lotteries(&account_id);
+    self.emit("DataErasure", serde_json::json!({
+      "tombstone": tombstone,
+      "bookings": booking_ids.len(),
+    }));
+    booking_ids.len() as u64
+  }
+}
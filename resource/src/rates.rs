@@ -0,0 +1,109 @@
+// Prices can be set in another currency, e.g. USD cents, and are converted to
+// yocto NEAR when charged with the latest rate a feed pushed. Every booking keeps
+// the rate it was charged at, so its amounts can be reconciled later.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::{Resource, ResourceExt, SensitiveChange};
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct PriceCurrency {
+  /// e.g. "USD", prices are given in its smallest unit
+  pub currency: String,
+  /// account pushing the exchange rates
+  pub feed: String,
+  /// bookings are refused while the latest rate is older than this
+  pub max_rate_age_ms: u64,
+}
+
+/// Rate a price in the resource's currency was converted at.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct ExchangeRate {
+  pub currency: String,
+  /// yocto NEAR per smallest unit of `currency`
+  pub yocto_per_unit: U128,
+  /// round of the feed this rate was published in
+  pub round_id: U64,
+  pub updated_at: U64,
+}
+
+impl ExchangeRate {
+  pub fn to_yocto(&self, amount: u128) -> u128 {
+    amount * self.yocto_per_unit.0
+  }
+}
+
+/// `amount` in yocto NEAR, unchanged if there is no rate to convert with.
+pub(crate) fn to_yocto(amount: u128, exchange_rate: Option<&ExchangeRate>) -> u128 {
+  exchange_rate.map_or(amount, |exchange_rate| exchange_rate.to_yocto(amount))
+}
+
+impl Resource {
+  /// Rate prices are charged at right now, `None` for resources priced in NEAR.
+  pub(crate) fn current_exchange_rate(&self) -> Option<ExchangeRate> {
+    let price_currency = self.price_currency.as_ref()?;
+    let exchange_rate = self.exchange_rate.clone().expect("no exchange rate yet");
+    assert!(
+      Self::now_ms() <= exchange_rate.updated_at.0 + price_currency.max_rate_age_ms,
+      "exchange rate is outdated"
+    );
+    Some(exchange_rate)
+  }
+
+  /// Base price for the factory's index at the latest rate, 0 while there is none.
+  pub(crate) fn base_price_yocto(&self) -> u128 {
+    let base_price = self.pricing.get_base_price();
+    match (&self.price_currency, &self.exchange_rate) {
+      (None, _) => base_price,
+      (Some(_), Some(exchange_rate)) => exchange_rate.to_yocto(base_price),
+      (Some(_), None) => 0,
+    }
+  }
+
+  pub(crate) fn apply_price_currency(&mut self, price_currency: Option<PriceCurrency>) {
+    // a rate of another currency or feed must not be used
+    self.exchange_rate = None;
+    self.price_currency = price_currency;
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Schedules pricing in another currency, or back in NEAR with `None`. Like
+  /// other pricing changes it only applies after the timelock.
+  pub fn set_price_currency(&mut self, price_currency: Option<PriceCurrency>) -> u64 {
+    self.schedule_change(SensitiveChange::PriceCurrency(price_currency))
+  }
+
+  pub fn get_price_currency(&self) -> Option<PriceCurrency> {
+    self.price_currency.clone()
+  }
+
+  /// Called by the feed with the rate of a new round.
+  pub fn push_exchange_rate(&mut self, yocto_per_unit: U128, round_id: U64) {
+    let price_currency = self.price_currency.as_ref().expect("prices are in NEAR");
+    assert!(
+      env::predecessor_account_id().as_str() == price_currency.feed,
+      "only the feed can push exchange rates"
+    );
+    assert!(yocto_per_unit.0 > 0, "exchange rate must be positive");
+    if let Some(exchange_rate) = &self.exchange_rate {
+      assert!(round_id.0 > exchange_rate.round_id.0, "round is not newer than the current rate");
+    }
+    let exchange_rate = ExchangeRate {
+      currency: price_currency.currency.clone(),
+      yocto_per_unit,
+      round_id,
+      updated_at: U64::from(Self::now_ms()),
+    };
+    self.emit("ExchangeRateUpdate", &exchange_rate);
+    self.exchange_rate = Some(exchange_rate);
+  }
+
+  pub fn get_exchange_rate(&self) -> Option<ExchangeRate> {
+    self.exchange_rate.clone()
+  }
+}
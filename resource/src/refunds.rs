@@ -0,0 +1,97 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::near_bindgen;
+
+use crate::{AdminAction, Booking, BookingPolicy, Resource, ResourceExt, MAX_REASON_TEXT_LENGTH};
+
+const MAX_SIMULATED_OFFSETS: usize = 100;
+
+#[derive(Deserialize, Serialize)]
+struct RefundLog {
+  id: U128,
+  amount: U128,
+  total_refunded: U128,
+  reason: String,
+}
+
+/// What cancelling a booking right now would return to the booker.
+#[derive(Deserialize, Serialize)]
+pub struct RefundQuote {
+  pub price_refund: U128,
+  /// the commitment deposit, unless the booker checked in already
+  pub deposit_refund: U128,
+  pub total: U128,
+}
+
+impl Resource {
+  /// Part of the price a cancellation by the booker at `now` refunds. Requests the
+  /// owner never accepted and bookings under force majeure are refunded in full.
+  pub(crate) fn cancellation_refund(&self, booking: &Booking, now: u64) -> u128 {
+    let refundable = booking.price - booking.refunded;
+    if booking.is_pending() || self.is_force_majeure(booking.start, booking.end) {
+      refundable
+    } else {
+      booking.policy.get_refund_amount(refundable, booking.start, booking.created_at, now)
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// The terms new bookings are made under, a starting point for `simulate_policy`.
+  pub fn get_booking_policy(&self) -> BookingPolicy {
+    self.current_booking_policy()
+  }
+
+  /// Refunds `policy` would give for a booking paid `booking_price` when cancelled
+  /// each of `cancel_offsets` ms before its start, outside the grace period.
+  pub fn simulate_policy(&self, policy: BookingPolicy, booking_price: U128, cancel_offsets: Vec<u64>) -> Vec<U128> {
+    assert!(cancel_offsets.len() <= MAX_SIMULATED_OFFSETS, "too many offsets");
+    let latest_offset = cancel_offsets.iter().copied().max().unwrap_or(0);
+    // booked at 0 and starting late enough that every cancellation is past the grace period
+    let start = policy.grace_period_ms + latest_offset;
+    cancel_offsets
+      .into_iter()
+      .map(|offset| U128::from(policy.get_refund_amount(booking_price.0, start, 0, start - offset)))
+      .collect()
+  }
+
+  pub fn get_refund_quote(&self, booking_id: U128) -> RefundQuote {
+    let booking = self.get_active_booking(booking_id.0);
+    let price_refund = self.cancellation_refund(&booking, Self::now_ms());
+    let deposit_refund = if booking.is_checked_in() { 0 } else { booking.commitment_deposit };
+    RefundQuote {
+      price_refund: U128::from(price_refund),
+      deposit_refund: U128::from(deposit_refund),
+      total: U128::from(price_refund + deposit_refund),
+    }
+  }
+
+  /// Refunds part of a booking's price at the owner's discretion, e.g. as goodwill
+  /// after a problem. All such refunds together can't exceed the price, later
+  /// automatic refunds are based on what is left. Only the price still in escrow
+  /// can be refunded.
+  pub fn issue_refund(&mut self, booking_id: u128, amount: U128, reason: String) {
+    self.assert_owner();
+    assert!(reason.len() <= MAX_REASON_TEXT_LENGTH, "reason text too long");
+    let mut booking = self.get_active_booking(booking_id);
+    assert!(!booking.paid_out, "booking is already paid out");
+    self.assert_payment_open(booking_id);
+    assert!(amount.0 > 0, "nothing to refund");
+    assert!(
+      booking.refunded + amount.0 <= booking.price,
+      "refund exceeds the amount paid, refundable: {}",
+      booking.price - booking.refunded
+    );
+    booking.refunded += amount.0;
+    self.store_booking(booking_id, &booking);
+    self.record_admin_action(AdminAction::RefundIssued { booking_id: U128::from(booking_id), amount });
+    self.emit("RefundIssued", &RefundLog {
+      id: U128::from(booking_id),
+      amount,
+      total_refunded: U128::from(booking.refunded),
+      reason,
+    });
+    self.refund(booking_id, &booking.consumer_account_id, amount.0);
+  }
+}
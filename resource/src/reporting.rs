@@ -0,0 +1,136 @@
+// Pushes to the factory that keep its registry and search indexes fresh.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{near_bindgen, AccountId, Gas, Promise};
+
+use crate::{ActiveWindow, AdminAction, Resource, ResourceExt, Visibility};
+
+const REPORT_GAS: Gas = Gas(5 * 10u64.pow(12));
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceStatus {
+  Active,
+  Paused,
+  /// paused by the factory, e.g. while being delisted, only the factory can lift it
+  Suspended,
+  /// still being set up by the owner, see `publish`
+  Draft,
+}
+
+/// Everything the factory indexes about a resource.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ResourceSummary {
+  pub title: String,
+  pub tags: Vec<String>,
+  pub category: Option<String>,
+  pub coordinates: [f32; 2],
+  /// price of a one hour booking
+  pub base_price: U128,
+  pub status: ResourceStatus,
+  pub visibility: Visibility,
+  pub active_window: ActiveWindow,
+  /// average time the owner takes to answer booking requests
+  pub avg_response_ms: Option<u64>,
+  pub unanswered_requests: u64,
+  /// see `Reliability::score_bps`
+  pub reliability_bps: Option<u16>,
+}
+
+impl Resource {
+  pub(crate) fn summary(&self) -> ResourceSummary {
+    ResourceSummary {
+      title: self.title.clone(),
+      tags: self.tags.to_vec(),
+      category: self.category.clone(),
+      coordinates: self.coordinates,
+      base_price: U128::from(self.base_price_yocto()),
+      status: self.status,
+      visibility: self.visibility,
+      active_window: self.active_window,
+      avg_response_ms: self.response_stats.average_response_ms(),
+      unanswered_requests: self.response_stats.unanswered(),
+      reliability_bps: self.reliability().score_bps,
+    }
+  }
+
+  /// Must be called after every change of a field in `ResourceSummary`.
+  pub(crate) fn report_update(&mut self) {
+    let args = serde_json::json!({ "summary": self.summary() });
+    Promise::new(self.factory.parse().unwrap()).function_call(
+      "report_update".to_string(),
+      args.to_string().into_bytes(),
+      0,
+      REPORT_GAS,
+    );
+    self.announce();
+  }
+
+  /// Emits the current summary and registers it with the indexer, if configured.
+  pub(crate) fn announce(&mut self) {
+    let summary = self.summary();
+    if let Some(indexer_account) = &self.indexer_account {
+      Promise::new(indexer_account.clone()).function_call(
+        "register".to_string(),
+        serde_json::json!({ "summary": summary }).to_string().into_bytes(),
+        0,
+        REPORT_GAS,
+      );
+    }
+    self.emit("SummaryAnnouncement", summary);
+  }
+
+  pub(crate) fn set_status(&mut self, status: ResourceStatus) {
+    assert!(self.status != status, "status unchanged");
+    self.status = status;
+    self.record_admin_action(AdminAction::StatusChange { status });
+    self.emit("StatusChange", status);
+    self.report_update();
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn get_summary(&self) -> ResourceSummary {
+    self.summary()
+  }
+
+  /// Stops accepting new bookings, existing ones are not affected.
+  pub fn pause(&mut self) {
+    self.assert_owner();
+    assert!(self.status != ResourceStatus::Suspended, "resource is suspended");
+    assert!(self.status != ResourceStatus::Draft, "resource is a draft");
+    self.set_status(ResourceStatus::Paused);
+  }
+
+  pub fn unpause(&mut self) {
+    self.assert_owner();
+    assert!(self.status != ResourceStatus::Suspended, "resource is suspended");
+    assert!(self.status != ResourceStatus::Draft, "resource is a draft");
+    assert!(self.legacy_bookings_from.is_none(), "bookings are still being migrated");
+    self.set_status(ResourceStatus::Active);
+  }
+
+  pub fn suspend(&mut self) {
+    self.assert_factory();
+    self.set_status(ResourceStatus::Suspended);
+  }
+
+  /// Set by the factory when its indexer changes, `None` stops registering.
+  pub fn set_indexer_account(&mut self, indexer_account: Option<AccountId>) {
+    self.assert_factory();
+    self.indexer_account = indexer_account;
+    self.announce();
+  }
+
+  pub fn get_indexer_account(&self) -> Option<AccountId> {
+    self.indexer_account.clone()
+  }
+
+  pub fn reinstate(&mut self) {
+    self.assert_factory();
+    assert!(self.status == ResourceStatus::Suspended, "resource is not suspended");
+    self.set_status(ResourceStatus::Active);
+  }
+}
@@ -0,0 +1,147 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, Promise};
+
+use crate::rates::to_yocto;
+use crate::{ExchangeRate, Resource, ResourceExt, MS_PER_DAY};
+
+/// Terms for moving an existing booking to another slot.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct ModificationPolicy {
+  /// rescheduling is free if requested at least this long before the start
+  pub free_until_ms_before_start: u64,
+  /// fee on the originally paid price for later reschedules
+  pub fee_bps: u16,
+  /// no reschedules at all this close to the start
+  pub cutoff_ms_before_start: u64,
+}
+
+impl Default for ModificationPolicy {
+  fn default() -> Self {
+    Self {
+      free_until_ms_before_start: 2 * MS_PER_DAY,
+      fee_bps: 1000,
+      cutoff_ms_before_start: 0,
+    }
+  }
+}
+
+impl ModificationPolicy {
+  pub fn get_fee(&self, price_payed: u128, start: u64, now: u64) -> u128 {
+    assert!(
+      now + self.cutoff_ms_before_start < start,
+      "too late to modify this booking"
+    );
+    if now + self.free_until_ms_before_start <= start {
+      0
+    } else {
+      price_payed * self.fee_bps as u128 / 10_000
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize)]
+struct BookingRescheduleLog {
+  id: U128,
+  start: u64,
+  end: u64,
+  price: U128,
+  fee: U128,
+  refund_amount: U128,
+  exchange_rate: Option<ExchangeRate>,
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn get_modification_policy(&self) -> ModificationPolicy {
+    self.modification_policy.clone()
+  }
+
+  /// Moves a booking to a new slot. The old payment is credited, the new price plus the
+  /// modification fee charged, and whatever is left over of payment and attached deposit refunded.
+  #[payable]
+  pub fn reschedule_booking(&mut self, booking_id: u128, new_start: u64, new_end: u64) {
+    let mut booking = self.get_active_booking(booking_id);
+    assert!(
+      booking.consumer_account_id.eq(&env::signer_account_id().to_string()),
+      "not your booking"
+    );
+    assert!(!booking.is_checked_in(), "already checked in");
+    assert!(self.invoices.get(&booking_id).is_none(), "pay the open invoice first");
+    assert!(new_end > new_start, "end before start");
+    assert!(new_end - new_start >= self.min_duration_ms);
+    assert!(self.active_window.contains(new_start, new_end), "booking is outside the season");
+    self.assert_no_force_majeure(new_start, new_end);
+    self.assert_booking_phase_open(&booking.consumer_account_id, new_start);
+    self.pricing.assert_valid_interval(new_start, new_end);
+    self.pricing.assert_within_advance_window(new_start, Self::now_ms());
+    let fee = booking.policy.modification.get_fee(booking.price, booking.start, Self::now_ms());
+
+    let was_blocking = self.is_blocking(booking_id, &booking);
+    self.remove_blockers(booking_id, &booking);
+    self.assert_no_booking_collision(new_start, new_end);
+
+    // a package booking stays on its credit if the package covers the new slot,
+    // otherwise the credit goes back and the new slot is paid for
+    let package_id = booking.package_id
+      .filter(|package_id| self.package_covers(&booking.consumer_account_id, *package_id, new_end - new_start));
+    let exchange_rate = self.current_exchange_rate();
+    let list_price = if package_id.is_some() {
+      0
+    } else {
+      to_yocto(self.pricing.get_price(new_start, new_end), exchange_rate.as_ref())
+    };
+    let (campaign_price, campaign_id) = self.campaign_price(new_start, list_price);
+    let new_price = campaign_price - self.last_minute_discount(new_start, campaign_price);
+    let new_commitment_deposit = to_yocto(
+      self.pricing.get_commitment_deposit(new_start, new_end),
+      exchange_rate.as_ref(),
+    );
+    let credit = booking.price - booking.refunded + booking.commitment_deposit + env::attached_deposit();
+    let due = new_price + new_commitment_deposit + fee;
+    assert!(
+      credit >= due,
+      "price: {}, deposit: {}, fee: {}, available: {}",
+      new_price,
+      new_commitment_deposit,
+      fee,
+      credit
+    );
+
+    booking.start = new_start;
+    booking.end = new_end;
+    booking.price = new_price;
+    booking.campaign_id = campaign_id;
+    booking.commitment_deposit = new_commitment_deposit;
+    booking.refunded = 0;
+    if let (Some(old_package_id), None) = (booking.package_id, package_id) {
+      self.return_package_credit(&booking.consumer_account_id, old_package_id);
+    }
+    booking.package_id = package_id;
+    booking.exchange_rate = exchange_rate.clone();
+    if was_blocking {
+      self.add_blockers(booking_id, &mut booking);
+    } else {
+      self.unblocked_requests.insert(&booking_id);
+    }
+    self.store_booking(booking_id, &booking);
+    if new_price > 0 {
+      self.payouts_due.insert(&booking_id, &());
+    }
+
+    let refund_amount = credit - due;
+    self.emit("BookingReschedule", &BookingRescheduleLog {
+      id: U128::from(booking_id),
+      start: new_start,
+      end: new_end,
+      price: U128::from(new_price),
+      fee: U128::from(fee),
+      refund_amount: U128::from(refund_amount),
+      exchange_rate,
+    });
+    if refund_amount > 0 {
+      Promise::new(booking.consumer_account_id.parse().unwrap()).transfer(refund_amount);
+    }
+  }
+}
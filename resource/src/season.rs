@@ -0,0 +1,42 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+/// The season of a resource, unbounded on a side that is `None`. Bookings have to
+/// lie within it and the factory only lists the resource while it is open.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ActiveWindow {
+  pub open_from: Option<u64>,
+  pub open_until: Option<u64>,
+}
+
+impl ActiveWindow {
+  pub fn is_open(&self, now: u64) -> bool {
+    self.open_from.is_none_or(|open_from| now >= open_from)
+      && self.open_until.is_none_or(|open_until| now < open_until)
+  }
+
+  pub fn contains(&self, start: u64, end: u64) -> bool {
+    self.open_from.is_none_or(|open_from| start >= open_from)
+      && self.open_until.is_none_or(|open_until| end <= open_until)
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn set_active_window(&mut self, open_from: Option<u64>, open_until: Option<u64>) {
+    self.assert_owner();
+    if let (Some(open_from), Some(open_until)) = (open_from, open_until) {
+      assert!(open_until > open_from, "window closes before it opens");
+    }
+    self.active_window = ActiveWindow { open_from, open_until };
+    self.record_admin_action(AdminAction::ActiveWindowChange { window: self.active_window });
+    self.report_update();
+  }
+
+  pub fn get_active_window(&self) -> ActiveWindow {
+    self.active_window
+  }
+}
@@ -0,0 +1,103 @@
+// Seasonal rates let the owner charge differently for date ranges, e.g. high
+// season or holidays. Bookings across several ranges pay each part at its rate.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::{ExchangeRate, Pricing};
+
+const MAX_SEASONAL_RATES: usize = 20;
+const MAX_LABEL_LENGTH: usize = 64;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone, Copy)]
+pub enum SeasonalRateKind {
+  /// scales the regular duration price, 15000 for +50%
+  Multiplier { bps: u32 },
+  /// replaces the regular duration price
+  PricePerMs { price_per_ms: U128 },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct SeasonalRate {
+  pub label: String,
+  pub start: u64,
+  pub end: u64,
+  pub rate: SeasonalRateKind,
+}
+
+/// Part of a booking charged at one rate, `label` is `None` for the regular rate.
+#[derive(Deserialize, Serialize)]
+pub struct PriceSegment {
+  pub start: u64,
+  pub end: u64,
+  pub label: Option<String>,
+  pub price: U128,
+}
+
+/// Itemized price of a booking made now.
+#[derive(Deserialize, Serialize)]
+pub struct Quote {
+  pub price_per_booking: U128,
+  pub segments: Vec<PriceSegment>,
+  /// price per booking plus the segments
+  pub gross: U128,
+  pub duration_discount: U128,
+  /// campaign and last minute discounts
+  pub discount: U128,
+  pub total: U128,
+  /// rate the amounts were converted at, for resources priced in another currency
+  pub exchange_rate: Option<ExchangeRate>,
+}
+
+/// Sorts the rates by start and rejects overlapping ones.
+pub(crate) fn sorted_seasonal_rates(mut rates: Vec<SeasonalRate>) -> Vec<SeasonalRate> {
+  assert!(rates.len() <= MAX_SEASONAL_RATES, "too many seasonal rates");
+  for rate in rates.iter() {
+    assert!(rate.end > rate.start, "seasonal rate ends before it starts");
+    assert!(rate.label.len() <= MAX_LABEL_LENGTH, "seasonal rate label too long");
+  }
+  rates.sort_by_key(|rate| rate.start);
+  assert!(rates.windows(2).all(|pair| pair[0].end <= pair[1].start), "seasonal rates overlap");
+  rates
+}
+
+impl Pricing {
+  /// The regular duration price split at the seasonal rates it overlaps, with
+  /// each part charged at its rate. The regular part of a segment is its share
+  /// of the regular price by time, or its weekday rates. Bookings matching a duration preset pay the
+  /// preset price as one segment.
+  pub fn get_price_segments(&self, from: u64, until: u64) -> Vec<PriceSegment> {
+    if let Some(preset) = self.preset(from, until) {
+      return vec![PriceSegment { start: from, end: until, label: Some(preset.label.clone()), price: preset.price }];
+    }
+    let duration_price = self.get_duration_price(from, until);
+    let regular = |start: u64, end: u64| {
+      if let Some(weekday_rates) = &self.weekday_rates {
+        weekday_rates.price(start, end)
+      } else if start == from && end == until {
+        duration_price
+      } else {
+        self.rounding.div(duration_price * (end - start) as u128, (until - from) as u128)
+      }
+    };
+    let mut segments = Vec::new();
+    let mut cursor = from;
+    for rate in self.seasonal_rates.iter().filter(|rate| rate.start < until && rate.end > from) {
+      let (start, end) = (rate.start.max(from), rate.end.min(until));
+      if cursor < start {
+        segments.push(PriceSegment { start: cursor, end: start, label: None, price: U128::from(regular(cursor, start)) });
+      }
+      let price = match rate.rate {
+        SeasonalRateKind::Multiplier { bps } => self.rounding.div(regular(start, end) * bps as u128, 10_000),
+        SeasonalRateKind::PricePerMs { price_per_ms } => (end - start) as u128 * price_per_ms.0,
+      };
+      segments.push(PriceSegment { start, end, label: Some(rate.label.clone()), price: U128::from(price) });
+      cursor = end;
+    }
+    if cursor < until {
+      segments.push(PriceSegment { start: cursor, end: until, label: None, price: U128::from(regular(cursor, until)) });
+    }
+    segments
+  }
+}
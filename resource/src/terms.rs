@@ -0,0 +1,81 @@
+// Versioned terms of the resource. The documents live off chain, the contract
+// keeps their hashes and when each version took effect, so every booking can
+// prove the terms it was made under.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::near_bindgen;
+
+use chershare_common::pagination::{decode_cursor, paginate, Page};
+
+use crate::{AdminAction, Resource, ResourceExt};
+
+const MAX_TERMS_PAGE_SIZE: u64 = 50;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct TermsVersion {
+  pub version: u64,
+  pub content_hash: String,
+  pub effective_from: u64,
+  pub published_at: u64,
+}
+
+impl Resource {
+  /// The latest version in effect, `None` before any terms took effect.
+  pub(crate) fn current_terms_version(&self) -> Option<u64> {
+    let now = Self::now_ms();
+    (0..self.terms.len())
+      .rev()
+      .find(|version| self.terms.get(*version).is_some_and(|terms| terms.effective_from <= now))
+  }
+
+  /// Bookers have to name the terms in effect, so nobody books under terms they
+  /// haven't seen.
+  pub(crate) fn assert_current_terms(&self, terms_version: Option<u64>) {
+    let current = self.current_terms_version();
+    assert!(
+      terms_version == current,
+      "terms version {:?} is not the one in effect, which is {:?}",
+      terms_version,
+      current
+    );
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Adds a new version of the terms taking effect at `effective_from`, which can't
+  /// be in the past or before the latest version's. Returns the version number.
+  pub fn publish_terms(&mut self, content_hash: String, effective_from: u64) -> u64 {
+    self.assert_owner();
+    assert!(!content_hash.is_empty() && content_hash.len() <= 128, "invalid content hash");
+    let now = Self::now_ms();
+    assert!(effective_from >= now, "terms can't take effect in the past");
+    if let Some(latest) = self.terms.len().checked_sub(1).and_then(|version| self.terms.get(version)) {
+      assert!(effective_from >= latest.effective_from, "terms must take effect after the latest version");
+    }
+    let version = self.terms.len();
+    let terms = TermsVersion { version, content_hash, effective_from, published_at: now };
+    self.terms.push(&terms);
+    self.record_admin_action(AdminAction::TermsPublished { version });
+    self.emit("TermsPublished", &terms);
+    version
+  }
+
+  pub fn get_current_terms(&self) -> Option<TermsVersion> {
+    self.current_terms_version().and_then(|version| self.terms.get(version))
+  }
+
+  pub fn get_terms(&self, version: u64) -> Option<TermsVersion> {
+    self.terms.get(version)
+  }
+
+  /// All versions including superseded and upcoming ones, oldest first,
+  /// continuing after `cursor` of the previous page.
+  pub fn get_terms_versions(&self, cursor: Option<String>, limit: u64) -> Page<TermsVersion> {
+    let from = cursor.map_or(0, |cursor| decode_cursor::<u64>(&cursor) + 1);
+    let entries = (from..self.terms.len())
+      .filter_map(|version| Some((version, self.terms.get(version)?)));
+    paginate(entries, limit.min(MAX_TERMS_PAGE_SIZE) as usize)
+  }
+}
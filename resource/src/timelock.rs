@@ -0,0 +1,181 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::invoicing::MAX_LATE_INTEREST_BPS_PER_DAY;
+use crate::usage::MAX_DEPOSIT_RELEASE_MS;
+use crate::{
+  AdminAction, ModificationPolicy, OverbookingPolicy, PriceCurrency, Pricing, PricingParams, Resource,
+  ResourceExt, UsagePricing,
+};
+
+/// Owner changes that affect what bookers pay or are owed. They are announced
+/// first and only applied after `timelock_ms`, so bookers can still cancel
+/// under the old terms.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub enum SensitiveChange {
+  Pricing(Box<PricingParams>),
+  ApprovalTimeout(u64),
+  GracePeriod(u64),
+  ModificationPolicy(ModificationPolicy),
+  OverbookingPolicy(OverbookingPolicy),
+  Beneficiary(String),
+  Timelock(u64),
+  /// in basis points per day, for invoices opened afterwards
+  LateInterest(u16),
+  PriceCurrency(Option<PriceCurrency>),
+  UsagePricing(Option<UsagePricing>),
+  DepositReleasePeriod(u64),
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct ScheduledChange {
+  pub change: SensitiveChange,
+  pub effective_at: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ScheduledChangeLog {
+  id: u64,
+  change: SensitiveChange,
+  effective_at: u64,
+}
+
+#[near_bindgen]
+impl Resource {
+  pub fn schedule_change(&mut self, change: SensitiveChange) -> u64 {
+    self.assert_owner();
+    // validate now rather than failing when the change is applied
+    match &change {
+      SensitiveChange::Pricing(params) => {
+        Pricing::new(*params.clone());
+      },
+      SensitiveChange::Beneficiary(beneficiary) => {
+        assert!(
+          env::is_valid_account_id(beneficiary.as_bytes()),
+          "invalid beneficiary account"
+        );
+      },
+      SensitiveChange::LateInterest(bps_per_day) => {
+        assert!(*bps_per_day <= MAX_LATE_INTEREST_BPS_PER_DAY, "late interest too high");
+      },
+      SensitiveChange::PriceCurrency(Some(price_currency)) => {
+        assert!(
+          env::is_valid_account_id(price_currency.feed.as_bytes()),
+          "invalid feed account"
+        );
+      },
+      SensitiveChange::DepositReleasePeriod(deposit_release_ms) => {
+        assert!(*deposit_release_ms <= MAX_DEPOSIT_RELEASE_MS, "release period above 30 days");
+      },
+      _ => {},
+    }
+    let id = self.next_change_id;
+    self.next_change_id += 1;
+    let effective_at = Self::now_ms() + self.timelock_ms;
+    self.scheduled_changes.insert(&id, &ScheduledChange {
+      change: change.clone(),
+      effective_at,
+    });
+    self.record_admin_action(AdminAction::ChangeScheduled { id });
+    self.emit("ChangeScheduled", &ScheduledChangeLog {
+      id,
+      change,
+      effective_at,
+    });
+    id
+  }
+
+  pub fn cancel_scheduled_change(&mut self, id: u64) {
+    self.assert_owner();
+    self.scheduled_changes.remove(&id).expect("change not found");
+    self.record_admin_action(AdminAction::ChangeCancelled { id });
+    self.emit("ChangeCancelled", id);
+  }
+
+  /// Anyone can apply a change once its timelock has passed.
+  pub fn apply_change(&mut self, id: u64) {
+    let scheduled = self.scheduled_changes.get(&id).expect("change not found");
+    assert!(
+      Self::now_ms() >= scheduled.effective_at,
+      "change is still timelocked"
+    );
+    self.scheduled_changes.remove(&id);
+    match scheduled.change.clone() {
+      SensitiveChange::Pricing(params) => self.apply_pricing(*params),
+      SensitiveChange::ApprovalTimeout(timeout_ms) => self.approval_timeout_ms = timeout_ms,
+      SensitiveChange::GracePeriod(grace_period_ms) => self.grace_period_ms = grace_period_ms,
+      SensitiveChange::ModificationPolicy(policy) => self.modification_policy = policy,
+      SensitiveChange::OverbookingPolicy(policy) => {
+        // requests that don't hold their slot would be confirmed as if they did
+        assert!(
+          policy == OverbookingPolicy::FirstApprovedWins || self.unblocked_requests.is_empty(),
+          "approve or reject the pending requests first"
+        );
+        self.overbooking_policy = policy;
+      },
+      SensitiveChange::Beneficiary(beneficiary) => self.beneficiary = beneficiary,
+      SensitiveChange::Timelock(timelock_ms) => self.timelock_ms = timelock_ms,
+      SensitiveChange::LateInterest(bps_per_day) => self.apply_late_interest(bps_per_day),
+      SensitiveChange::PriceCurrency(price_currency) => self.apply_price_currency(price_currency),
+      SensitiveChange::UsagePricing(usage_pricing) => self.apply_usage_pricing(usage_pricing),
+      SensitiveChange::DepositReleasePeriod(deposit_release_ms) => self.apply_deposit_release_period(deposit_release_ms),
+    }
+    self.record_admin_action(AdminAction::ChangeApplied { id });
+    self.emit("ChangeApplied", &ScheduledChangeLog {
+      id,
+      change: scheduled.change,
+      effective_at: scheduled.effective_at,
+    });
+  }
+
+  pub fn get_scheduled_changes(&self) -> Vec<(u64, ScheduledChange)> {
+    self.scheduled_changes.to_vec()
+  }
+
+  pub fn get_timelock_ms(&self) -> u64 {
+    self.timelock_ms
+  }
+
+  pub fn get_beneficiary(&self) -> String {
+    self.beneficiary.clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use near_sdk::test_utils::VMContextBuilder;
+  use near_sdk::testing_env;
+
+  use super::*;
+  use crate::tests::resource;
+
+  #[test]
+  fn late_interest_applies_once_the_timelock_passed() {
+    let mut resource = resource(1);
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .build());
+    let id = resource.set_late_interest(50);
+    assert_eq!(resource.get_late_interest(), 0);
+    testing_env!(VMContextBuilder::new()
+      .block_timestamp(resource.timelock_ms * 1_000_000)
+      .build());
+    resource.apply_change(id);
+    assert_eq!(resource.get_late_interest(), 50);
+  }
+
+  #[test]
+  #[should_panic(expected = "change is still timelocked")]
+  fn change_cant_be_applied_early() {
+    let mut resource = resource(1);
+    testing_env!(VMContextBuilder::new()
+      .predecessor_account_id("owner.near".parse().unwrap())
+      .build());
+    let id = resource.schedule_change(SensitiveChange::GracePeriod(0));
+    testing_env!(VMContextBuilder::new()
+      .block_timestamp((resource.timelock_ms - 1) * 1_000_000)
+      .build());
+    resource.apply_change(id);
+  }
+}
@@ -0,0 +1,54 @@
+// Conversions between yoctoNEAR amounts and human readable NEAR strings.
+// Frontends kept getting the rounding wrong when dividing by 10^24 in floats,
+// so the contract hands out an already formatted representation.
+
+pub const YOCTO_PER_NEAR: u128 = 10u128.pow(24);
+pub const NEAR_DISPLAY_DECIMALS: u32 = 5;
+
+const NEAR_DECIMALS: u32 = 24;
+
+/// Formats a yoctoNEAR amount as NEAR with `NEAR_DISPLAY_DECIMALS` decimals,
+/// rounding half up, e.g. `1_234_567_000_000_000_000_000_000` -> `"1.23457"`.
+pub fn yocto_to_near_string(amount: u128) -> String {
+  let unit = 10u128.pow(NEAR_DECIMALS - NEAR_DISPLAY_DECIMALS);
+  let rounded = amount / unit + if amount % unit >= unit / 2 { 1 } else { 0 };
+  let scale = 10u128.pow(NEAR_DISPLAY_DECIMALS);
+  format!(
+    "{}.{:0width$}",
+    rounded / scale,
+    rounded % scale,
+    width = NEAR_DISPLAY_DECIMALS as usize
+  )
+}
+
+/// Parses a decimal NEAR string (`"1"`, `"0.5"`, `"12.000001"`) into yoctoNEAR.
+/// Panics on malformed input or more than 24 fractional digits.
+pub fn near_string_to_yocto(amount: &str) -> u128 {
+  let (whole, fraction) = match amount.split_once('.') {
+    Some((whole, fraction)) => (whole, fraction),
+    None => (amount, ""),
+  };
+  assert!(
+    !whole.is_empty() || !fraction.is_empty(),
+    "invalid NEAR amount"
+  );
+  assert!(
+    fraction.len() <= NEAR_DECIMALS as usize,
+    "too many decimals in NEAR amount"
+  );
+  let parse = |digits: &str| -> u128 {
+    if digits.is_empty() {
+      return 0;
+    }
+    assert!(
+      digits.bytes().all(|b| b.is_ascii_digit()),
+      "invalid NEAR amount"
+    );
+    digits.parse().expect("NEAR amount out of range")
+  };
+  let fraction_yocto = parse(fraction) * 10u128.pow(NEAR_DECIMALS - fraction.len() as u32);
+  parse(whole)
+    .checked_mul(YOCTO_PER_NEAR)
+    .and_then(|yocto| yocto.checked_add(fraction_yocto))
+    .expect("NEAR amount out of range")
+}
@@ -0,0 +1,219 @@
+// Surcharges for what was consumed during a booking, e.g. kilometres driven. The
+// commitment deposit is held as security from check-in until the usage is
+// settled, the booker can dispute a report and the factory arbitrates.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, Promise};
+
+use crate::{AdminAction, Booking, BookingStatus, Resource, ResourceExt, SensitiveChange, MS_PER_DAY};
+
+/// How long the booker has to dispute a usage report.
+const USAGE_DISPUTE_WINDOW_MS: u64 = 3 * MS_PER_DAY;
+/// Default for how long the owner has to report usage after check-out.
+pub(crate) const DEFAULT_DEPOSIT_RELEASE_MS: u64 = 3 * MS_PER_DAY;
+pub(crate) const MAX_DEPOSIT_RELEASE_MS: u64 = 30 * MS_PER_DAY;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct UsagePricing {
+  pub unit_price: U128,
+  /// may report usage besides the owner, e.g. a telematics oracle
+  pub reporter: Option<String>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct UsageReport {
+  pub units: u64,
+  /// capped at the held deposit
+  pub surcharge: U128,
+  pub reported_at: u64,
+  pub disputed: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct UsageReportLog {
+  id: U128,
+  units: u64,
+  surcharge: U128,
+}
+
+#[derive(Deserialize, Serialize)]
+struct UsageSettlementLog {
+  id: U128,
+  surcharge: U128,
+  returned_deposit: U128,
+}
+
+impl Resource {
+  /// Whether check-in keeps the commitment deposit as security.
+  pub(crate) fn holds_deposits(&self) -> bool {
+    self.usage_pricing.is_some()
+  }
+
+  /// When a held deposit without usage report goes back to the booker, counted
+  /// from check-out or the end of the booking.
+  fn deposit_release_at(&self, booking: &Booking) -> u64 {
+    booking.checked_out_at.unwrap_or(booking.end) + self.deposit_release_ms
+  }
+
+  /// Pays the surcharge to the beneficiary and the rest of the held deposit back.
+  fn release_deposit(&mut self, booking_id: u128, surcharge: u128) {
+    let mut booking = self.bookings.get(&booking_id).unwrap();
+    let returned_deposit = booking.commitment_deposit - surcharge;
+    booking.commitment_deposit = 0;
+    booking.deposit_held = false;
+    self.store_booking(booking_id, &booking);
+    self.held_deposits.remove(&booking_id);
+    self.emit("UsageSettlement", UsageSettlementLog {
+      id: U128::from(booking_id),
+      surcharge: U128::from(surcharge),
+      returned_deposit: U128::from(returned_deposit),
+    });
+    if surcharge > 0 {
+      Promise::new(self.beneficiary.parse().unwrap()).transfer(surcharge);
+    }
+    if returned_deposit > 0 {
+      Promise::new(booking.consumer_account_id.parse().unwrap()).transfer(returned_deposit);
+    }
+  }
+}
+
+#[near_bindgen]
+impl Resource {
+  /// Schedules usage pricing for the time after the timelock, `None` turns usage
+  /// surcharges off for bookings checking in from then on.
+  pub fn set_usage_pricing(&mut self, usage_pricing: Option<UsagePricing>) -> u64 {
+    self.schedule_change(SensitiveChange::UsagePricing(usage_pricing))
+  }
+
+  pub(crate) fn apply_usage_pricing(&mut self, usage_pricing: Option<UsagePricing>) {
+    self.usage_pricing = usage_pricing.clone();
+    self.record_admin_action(AdminAction::UsagePricingChange { usage_pricing });
+  }
+
+  pub fn get_usage_pricing(&self) -> Option<UsagePricing> {
+    self.usage_pricing.clone()
+  }
+
+  /// Schedules how long after check-out the owner can still report usage before
+  /// the held deposit can be released to the booker by anyone.
+  pub fn set_deposit_release_period(&mut self, deposit_release_ms: u64) -> u64 {
+    self.schedule_change(SensitiveChange::DepositReleasePeriod(deposit_release_ms))
+  }
+
+  pub(crate) fn apply_deposit_release_period(&mut self, deposit_release_ms: u64) {
+    self.deposit_release_ms = deposit_release_ms;
+    self.record_admin_action(AdminAction::DepositReleasePeriodChange { deposit_release_ms });
+  }
+
+  pub fn get_deposit_release_period(&self) -> u64 {
+    self.deposit_release_ms
+  }
+
+  /// Reports what a checked in booking consumed, once. The surcharge is taken
+  /// from the held deposit unless the booker disputes it in time.
+  pub fn report_usage(&mut self, booking_id: U128, units: u64) {
+    let usage_pricing = self.usage_pricing.clone().expect("usage pricing is off");
+    let caller = env::predecessor_account_id().to_string();
+    assert!(
+      caller == self.owner || usage_pricing.reporter.as_ref() == Some(&caller),
+      "only the owner or the usage reporter can do this"
+    );
+    let booking = self.bookings.get(&booking_id.0).expect("booking not found");
+    assert!(booking.deposit_held, "no deposit held for this booking");
+    assert!(self.usage_reports.get(&booking_id.0).is_none(), "usage already reported");
+    assert!(Self::now_ms() < self.deposit_release_at(&booking), "deposit release period is over");
+    let surcharge = (units as u128 * usage_pricing.unit_price.0).min(booking.commitment_deposit);
+    self.usage_reports.insert(&booking_id.0, &UsageReport {
+      units,
+      surcharge: U128::from(surcharge),
+      reported_at: Self::now_ms(),
+      disputed: false,
+    });
+    self.emit("UsageReport", UsageReportLog {
+      id: booking_id,
+      units,
+      surcharge: U128::from(surcharge),
+    });
+  }
+
+  pub fn get_usage_report(&self, booking_id: U128) -> Option<UsageReport> {
+    self.usage_reports.get(&booking_id.0)
+  }
+
+  /// The booker contests a usage report, the factory decides with `resolve_usage_dispute`.
+  pub fn dispute_usage(&mut self, booking_id: U128) {
+    let mut booking = self.bookings.get(&booking_id.0).expect("booking not found");
+    assert!(
+      booking.consumer_account_id.eq(&env::signer_account_id().to_string()),
+      "not your booking"
+    );
+    let mut report = self.usage_reports.get(&booking_id.0).expect("no usage reported");
+    assert!(booking.deposit_held && !report.disputed, "usage is already settled or disputed");
+    // a paid out booking can't be held back anymore
+    assert!(booking.status == BookingStatus::CheckedIn, "booking can't be disputed");
+    assert!(Self::now_ms() < report.reported_at + USAGE_DISPUTE_WINDOW_MS, "dispute window is over");
+    assert!(self.condition_evidence(booking_id.0).booker_post, "file a post-booking condition report first");
+    report.disputed = true;
+    self.usage_reports.insert(&booking_id.0, &report);
+    booking.set_status(BookingStatus::Disputed);
+    self.store_booking(booking_id.0, &booking);
+    self.emit("UsageDispute", serde_json::json!({ "id": booking_id }));
+  }
+
+  /// Releases a held deposit once the dispute window of its report passed, or
+  /// in full once the owner let the release period pass without a report.
+  pub fn settle_usage(&mut self, booking_id: U128) {
+    let booking = self.bookings.get(&booking_id.0).expect("booking not found");
+    assert!(booking.deposit_held, "no deposit held for this booking");
+    let now = Self::now_ms();
+    let surcharge = match self.usage_reports.get(&booking_id.0) {
+      Some(report) => {
+        assert!(!report.disputed, "usage is disputed");
+        assert!(now >= report.reported_at + USAGE_DISPUTE_WINDOW_MS, "dispute window is still open");
+        report.surcharge.0
+      },
+      None => {
+        assert!(now >= self.deposit_release_at(&booking), "usage can still be reported");
+        0
+      },
+    };
+    self.release_deposit(booking_id.0, surcharge);
+  }
+
+  /// Returns a held deposit in full to the booker when the owner neither reported
+  /// usage nor released it within the release period after check-out. Anyone
+  /// can call this, so deposits don't depend on an active owner.
+  pub fn auto_release_deposit(&mut self, booking_id: U128) {
+    let booking = self.bookings.get(&booking_id.0).expect("booking not found");
+    assert!(booking.deposit_held, "no deposit held for this booking");
+    assert!(self.usage_reports.get(&booking_id.0).is_none(), "usage was reported, use settle_usage");
+    assert!(Self::now_ms() >= self.deposit_release_at(&booking), "deposit release period is still running");
+    self.release_deposit(booking_id.0, 0);
+  }
+
+  /// Settles a disputed report with the surcharge the factory decided on, and
+  /// pays out the booking the dispute held back. An owner without a post-booking
+  /// condition report, or whose report matches the booker's, gets no surcharge.
+  pub fn resolve_usage_dispute(&mut self, booking_id: U128, surcharge: U128) -> Promise {
+    self.assert_factory();
+    let booking = self.bookings.get(&booking_id.0).expect("booking not found");
+    let mut report = self.usage_reports.get(&booking_id.0).expect("no usage reported");
+    assert!(booking.status == BookingStatus::Disputed && report.disputed, "usage is not disputed");
+    assert!(!booking.paid_out, "booking is already paid out");
+    assert!(Self::now_ms() >= booking.end, "booking has not ended yet");
+    assert!(surcharge.0 <= report.surcharge.0, "surcharge above the reported one");
+    let evidence = self.condition_evidence(booking_id.0);
+    if !evidence.owner_post || evidence.post_matches == Some(true) {
+      assert!(surcharge.0 == 0, "the owner has no condition evidence for a surcharge");
+    }
+    report.surcharge = surcharge;
+    report.disputed = false;
+    self.usage_reports.insert(&booking_id.0, &report);
+    self.release_deposit(booking_id.0, surcharge.0);
+    let amount = booking.price - booking.refunded;
+    self.complete_booking(booking_id.0, amount);
+    self.pay_out(1, amount)
+  }
+}
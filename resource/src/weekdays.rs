@@ -0,0 +1,54 @@
+// Rates per day of the week, e.g. higher prices on weekends. The booked interval
+// is walked day by day in the owner's time zone and each part is charged at the
+// rate of its day.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::MS_PER_DAY;
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Clone)]
+pub struct WeekdayRates {
+  pub utc_offset_minutes: i16,
+  /// Monday to Friday
+  pub weekday_price_per_ms: U128,
+  /// Saturday and Sunday
+  pub weekend_price_per_ms: U128,
+  /// seven prices, Monday first, replace the two above
+  #[serde(default)]
+  pub per_day_price_per_ms: Option<Vec<U128>>,
+}
+
+impl WeekdayRates {
+  pub(crate) fn assert_valid(&self) {
+    if let Some(prices) = &self.per_day_price_per_ms {
+      assert!(prices.len() == 7, "per day prices need one price for each day of the week");
+    }
+  }
+
+  /// `weekday` counts from Monday.
+  fn price_per_ms(&self, weekday: usize) -> u128 {
+    match &self.per_day_price_per_ms {
+      Some(prices) => prices[weekday].0,
+      None if weekday >= 5 => self.weekend_price_per_ms.0,
+      None => self.weekday_price_per_ms.0,
+    }
+  }
+
+  pub fn price(&self, from: u64, until: u64) -> u128 {
+    let offset_ms = self.utc_offset_minutes as i64 * 60 * 1000;
+    let mut price = 0;
+    let mut cursor = from;
+    while cursor < until {
+      let local_day = (cursor as i64 + offset_ms).div_euclid(MS_PER_DAY as i64);
+      let day_end = ((local_day + 1) * MS_PER_DAY as i64 - offset_ms) as u64;
+      let end = day_end.min(until);
+      // 1970-01-01 was a Thursday
+      let weekday = (local_day + 3).rem_euclid(7) as usize;
+      price += (end - cursor) as u128 * self.price_per_ms(weekday);
+      cursor = end;
+    }
+    price
+  }
+}